@@ -0,0 +1,126 @@
+// Memory taken by a million positions as SolitareState and as the compact
+// encoding the solver hashes, with the time to encode, decode and hash them.
+// Encoding and hashing include sorting the slots, which the Zobrist hash the
+// solver uses does without. Positions come from random play of deals of each
+// game.
+//
+//   cargo bench --bench encoding
+
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    hint::black_box,
+    mem::size_of,
+    time::Instant,
+};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use solitare::{Rules, SolitareState, rules::Game};
+
+const DEALS: u64 = 200;
+const MOVES: usize = 150; // Random moves played from each deal
+
+fn positions(rules: Rules) -> Vec<SolitareState> {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let mut positions = Vec::new();
+
+    for seed in 0..DEALS {
+        let mut state = SolitareState::new(seed, rules);
+        positions.push(state);
+
+        for _ in 0..MOVES {
+            let moves = state.legal_moves();
+            if moves.is_empty() {
+                break;
+            }
+            let m = moves[rng.random_range(0..moves.len())];
+            state.apply_move(m).unwrap();
+            positions.push(state);
+        }
+    }
+
+    positions
+}
+
+fn hash(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Nanoseconds per position
+fn time(positions: &[SolitareState], f: impl Fn(&SolitareState)) -> f64 {
+    let start = Instant::now();
+    for state in positions {
+        f(state);
+    }
+    start.elapsed().as_nanos() as f64 / positions.len() as f64
+}
+
+fn main() {
+    let whole = size_of::<SolitareState>();
+
+    println!(
+        "{:<14}{:>10}{:>10}{:>12}{:>12}{:>12}{:>12}{:>12}",
+        "game",
+        "bytes",
+        "MB/1M",
+        "encode ns",
+        "decode ns",
+        "hash ns",
+        "state ns",
+        "zobrist ns"
+    );
+    println!(
+        "{:<14}{:>10}{:>10.0}",
+        "SolitareState",
+        whole,
+        whole as f64 // Megabytes per million positions
+    );
+
+    for game in Game::ALL {
+        let positions = positions(Rules::for_game(game));
+        let encoded: Vec<_> =
+            positions.iter().map(SolitareState::to_bytes).collect();
+
+        for (state, bytes) in positions.iter().zip(&encoded) {
+            assert_eq!(SolitareState::from_bytes(bytes).as_ref(), Ok(state));
+        }
+
+        let bytes = encoded.iter().map(Vec::len).sum::<usize>() as f64
+            / encoded.len() as f64;
+
+        let encode = time(&positions, |state| {
+            black_box(state.canonical_bytes());
+        });
+        let decode = {
+            let start = Instant::now();
+            for bytes in &encoded {
+                black_box(SolitareState::from_bytes(bytes).unwrap());
+            }
+            start.elapsed().as_nanos() as f64 / encoded.len() as f64
+        };
+        let hashed = time(&positions, |state| {
+            black_box(hash(state.canonical_bytes()));
+        });
+        // The whole state as it is, without sorting the slots
+        let hashed_whole = time(&positions, |state| {
+            black_box(hash(state));
+        });
+        let zobrist = time(&positions, |state| {
+            black_box(state.zobrist());
+        });
+
+        println!(
+            "{:<14}{:>10.1}{:>10.0}{:>12.0}{:>12.0}{:>12.0}{:>12.0}{:>12.0}",
+            game.title(),
+            bytes,
+            bytes, // Megabytes per million positions
+            encode,
+            decode,
+            hashed,
+            hashed_whole,
+            zobrist,
+        );
+    }
+}
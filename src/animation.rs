@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use solitare::{Card, SolitareState};
+
+use crate::CONFIG;
+
+// Time between the frames of an animation
+pub const STEP: Duration = Duration::from_millis(15);
+
+// Frames it takes the cards of a move to get where they go
+const FRAMES: u16 = 8;
+
+// Cards of a move on their way across the screen. The board is shown as it
+// was before the move until they arrive, without them at the place they
+// left.
+pub struct Animation {
+    pub before: SolitareState,
+    pub cards: Vec<Card>, // From the bottom one up, drawn one per line
+    pub from: (u16, u16), // Top left corner on the screen
+    pub to: (u16, u16),
+    frame: u16,
+}
+
+impl Animation {
+    pub fn new(
+        before: SolitareState,
+        cards: Vec<Card>,
+        from: (u16, u16),
+        to: (u16, u16),
+    ) -> Self {
+        Self {
+            before,
+            cards,
+            from,
+            to,
+            frame: 1,
+        }
+    }
+
+    // Where the cards are drawn in the current frame, on the straight line
+    // between where they came from and where they go
+    pub fn position(&self) -> (u16, u16) {
+        let along = |from: u16, to: u16| {
+            let distance = to as i32 - from as i32;
+            (from as i32 + distance * self.frame as i32 / FRAMES as i32) as u16
+        };
+
+        (along(self.from.0, self.to.0), along(self.from.1, self.to.1))
+    }
+
+    // Moves on to the next frame, false once the cards have arrived
+    pub fn step(&mut self) -> bool {
+        self.frame += 1;
+        self.frame < FRAMES
+    }
+}
+
+// Time between the steps of the cascade after a win
+pub const CASCADE_STEP: Duration = Duration::from_millis(15);
+
+// Card of the cascade flying through the air, in columns and rows with
+// their speed per step
+struct Thrown {
+    card: Card,
+    x: f32,
+    y: f32,
+    dx: f32,
+    dy: f32,
+}
+
+// Cards thrown off the foundations one at a time after a win, bouncing
+// along the bottom of the screen until they are out of it. The screen is
+// not cleared in between, so every card leaves a trail.
+pub struct Cascade {
+    left: Vec<(Card, (u16, u16))>, // To throw, last first, with their start
+    thrown: Option<Thrown>,
+    width: u16, // Columns of the screen, where cards leave it
+    floor: u16, // Row of the top of cards resting on the bottom
+}
+
+impl Cascade {
+    // Cards are thrown in the order given
+    pub fn new(
+        mut cards: Vec<(Card, (u16, u16))>,
+        width: u16,
+        floor: u16,
+    ) -> Self {
+        cards.reverse();
+
+        Self {
+            left: cards,
+            thrown: None,
+            width,
+            floor,
+        }
+    }
+
+    // Moves the card in the air a step, throwing the next one once it has
+    // left the screen. The card to draw and where, or None once every card
+    // is gone.
+    pub fn step(&mut self) -> Option<(Card, (u16, u16))> {
+        let (width, floor) = (self.width as f32, self.floor as f32);
+        let card_width = CONFIG.card_width() as f32;
+
+        loop {
+            let Some(thrown) = self.thrown.as_mut() else {
+                let (card, (x, y)) = self.left.pop()?;
+                let speed = rand::random_range(0.8..2.2);
+                let direction = if rand::random() { 1.0 } else { -1.0 };
+
+                self.thrown = Some(Thrown {
+                    card,
+                    x: x as f32,
+                    y: y as f32,
+                    dx: speed * direction,
+                    dy: rand::random_range(-1.5..0.0),
+                });
+                return Some((card, (x, y)));
+            };
+
+            thrown.x += thrown.dx;
+            thrown.y += thrown.dy;
+            thrown.dy += 0.25;
+
+            // Cards lose some of their speed on every bounce
+            if thrown.y > floor {
+                thrown.y = floor;
+                thrown.dy *= -0.75;
+            }
+
+            if thrown.x < 0.0 || thrown.x + card_width > width {
+                self.thrown = None;
+                continue;
+            }
+
+            return Some((thrown.card, (thrown.x as u16, thrown.y as u16)));
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use std::{
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+// Work done once on a thread of its own, like a search of the solver that
+// can take seconds, so the game goes on meanwhile
+pub struct Background<T> {
+    result: Receiver<T>,
+    done: Option<T>,
+}
+
+impl<T: Send + 'static> Background<T> {
+    pub fn start(work: impl FnOnce() -> T + Send + 'static) -> Self {
+        let (sender, result) = mpsc::channel();
+        // The result is dropped if no one waits for it any more
+        thread::spawn(move || {
+            let _ = sender.send(work());
+        });
+
+        Self { result, done: None }
+    }
+
+    // The result, once the work is done
+    pub fn get(&mut self) -> Option<&T> {
+        if self.done.is_none() {
+            self.done = self.result.try_recv().ok();
+        }
+        self.done.as_ref()
+    }
+}
@@ -0,0 +1,52 @@
+const BASE64_CHARS: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let combined = ((bytes[0] as u32) << 16)
+            | ((bytes[1] as u32) << 8)
+            | (bytes[2] as u32);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let sextet = (combined >> (18 - 6 * i)) & 0b11_1111;
+                encoded.push(BASE64_CHARS[sextet as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+// The other way around from `encode`. The padding can be left out, and
+// whitespace, as from text wrapped in a chat message, is skipped.
+pub fn decode(text: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(text.len() / 4 * 3);
+    let mut bits = 0u32;
+    let mut n_bits = 0;
+
+    for c in text.trim().trim_end_matches('=').bytes() {
+        if c.is_ascii_whitespace() {
+            continue;
+        }
+        let sextet = BASE64_CHARS.iter().position(|&b| b == c)?;
+
+        bits = (bits << 6) | sextet as u32;
+        n_bits += 6;
+        if n_bits >= 8 {
+            n_bits -= 8;
+            decoded.push((bits >> n_bits) as u8);
+        }
+    }
+
+    Some(decoded)
+}
@@ -0,0 +1,85 @@
+// Values packed bit by bit into bytes, the lowest bits first, as in the
+// compact form of positions and in replay codes
+
+#[derive(Default)]
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    bits: u64, // Bits not yet making up a whole byte
+    n_bits: u32,
+}
+
+impl BitWriter {
+    pub fn with_capacity(bytes: usize) -> Self {
+        Self {
+            bytes: Vec::with_capacity(bytes),
+            ..Self::default()
+        }
+    }
+
+    // The lowest `n_bits` of a value, up to 32
+    pub fn write(&mut self, value: u32, n_bits: u32) {
+        self.bits |= (value as u64) << self.n_bits;
+        self.n_bits += n_bits;
+
+        while self.n_bits >= 8 {
+            self.bytes.push(self.bits as u8);
+            self.bits >>= 8;
+            self.n_bits -= 8;
+        }
+    }
+
+    // Bytes written, the last one filled up with zeros
+    pub fn finish(mut self) -> Vec<u8> {
+        if self.n_bits > 0 {
+            self.bytes.push(self.bits as u8);
+        }
+        self.bytes
+    }
+}
+
+pub struct BitReader<'a> {
+    bytes: &'a [u8],
+    bits: u64, // Bits of the bytes read not yet used
+    n_bits: u32,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            bits: 0,
+            n_bits: 0,
+        }
+    }
+
+    // None when the bytes run out
+    pub fn read(&mut self, n_bits: u32) -> Option<u32> {
+        while self.n_bits < n_bits {
+            let (&byte, rest) = self.bytes.split_first()?;
+            self.bits |= (byte as u64) << self.n_bits;
+            self.n_bits += 8;
+            self.bytes = rest;
+        }
+
+        let value = (self.bits & ((1 << n_bits) - 1)) as u32;
+        self.bits >>= n_bits;
+        self.n_bits -= n_bits;
+
+        Some(value)
+    }
+
+    pub fn read_u8(&mut self, n_bits: u32) -> Option<u8> {
+        self.read(n_bits).map(|value| value as u8)
+    }
+
+    // Whether whole bytes are left unread
+    pub fn has_bytes_left(&self) -> bool {
+        !self.bytes.is_empty()
+    }
+
+    // Whether the bits left of the last byte read are not all zero, as
+    // `BitWriter::finish` leaves them
+    pub fn has_bits_left(&self) -> bool {
+        self.bits != 0
+    }
+}
@@ -0,0 +1,228 @@
+use std::{fs, path::Path};
+
+use serde_json::json;
+
+use solitare::{Card, Rules, SolitareState, rules::Game};
+
+// Layout of a game in the text notation of FreeCell Solver, which other
+// solvers read as well. Every line is a slot, its cards from the bottom up,
+// with a few optional lines for the rest of the board:
+//
+//   Foundations: H-0 C-2 D-A S-0
+//   Freecells: 5D - - -
+//   Stock: KS 4D ...   (or Talon:, the next card drawn first)
+//   Waste: 2C 9H       (the top card last)
+//   : 8S TH 2C         (a slot, the colon can be left out)
+//
+// Ranks are A, 2 to 9, T or 10, J, Q and K, and suits are H, C, D and S.
+// Cards written in lowercase, like `ks`, are face down, as in Solvitaire.
+// The number of slots and free cells is taken from the file.
+pub fn read(path: &Path, rules: Rules) -> Result<SolitareState, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    parse(&text, rules)
+}
+
+pub fn parse(text: &str, rules: Rules) -> Result<SolitareState, String> {
+    let mut foundations = vec![0u8; 4 * rules.decks as usize];
+    let mut cells = None;
+    let mut stock = Vec::new();
+    let mut waste = Vec::new();
+    let mut slots = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let invalid = |what: &str| format!("line {}: {what}", i + 1);
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (label, rest) = match line.split_once(':') {
+            Some((label, rest)) => (label.trim().to_lowercase(), rest),
+            None => (String::new(), line),
+        };
+        let words = rest.split_whitespace();
+
+        match label.as_str() {
+            "foundations" => {
+                for word in words {
+                    let (suit, rank) = word
+                        .split_once('-')
+                        .ok_or_else(|| invalid(&format!("invalid {word}")))?;
+                    let card = card(&format!("A{suit}"))
+                        .ok_or_else(|| invalid(&format!("invalid {word}")))?;
+                    let rank = match rank {
+                        "0" => 0,
+                        rank => card_rank(rank).ok_or_else(|| {
+                            invalid(&format!("invalid {word}"))
+                        })?,
+                    };
+
+                    // Two decks have two foundations of each suit
+                    let free = (card.suit() as usize..foundations.len())
+                        .step_by(4)
+                        .find(|&i| foundations[i] == 0)
+                        .ok_or_else(|| invalid(&format!("too many {word}")))?;
+                    foundations[free] = rank;
+                }
+            }
+            "freecells" => {
+                cells = Some(
+                    words
+                        .map(|word| match word {
+                            "-" => Ok(None),
+                            word => name(word).map(Some),
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|e| invalid(&e))?,
+                );
+            }
+            "stock" | "talon" => {
+                stock = words
+                    .map(name)
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| invalid(&e))?;
+            }
+            "waste" => {
+                waste = words
+                    .map(name)
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| invalid(&e))?;
+            }
+            "" => {
+                let (mut hidden, mut visible) = (Vec::new(), Vec::new());
+
+                for word in words {
+                    let face_down = word.chars().any(char::is_lowercase)
+                        && !word.chars().any(char::is_uppercase);
+                    if face_down && !visible.is_empty() {
+                        return Err(invalid("face down card on a face up one"));
+                    }
+
+                    let name = name(word).map_err(|e| invalid(&e))?;
+                    if face_down { &mut hidden } else { &mut visible }
+                        .push(name);
+                }
+
+                // Klondike only ever turns up the top card, so the ones on
+                // it were built on each other. FreeCell deals every card
+                // face up, built or not.
+                if rules.game == Game::Klondike
+                    && !rules.thoughtful
+                    && !is_built(&visible)
+                {
+                    return Err(invalid(&format!(
+                        "face up cards of column {} are not a run",
+                        slots.len() + 1
+                    )));
+                }
+
+                slots.push(json!({ "hidden": hidden, "visible": visible }));
+            }
+            label => return Err(invalid(&format!("unknown line {label}"))),
+        }
+    }
+
+    let rules = Rules {
+        slots: slots.len() as u8,
+        cells: cells
+            .as_ref()
+            .map_or(rules.cells, |cells| cells.len() as u8),
+        ..rules
+    };
+    let mut cells = cells.unwrap_or_default();
+    cells.resize(rules.cells as usize, None);
+
+    if !rules.is_valid() {
+        return Err(format!("{} slots can not be played", slots.len()));
+    }
+
+    let position = json!({
+        "foundations": foundations,
+        "stock": stock,
+        "waste": waste,
+        "rules": rules,
+        "slots": slots,
+        "cells": cells,
+    });
+
+    SolitareState::from_json(&position.to_string())
+}
+
+// Every card written in a layout, with the ones below the top cards of the
+// foundations, leaving out any that can not be read
+pub fn cards(text: &str) -> Vec<Card> {
+    let mut cards = Vec::new();
+
+    for line in text.lines() {
+        let (label, rest) = line.split_once(':').unwrap_or(("", line));
+
+        for word in rest.split_whitespace() {
+            if label.trim().eq_ignore_ascii_case("foundations") {
+                let Some((suit, rank)) = word.split_once('-') else {
+                    continue;
+                };
+                let (Some(ace), Some(top)) =
+                    (card(&format!("A{suit}")), card_rank(rank))
+                else {
+                    continue;
+                };
+                cards.extend(
+                    (1..=top)
+                        .map(|rank| Card::from_suit_rank(ace.suit(), rank)),
+                );
+            } else if let Some(card) = card(word) {
+                cards.push(card);
+            }
+        }
+    }
+
+    cards
+}
+
+// Game a layout is of when none is given: FreeCell without a stock, as
+// in the deals solvers are usually tried on, and Klondike otherwise
+pub fn game(path: &Path) -> Game {
+    let text = fs::read_to_string(path).unwrap_or_default();
+    let has_stock = text.lines().any(|line| {
+        let line = line.trim().to_lowercase();
+        line.starts_with("stock:") || line.starts_with("talon:")
+    });
+
+    if has_stock {
+        Game::Klondike
+    } else {
+        Game::FreeCell
+    }
+}
+
+// Name of a card as read by `Card::from_name`, like `10♥` for `TH`
+fn name(word: &str) -> Result<String, String> {
+    card(word)
+        .map(|card| card.name())
+        .ok_or_else(|| format!("invalid card {word}"))
+}
+
+fn card(word: &str) -> Option<Card> {
+    let word = word.to_uppercase();
+    let word = match word.strip_prefix('T') {
+        Some(suit) => format!("10{suit}"),
+        None => word,
+    };
+
+    Card::from_name(&word)
+}
+
+// Whether cards, from the bottom up, go down in rank and alternate in color
+fn is_built(names: &[String]) -> bool {
+    let cards: Vec<Card> =
+        names.iter().filter_map(|n| Card::from_name(n)).collect();
+    cards.windows(2).all(|pair| {
+        pair[1].rank() + 1 == pair[0].rank()
+            && pair[1].is_red() != pair[0].is_red()
+    })
+}
+
+fn card_rank(rank: &str) -> Option<u8> {
+    card(&format!("{rank}S")).map(|card| card.rank())
+}
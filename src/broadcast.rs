@@ -0,0 +1,129 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write, stdout},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use crossterm::{queue, style::ResetColor};
+
+use solitare::SolitareState;
+
+// Game served to viewers over TCP, the board sent to every one of them
+// each time it changes. Viewers get ANSI text redrawing their terminal, or
+// a line of JSON for every board if they send `json` when connecting.
+pub struct Broadcast {
+    shared: Arc<Mutex<Shared>>,
+    last: Option<SolitareState>, // Board last sent
+}
+
+// Board in both forms viewers can ask for
+#[derive(Default)]
+pub struct Frame {
+    pub ansi: String,
+    pub json: String,
+}
+
+#[derive(Default)]
+struct Shared {
+    viewers: Vec<Viewer>,
+    frame: Frame, // Last sent, for viewers joining
+}
+
+struct Viewer {
+    stream: TcpStream,
+    json: bool,
+}
+
+impl Viewer {
+    // Whether the viewer is still there
+    fn send(&mut self, frame: &Frame) -> bool {
+        let text = if self.json { &frame.json } else { &frame.ansi };
+        self.stream.write_all(text.as_bytes()).is_ok()
+    }
+}
+
+impl Broadcast {
+    // Listens for viewers on all interfaces
+    pub fn serve(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        let shared = Arc::new(Mutex::new(Shared::default()));
+
+        let accepting = shared.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let shared = accepting.clone();
+                thread::spawn(move || join(stream, &shared));
+            }
+        });
+
+        Ok(Self { shared, last: None })
+    }
+
+    // Sends the board to the viewers if it changed since it was last sent,
+    // only making the frame then
+    pub fn send(
+        &mut self,
+        state: SolitareState,
+        frame: impl FnOnce() -> Frame,
+    ) {
+        if self.last == Some(state) {
+            return;
+        }
+        self.last = Some(state);
+
+        let frame = frame();
+        let mut shared = self.shared.lock().unwrap();
+        shared.viewers.retain_mut(|viewer| viewer.send(&frame));
+        shared.frame = frame;
+    }
+}
+
+fn join(stream: TcpStream, shared: &Mutex<Shared>) {
+    // Viewers not saying which form they want, like `nc`, get ANSI text
+    let mut request = String::new();
+    stream
+        .set_read_timeout(Some(Duration::from_millis(500)))
+        .ok();
+    BufReader::new(&stream).read_line(&mut request).ok();
+
+    // A viewer that stops reading is dropped rather than holding up the game
+    stream.set_write_timeout(Some(Duration::from_secs(1))).ok();
+    stream.set_nodelay(true).ok();
+
+    let mut viewer = Viewer {
+        stream,
+        json: request.trim() == "json",
+    };
+
+    let mut shared = shared.lock().unwrap();
+    if viewer.send(&shared.frame) {
+        shared.viewers.push(viewer);
+    }
+}
+
+// Prints the boards of a game broadcast at an address like
+// `localhost:4000` until it ends
+pub fn watch(address: &str, json: bool) -> io::Result<()> {
+    let mut stream = TcpStream::connect(address)?;
+    writeln!(stream, "{}", if json { "json" } else { "ansi" })?;
+
+    let mut out = stdout();
+    let mut buf = [0; 4096];
+    loop {
+        let n = stream.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        // Frames are shown as they come, not only at the end of a line
+        out.write_all(&buf[..n])?;
+        out.flush()?;
+    }
+
+    if !json {
+        queue!(out, ResetColor)?;
+        writeln!(out)?;
+    }
+    out.flush()
+}
@@ -0,0 +1,85 @@
+// Card in u8:
+// suit rank
+// 0000 0000
+//    | Color (0 black, 1 red)
+//
+// Example, ♥ J:
+// 0001 1011
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Card(pub u8);
+
+// Suits ordered: ♠, ♥, ♣, ♦
+pub const SUIT_SYMBOLS: [char; 4] = ['♠', '♥', '♣', '♦'];
+pub const RANK_NAMES: [&str; 13] = [
+    "A", "2", "3", "4", "5", "6", "7", "8", "9", "10", "J", "Q", "K",
+];
+
+impl Card {
+    pub fn from_index(i: usize) -> Self {
+        let rank = (i % 13 + 1) as u8;
+        let suit = (i / 13) as u8;
+
+        Self::from_suit_rank(suit, rank)
+    }
+
+    pub fn from_suit_rank(suit: u8, rank: u8) -> Self {
+        assert!(suit < 4 && rank <= 13);
+
+        Self((suit << 4) | rank)
+    }
+
+    pub fn to_ind(&self) -> usize {
+        (self.suit() * 13 + self.rank() - 1) as usize
+    }
+
+    pub fn rank(&self) -> u8 {
+        self.0 & 0b0000_1111
+    }
+
+    pub fn suit(&self) -> u8 {
+        self.0 >> 4
+    }
+
+    pub fn is_red(&self) -> bool {
+        (self.0 >> 4) & 1 == 1
+    }
+
+    // Plain text name, e.g. `10♥`
+    pub fn name(&self) -> String {
+        format!(
+            "{}{}",
+            RANK_NAMES[self.rank() as usize - 1],
+            SUIT_SYMBOLS[self.suit() as usize]
+        )
+    }
+
+    // Parses a name as given by `name`. The suit can also be given as one
+    // of the letters S, H, C or D.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let suit_char = name.chars().last()?;
+        let rank = &name[..name.len() - suit_char.len_utf8()];
+
+        let suit = match suit_char {
+            '♠' | 'S' | 's' => 0,
+            '♥' | 'H' | 'h' => 1,
+            '♣' | 'C' | 'c' => 2,
+            '♦' | 'D' | 'd' => 3,
+            _ => return None,
+        };
+
+        let rank = RANK_NAMES.iter().position(|&r| r == rank)? as u8 + 1;
+
+        Some(Self::from_suit_rank(suit, rank))
+    }
+
+    // Glyph from the Unicode playing cards block
+    pub fn glyph(&self) -> char {
+        let rank = self.rank();
+        let rank_offset = if let 1..=11 = rank { rank } else { rank + 1 };
+
+        let suit = self.suit();
+        let suit_offset = [0, 1, 3, 2][suit as usize] << 4;
+
+        char::from_u32('🂠' as u32 + suit_offset + rank_offset as u32).unwrap()
+    }
+}
@@ -0,0 +1,60 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Sender},
+    },
+    thread,
+};
+
+use solitare::{SolitareState, solver};
+
+// Chance of winning the game being played, worked out on a thread of its
+// own so the game goes on meanwhile. Positions asked for while it is busy
+// are skipped for the last one.
+pub struct WinChance {
+    positions: Sender<SolitareState>,
+    done: Arc<Mutex<Option<(SolitareState, f64)>>>, // Last worked out
+    asked: Option<SolitareState>,
+}
+
+impl WinChance {
+    pub fn start() -> Self {
+        let (positions, received) = mpsc::channel::<SolitareState>();
+        let done = Arc::new(Mutex::new(None));
+
+        let shared = done.clone();
+        // Ends with the sender, once the estimate is switched off
+        thread::spawn(move || {
+            let mut rng = rand::rng();
+            while let Ok(mut state) = received.recv() {
+                while let Ok(later) = received.try_recv() {
+                    state = later;
+                }
+                let chance = solver::win_chance(&state, &mut rng);
+                *shared.lock().unwrap() = Some((state, chance));
+            }
+        });
+
+        Self {
+            positions,
+            done,
+            asked: None,
+        }
+    }
+
+    // Starts on a position, unless it was the last one asked for
+    pub fn ask(&mut self, state: SolitareState) {
+        if self.asked != Some(state) {
+            self.asked = Some(state);
+            let _ = self.positions.send(state);
+        }
+    }
+
+    // Chance for a position, once it is worked out
+    pub fn get(&self, state: &SolitareState) -> Option<f64> {
+        match *self.done.lock().unwrap() {
+            Some((done, chance)) if done == *state => Some(chance),
+            _ => None,
+        }
+    }
+}
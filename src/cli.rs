@@ -0,0 +1,313 @@
+use std::{env, path::PathBuf};
+
+use clap::{Args, Parser, Subcommand};
+
+use solitare::rules::Game;
+
+use crate::config::CardStyle;
+
+// Flags for the game and deal can be given before or after the subcommand,
+// and without one a game is played
+#[derive(Parser)]
+#[command(about = "Solitaire in the terminal", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    #[command(flatten)]
+    pub rules: RuleArgs,
+    #[command(flatten)]
+    pub deal: DealArgs,
+    #[command(flatten)]
+    pub play: PlayArgs,
+    #[command(flatten)]
+    pub solver: SolverArgs,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Play a game, the same as giving no subcommand
+    Play,
+    /// Find out if a deal can be won, printing the winning moves as a
+    /// record that can be replayed
+    Solve,
+    /// Go through a recorded game and find the move that lost it, or with
+    /// --batch deal and solve many games
+    Analyze {
+        /// Recorded game
+        #[arg(required_unless_present = "batch", conflicts_with = "batch")]
+        file: Option<PathBuf>,
+        /// Deal and solve this many games, 1000 by default, telling how
+        /// many can be won and how long it takes. Seeds count up from
+        /// --seed when given.
+        #[arg(
+            long,
+            value_name = "DEALS",
+            num_args = 0..=1,
+            default_missing_value = "1000"
+        )]
+        batch: Option<u64>,
+        /// Deals solved at the same time, one per core by default
+        #[arg(long, requires = "batch")]
+        jobs: Option<usize>,
+    },
+    /// Step through a recorded game
+    Replay { file: PathBuf },
+    /// Print the board of the deal as it is drawn, without colors
+    Show,
+    /// Build a position card by card, to play or solve it. It is read from
+    /// and saved to FILE, in the layout of --board.
+    Edit { file: Option<PathBuf> },
+    /// Pick one of the puzzles shipped with the game, positions to finish,
+    /// going back to the list after each
+    Puzzles,
+    /// Show the results of the daily deals, with a calendar of this month
+    #[command(alias = "calendar")]
+    Stats,
+}
+
+#[derive(Args)]
+pub struct RuleArgs {
+    /// Game to play, one of klondike, spider, freecell, pyramid and
+    /// fortythieves
+    #[arg(long, global = true, value_parser = parse_game)]
+    pub game: Option<Game>,
+    /// Play Spider with 1, 2 or 4 suits
+    #[arg(long, global = true, value_parser = parse_suits)]
+    pub spider: Option<u8>,
+    /// Play FreeCell
+    #[arg(long, global = true)]
+    pub freecell: bool,
+    /// Play Pyramid
+    #[arg(long, global = true)]
+    pub pyramid: bool,
+    /// Play Forty Thieves
+    #[arg(long, global = true)]
+    pub forty_thieves: bool,
+    /// Play Klondike with two decks and nine slots
+    #[arg(long, global = true)]
+    pub double_deck: bool,
+    /// Number of slots, or rows of the pyramid
+    #[arg(long, global = true)]
+    pub columns: Option<u8>,
+    /// Draw three cards at a time
+    #[arg(long, global = true)]
+    pub draw3: bool,
+    /// Passes through the stock allowed, 0 for no limit
+    #[arg(long, global = true)]
+    pub passes: Option<u8>,
+    /// Vegas scoring, paying for every deal and winning back per card
+    #[arg(long, global = true)]
+    pub vegas: bool,
+    /// Play without a score
+    #[arg(long, global = true)]
+    pub no_scoring: bool,
+    /// Play with every card face up
+    #[arg(long, global = true)]
+    pub thoughtful: bool,
+    /// Any card can go on an empty slot, not only kings
+    #[arg(long, global = true)]
+    pub any_on_empty: bool,
+    /// Cards can not be taken back off the foundations
+    #[arg(long, global = true)]
+    pub no_foundation_to_slot: bool,
+    /// Only move runs as large as the free cells and empty slots allow, as
+    /// in FreeCell
+    #[arg(long, global = true)]
+    pub run_limit: bool,
+}
+
+#[derive(Args)]
+pub struct DealArgs {
+    /// Seed to deal the game from
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+    /// Share code of the deal to play
+    #[arg(long, global = true)]
+    pub deal: Option<String>,
+    /// Deal the layout in a file, in the board notation of FreeCell
+    /// Solver. FreeCell unless the file has a stock or a game is given.
+    #[arg(long, global = true)]
+    pub board: Option<PathBuf>,
+    /// Play FreeCell deal N as numbered by Microsoft FreeCell
+    #[arg(long, global = true, value_parser = clap::value_parser!(u32).range(1..=0x7fff_ffff))]
+    pub ms_deal: Option<u32>,
+    /// Only deal games the solver can win
+    #[arg(long, global = true)]
+    pub winnable: bool,
+    /// Play the daily deal, the same for everyone
+    #[arg(long, global = true)]
+    pub daily: bool,
+}
+
+#[derive(Args)]
+pub struct PlayArgs {
+    /// Resume the game saved with `s`
+    #[arg(short = 'c', long = "continue", global = true)]
+    pub resume: bool,
+    /// Add every game to a file as a line of JSON when it ends, with its
+    /// deal, rules, result, time and moves
+    #[arg(long, global = true)]
+    pub game_log: Option<PathBuf>,
+    /// Step through a game shared as a replay string, copied with M
+    #[arg(long, global = true)]
+    pub replay_string: Option<String>,
+    /// Write the moves made to a file when quitting
+    #[arg(long, global = true)]
+    pub record: Option<PathBuf>,
+    /// How to draw the cards, one of glyph, suits, ascii and big. Found
+    /// from the terminal when not given.
+    #[arg(long, global = true, value_parser = parse_cards)]
+    pub cards: Option<CardStyle>,
+    /// Cards take up two columns, for terminals drawing them wide
+    #[arg(long, global = true)]
+    pub twice_width: bool,
+    /// Show the face down cards of a slot as a single line with their count
+    #[arg(long, global = true)]
+    pub compact: bool,
+    /// Put the stock and waste on the left and the foundations on the
+    /// right
+    #[arg(long, global = true)]
+    pub mirrored: bool,
+    /// Bold cards on black, with what is selected spelled out
+    #[arg(long, global = true)]
+    pub high_contrast: bool,
+    /// Tell moves and the cards under the cursor in words, for screen
+    /// readers
+    #[arg(long, global = true)]
+    pub screen_reader: bool,
+    /// A single click plays a card to its foundation
+    #[arg(long, global = true)]
+    pub one_tap: bool,
+    /// Cards that are safe to play go to the foundations by themselves
+    #[arg(long, global = true)]
+    pub auto_move: bool,
+    /// Cards go straight to where they are moved, without being shown
+    /// on their way
+    #[arg(long, global = true)]
+    pub no_animations: bool,
+    /// Play no sounds
+    #[cfg(feature = "sound")]
+    #[arg(long, global = true)]
+    pub no_sound: bool,
+    /// How the game is drawn, terminal or ratatui. Ratatui only redraws
+    /// what changed, without flicker.
+    #[cfg(feature = "ratatui")]
+    #[arg(long, global = true, value_parser = parse_ui, default_value = "terminal")]
+    pub ui: Ui,
+    /// Show the move history next to the board
+    #[arg(long, global = true)]
+    pub history_panel: bool,
+    /// Show a rough chance of winning from the position, from solving a
+    /// few random guesses at the face down cards after each move
+    #[arg(long, global = true)]
+    pub win_chance: bool,
+    /// Hints from solving the whole game when possible
+    #[arg(long, global = true)]
+    pub perfect_hints: bool,
+    /// Play with commands read from stdin, like `draw` or `move s3 s5`,
+    /// printing the board after each. Exits with 0 if the game was won.
+    #[arg(long, global = true)]
+    pub headless: bool,
+    /// Print the board as JSON in headless mode
+    #[arg(long, global = true)]
+    pub json: bool,
+    /// Show face down cards and the order of the stock, dimmed. Ctrl+D
+    /// switches them off and on.
+    #[arg(long, global = true)]
+    pub debug: bool,
+    /// Practice without the result counting: undo as much as you like and
+    /// peek at the face down cards with Ctrl+D. Practice games are left out
+    /// of the daily results, the leaderboard and the game log.
+    #[arg(long, global = true)]
+    pub practice: bool,
+    /// Challenge: win each deal within the moves the solver takes plus
+    /// EXTRA, or it is lost. The deals are all winnable, and the games won
+    /// have a leaderboard of their own.
+    #[arg(long, global = true, value_name = "EXTRA")]
+    pub move_budget: Option<usize>,
+    /// Challenge: win each deal within MINUTES minutes. The clock counts
+    /// down from the first move, and the deal is given up when it runs out.
+    #[arg(long, global = true, value_name = "MINUTES")]
+    pub time_limit: Option<u64>,
+    /// Serve the board on a port to viewers, who are sent it after every
+    /// move
+    #[arg(long, global = true)]
+    pub broadcast: Option<u16>,
+    /// Watch a game served with --broadcast at HOST:PORT, as lines of JSON
+    /// with --json
+    #[arg(long, global = true)]
+    pub watch: Option<String>,
+    /// Watch the game play itself until a key is pressed
+    #[arg(long, global = true)]
+    pub bot: bool,
+}
+
+// Limits on the table of positions the solver has searched. It gives up
+// once either is reached.
+#[derive(Args)]
+pub struct SolverArgs {
+    /// Positions the solver searches before giving up
+    #[arg(long, global = true)]
+    pub max_positions: Option<usize>,
+    /// Megabytes the solver may use for the positions it has searched
+    #[arg(long, global = true)]
+    pub max_memory: Option<usize>,
+}
+
+impl Cli {
+    // Short flags of two letters are spelled out, as clap only takes a
+    // single letter after a dash
+    pub fn from_env() -> Self {
+        Self::parse_from(env::args().map(|arg| {
+            let long = match arg.as_str() {
+                "-tw" => "--twice-width",
+                "-ot" => "--one-tap",
+                "-d3" => "--draw3",
+                "-am" => "--auto-move",
+                "-ae" => "--any-on-empty",
+                "-hp" => "--history-panel",
+                _ => return arg,
+            };
+            long.to_owned()
+        }))
+    }
+}
+
+fn parse_game(name: &str) -> Result<Game, String> {
+    Game::from_name(name).ok_or_else(|| {
+        let names: Vec<_> = Game::ALL.iter().map(|game| game.name()).collect();
+        format!("expected one of {}", names.join(", "))
+    })
+}
+
+fn parse_cards(name: &str) -> Result<CardStyle, String> {
+    CardStyle::from_name(name).ok_or_else(|| {
+        let names: Vec<_> =
+            CardStyle::ALL.iter().map(|style| style.name()).collect();
+        format!("expected one of {}", names.join(", "))
+    })
+}
+
+#[cfg(feature = "ratatui")]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Ui {
+    Terminal, // Written straight to the terminal with crossterm
+    Ratatui,
+}
+
+#[cfg(feature = "ratatui")]
+fn parse_ui(name: &str) -> Result<Ui, String> {
+    match name {
+        "terminal" => Ok(Ui::Terminal),
+        "ratatui" => Ok(Ui::Ratatui),
+        _ => Err(String::from("expected terminal or ratatui")),
+    }
+}
+
+fn parse_suits(suits: &str) -> Result<u8, String> {
+    suits
+        .parse()
+        .ok()
+        .filter(|n| matches!(n, 1 | 2 | 4))
+        .ok_or_else(|| String::from("expected 1, 2 or 4"))
+}
@@ -0,0 +1,11 @@
+use std::io::Write;
+
+use crate::base64;
+
+// Copies text to the system clipboard through the terminal using an OSC 52
+// escape sequence. This works over SSH as well, but some terminals need it
+// enabled in their settings.
+pub fn copy(out: &mut impl Write, text: &str) -> std::io::Result<()> {
+    write!(out, "\x1b]52;c;{}\x07", base64::encode(text.as_bytes()))?;
+    out.flush()
+}
@@ -0,0 +1,384 @@
+use std::{env, fs, io, path::PathBuf};
+
+use crossterm::event::KeyCode;
+use serde::Deserialize;
+
+use crate::theme::{Colors, ThemeName};
+
+// Settings read from `solitare/config.toml` in the config directory, like
+// ~/.config/solitare/config.toml. Everything can be left out, and flags
+// given on the command line are used on top of the file.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub draw: u8,          // Cards drawn from the stock at a time
+    pub cards: CardStyle,  // Found from the terminal when not given
+    pub twice_width: bool, // Only for glyphs
+    pub one_tap: bool,
+    pub auto_move: bool, // Safe cards go to the foundations after each move
+    pub animations: bool, // Cards of a move are shown going where they go
+    pub illegal_moves: Feedback, // Besides saying why a move is not allowed
+    #[cfg(feature = "sound")]
+    pub sound: bool,
+    #[cfg(feature = "sound")]
+    pub volume: u8, // From 1 to 7
+    // h, j, k and l move the cursor, after a count, so no key in `keys`
+    // can be on them. The hint moves to VIM_HINT unless set.
+    pub vim_keys: bool,
+    // Face down cards in the slots take up a single line. They are also
+    // when the board would not fit the terminal otherwise.
+    pub compact: bool,
+    // Stock and waste on the left and foundations on the right, the other
+    // way around from the usual
+    pub mirrored: bool,
+    // Bold cards on black, with what is highlighted spelled out below the
+    // board rather than shown by shades of color
+    pub high_contrast: bool,
+    // Moves, what is highlighted and the pile under the cursor are told in
+    // words below the board, for screen readers
+    pub screen_reader: bool,
+    pub theme: ThemeName,
+    // Given by name, like "dark_green", or as "#rrggbb", "rgb_(r,g,b)" or
+    // "ansi_(n)", in place of the colors of the themes
+    pub colors: Colors,
+    pub keys: Keys,
+    // Every game is added to this file as a line of JSON when it ends
+    pub game_log: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            draw: 1,
+            cards: CardStyle::default(),
+            twice_width: false,
+            one_tap: false,
+            auto_move: false,
+            animations: true,
+            illegal_moves: Feedback::Flash,
+            #[cfg(feature = "sound")]
+            sound: true,
+            #[cfg(feature = "sound")]
+            volume: 3,
+            vim_keys: false,
+            compact: false,
+            mirrored: false,
+            high_contrast: false,
+            screen_reader: false,
+            theme: ThemeName::Dark,
+            colors: Colors::default(),
+            keys: Keys::default(),
+            game_log: None,
+        }
+    }
+}
+
+// How cards are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CardStyle {
+    Glyph, // From the Unicode playing cards block
+    Suits, // Rank and suit symbol, like K♠ or 10♦
+    Ascii, // Rank and suit letter, like KS or TD
+    Big,   // Boxes of several lines, with the rank and suit in the corners
+}
+
+impl CardStyle {
+    pub const ALL: [CardStyle; 4] = [
+        CardStyle::Glyph,
+        CardStyle::Suits,
+        CardStyle::Ascii,
+        CardStyle::Big,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CardStyle::Glyph => "glyph",
+            CardStyle::Suits => "suits",
+            CardStyle::Ascii => "ascii",
+            CardStyle::Big => "big",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|style| style.name() == name)
+    }
+}
+
+// Guessed from the environment. Without UTF-8 only ASCII can be shown, and
+// the fonts of the Linux console and the old Windows console have the suit
+// symbols but not the card glyphs.
+impl Default for CardStyle {
+    fn default() -> Self {
+        if cfg!(windows) {
+            return if env::var_os("WT_SESSION").is_some() {
+                CardStyle::Glyph
+            } else {
+                CardStyle::Suits
+            };
+        }
+
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .into_iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        if !locale.contains("utf-8") && !locale.contains("utf8") {
+            CardStyle::Ascii
+        } else if env::var("TERM").is_ok_and(|term| term == "linux") {
+            CardStyle::Suits
+        } else {
+            CardStyle::Glyph
+        }
+    }
+}
+
+// What happens when trying a move that is not allowed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Feedback {
+    Flash, // The place the cards were to go turns red for a moment
+    Bell,  // Rings the bell of the terminal
+    Off,
+}
+
+// Keys for the commands in the game. Uppercase letters are pressed with
+// shift, and control keys like ctrl+z for undo always work.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Keys {
+    pub quit: char,
+    pub undo: char,
+    pub redo: char,
+    pub restart: char,
+    pub new_deal: char,
+    pub save: char,
+    pub one_tap: char,
+    pub history: char,
+    pub hidden_cards: char,
+    pub copy_board: char,
+    pub copy_code: char,
+    pub copy_moves: char,
+    pub copy_replay: char,
+    pub export_deal: char,
+    pub hint: char,
+    pub solution: char,
+    pub scrub: char,
+    pub finish: char,
+    pub safe_moves: char,
+    pub draw: char,
+    pub draw_alt: char, // Draws even while the cursor is shown
+    pub help: char,
+    pub theme: char,
+}
+
+impl Keys {
+    // Every key, with the name of its setting
+    fn all(&self) -> [(&'static str, char); 23] {
+        [
+            ("quit", self.quit),
+            ("undo", self.undo),
+            ("redo", self.redo),
+            ("restart", self.restart),
+            ("new_deal", self.new_deal),
+            ("save", self.save),
+            ("one_tap", self.one_tap),
+            ("history", self.history),
+            ("hidden_cards", self.hidden_cards),
+            ("copy_board", self.copy_board),
+            ("copy_code", self.copy_code),
+            ("copy_moves", self.copy_moves),
+            ("copy_replay", self.copy_replay),
+            ("export_deal", self.export_deal),
+            ("hint", self.hint),
+            ("solution", self.solution),
+            ("scrub", self.scrub),
+            ("finish", self.finish),
+            ("safe_moves", self.safe_moves),
+            ("draw", self.draw),
+            ("draw_alt", self.draw_alt),
+            ("help", self.help),
+            ("theme", self.theme),
+        ]
+    }
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        Self {
+            quit: 'q',
+            undo: 'u',
+            redo: 'r',
+            restart: 'R',
+            new_deal: 'n',
+            save: 's',
+            one_tap: 'o',
+            history: 'p',
+            hidden_cards: 'i',
+            copy_board: 'c',
+            copy_code: 'x',
+            copy_moves: 'm',
+            copy_replay: 'M',
+            export_deal: 'e',
+            hint: 'h',
+            solution: 'H',
+            scrub: 't',
+            finish: 'f',
+            safe_moves: 'a',
+            draw: ' ',
+            draw_alt: 'd',
+            help: '?',
+            theme: 'T',
+        }
+    }
+}
+
+impl Config {
+    // Columns taken up by a card, including the gap after cards drawn as
+    // text
+    pub fn card_width(&self) -> u16 {
+        match self.cards {
+            CardStyle::Glyph if self.twice_width => 2,
+            CardStyle::Glyph => 1,
+            CardStyle::Suits => 4,
+            CardStyle::Ascii => 3,
+            CardStyle::Big => 6,
+        }
+    }
+
+    // Lines taken up by a card that is not covered by another
+    pub fn card_height(&self) -> u16 {
+        match self.cards {
+            CardStyle::Big => 4,
+            _ => 1,
+        }
+    }
+}
+
+fn path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("solitare").join("config.toml"))
+}
+
+impl Config {
+    // The defaults if there is no config file
+    pub fn load() -> io::Result<Self> {
+        // Tests leave out the config file of whoever runs them
+        if cfg!(test) {
+            return Ok(Self::default());
+        }
+
+        let Some(path) = path() else {
+            return Ok(Self::default());
+        };
+
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok(Self::default());
+            }
+            Err(e) => return Err(e),
+        };
+
+        let invalid = |e: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {e}", path.display()),
+            )
+        };
+        let config: Self =
+            toml::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+
+        config.check().map_err(invalid)
+    }
+
+    // Turns away settings that can not work, after making room for the
+    // vim keys
+    fn check(mut self) -> Result<Self, String> {
+        if self.draw == 0 {
+            return Err(String::from("at least one card must be drawn"));
+        }
+
+        #[cfg(feature = "sound")]
+        if self.volume > 7 {
+            return Err(String::from("the volume goes up to 7"));
+        }
+
+        if self.vim_keys {
+            let keys = self.keys.all();
+            if self.keys.hint == Keys::default().hint
+                && keys.iter().all(|&(_, key)| key != VIM_HINT)
+            {
+                self.keys.hint = VIM_HINT;
+            }
+
+            let taken = self
+                .keys
+                .all()
+                .into_iter()
+                .find(|(_, key)| VIM_KEYS.contains(key));
+            if let Some((name, key)) = taken {
+                return Err(format!(
+                    "{key} moves the cursor with vim keys, so keys.{name} \
+                     needs another key"
+                ));
+            }
+        }
+
+        Ok(self)
+    }
+}
+
+// Keys moving the cursor left, down, up and right when vim keys are on
+const VIM_KEYS: [char; 4] = ['h', 'j', 'k', 'l'];
+
+// Where the hint goes from h when vim keys are on
+const VIM_HINT: char = 'g';
+
+// Arrow key a vim key stands for
+pub fn vim_direction(c: char) -> Option<KeyCode> {
+    match c {
+        'h' => Some(KeyCode::Left),
+        'j' => Some(KeyCode::Down),
+        'k' => Some(KeyCode::Up),
+        'l' => Some(KeyCode::Right),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(text: &str) -> Result<Config, String> {
+        toml::from_str::<Config>(text).unwrap().check()
+    }
+
+    #[test]
+    fn vim_keys_move_the_default_hint() {
+        let config = config("vim_keys = true").unwrap();
+        assert_eq!(config.keys.hint, VIM_HINT);
+        assert!(config.keys.all().iter().all(|(_, k)| !VIM_KEYS.contains(k)));
+
+        assert_eq!(vim_direction('h'), Some(KeyCode::Left));
+        assert_eq!(vim_direction('l'), Some(KeyCode::Right));
+        assert_eq!(vim_direction('g'), None);
+    }
+
+    #[test]
+    fn hint_stays_without_vim_keys() {
+        assert_eq!(config("").unwrap().keys.hint, 'h');
+    }
+
+    #[test]
+    fn key_on_a_vim_key_is_refused() {
+        let refused = |text: &str, name: &str| {
+            config(text).err().is_some_and(|e| e.contains(name))
+        };
+
+        assert!(refused("vim_keys = true\n[keys]\nundo = 'j'", "keys.undo"));
+        // The hint stays on h when its place is taken
+        assert!(refused("vim_keys = true\n[keys]\nquit = 'g'", "keys.hint"));
+    }
+}
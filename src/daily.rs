@@ -0,0 +1,228 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+
+use crate::{puzzle, theme};
+
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+// Day in UTC, so everyone gets the same daily deal at the same time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Date {
+    year: i64,
+    month: i64, // 1 to 12
+    day: i64,   // 1 to 31
+}
+
+impl Date {
+    pub fn today() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        Self::from_days((seconds / 86400) as i64)
+    }
+
+    // Seed of the daily deal, the date written as a number like 20240131
+    pub fn seed(self) -> u64 {
+        (self.year * 10000 + self.month * 100 + self.day) as u64
+    }
+
+    // Date from the number of days since 1970-01-01, counting in eras of
+    // 400 years starting in March, so leap days come last
+    fn from_days(days: i64) -> Self {
+        let days = days + 719468; // From 0000-03-01
+        let era = days.div_euclid(146097);
+        let day_of_era = days.rem_euclid(146097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524
+            - day_of_era / 146096)
+            / 365;
+        let day_of_year = day_of_era
+            - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_from_march = (5 * day_of_year + 2) / 153;
+        let month = (month_from_march + 2) % 12 + 1;
+
+        Self {
+            year: era * 400 + year_of_era + (month <= 2) as i64,
+            month,
+            day: day_of_year - (153 * month_from_march + 2) / 5 + 1,
+        }
+    }
+
+    // The other way around from `from_days`
+    fn to_days(self) -> i64 {
+        let year = self.year - (self.month <= 2) as i64;
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let month_from_march = (self.month + 9) % 12;
+        let day_of_year = (153 * month_from_march + 2) / 5 + self.day - 1;
+        let day_of_era = 365 * year_of_era + year_of_era / 4
+            - year_of_era / 100
+            + day_of_year;
+
+        era * 146097 + day_of_era - 719468
+    }
+
+    fn days_in_month(self) -> i64 {
+        let next_month = match self.month {
+            12 => Date {
+                year: self.year + 1,
+                month: 1,
+                day: 1,
+            },
+            month => Date {
+                month: month + 1,
+                day: 1,
+                ..self
+            },
+        };
+
+        next_month.to_days() - Date { day: 1, ..self }.to_days()
+    }
+}
+
+impl Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+// How the daily deal of a day went
+#[derive(Serialize, Deserialize)]
+pub struct DailyResult {
+    pub won: bool,
+    pub moves: usize,
+    pub seconds: u64,
+}
+
+// Results of the daily deals kept apart from everything else, by date
+fn results_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("solitare").join("daily.json"))
+}
+
+pub fn results() -> BTreeMap<String, DailyResult> {
+    results_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+// Saves the result of a day, unless the deal was already won that day
+pub fn record(date: Date, result: DailyResult) -> io::Result<()> {
+    let Some(path) = results_path() else {
+        return Ok(());
+    };
+
+    let mut results = results();
+    let key = date.to_string();
+
+    if results.get(&key).is_some_and(|r| r.won) {
+        return Ok(());
+    }
+    results.insert(key, result);
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(&results)?)
+}
+
+// The month of a day laid out by week, with the days the daily deal was
+// won or only tried in the colors of the theme. Every part is drawn in them,
+// so the text color is not lost after a day.
+pub fn calendar(today: Date) -> String {
+    let results = results();
+    let first = Date { day: 1, ..today };
+    // 1970-01-01 was a Thursday, and weeks start on Monday
+    let offset = (first.to_days() + 3).rem_euclid(7);
+
+    let title =
+        format!("{} {}", MONTH_NAMES[today.month as usize - 1], today.year);
+    let mut text = format!("{title:^20}\nMo Tu We Th Fr Sa Su\n");
+    text += &theme::plain("   ".repeat(offset as usize)).to_string();
+
+    let mut n_won = 0;
+
+    for day in 1..=today.days_in_month() {
+        let date = Date { day, ..today };
+        let label = format!("{day:>2}");
+
+        text += &match results.get(&date.to_string()) {
+            Some(result) if result.won => {
+                n_won += 1;
+                theme::won(label).to_string()
+            }
+            Some(_) => theme::tried(label).to_string(),
+            None if date == today => theme::plain(label).bold().to_string(),
+            None => theme::plain(label).to_string(),
+        };
+        text += &if (offset + day) % 7 == 0 {
+            String::from("\n")
+        } else {
+            theme::plain(" ").to_string()
+        };
+    }
+
+    text = text.trim_end().to_owned();
+    text += &format!("\n\nWon {n_won} daily deals this month\n");
+
+    text
+}
+
+// Totals over every daily deal played, after the calendar of this month
+pub fn stats(today: Date) -> String {
+    let results = results();
+    let won: Vec<_> = results.values().filter(|r| r.won).collect();
+
+    let mut text = calendar(today);
+
+    text += &format!(
+        "Won {} of {} daily deals played\n",
+        won.len(),
+        results.len()
+    );
+
+    if let Some(best) = won.iter().map(|r| r.seconds).min() {
+        text += &format!("Best time {}:{:02}\n", best / 60, best % 60);
+    }
+    if let Some(fewest) = won.iter().map(|r| r.moves).min() {
+        text += &format!("Fewest moves {fewest}\n");
+    }
+
+    // Days won in a row up to today, or up to yesterday while today's deal
+    // is not won yet
+    let is_won = |days| {
+        let key = Date::from_days(days).to_string();
+        results.get(&key).is_some_and(|r| r.won)
+    };
+    let mut day = today.to_days();
+    if !is_won(day) {
+        day -= 1;
+    }
+    let streak = (0..).take_while(|i| is_won(day - i)).count();
+    text += &format!("Current streak {streak} days\n");
+
+    text += &puzzle::stats();
+
+    text
+}
@@ -0,0 +1,305 @@
+use std::{
+    fs,
+    io::{self, Stdout, Write, stdout},
+    path::Path,
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute, queue,
+    style::{Print, ResetColor, Stylize},
+    terminal::{
+        self, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode,
+        enable_raw_mode,
+    },
+};
+
+use solitare::{
+    Card, Rules, SolitareState,
+    rules::Game,
+    solver::{self, Solution},
+};
+
+use crate::{board, solver_positions, theme};
+
+// Row of the first line of the layout
+const FIRST_ROW: u16 = 2;
+
+// File the position is saved to when no other is given
+pub const BOARD_PATH: &str = "solitare_board.txt";
+
+// A line of the layout, edited after its label. Slots have no label.
+struct Line {
+    label: &'static str,
+    cards: String,
+}
+
+const LABELS: [&str; 4] = ["Foundations", "Freecells", "Stock", "Waste"];
+
+// Lets the player build a position line by line, in the layout read by
+// --board, and see it as it would be dealt. Enter plays it, after which it
+// is returned, and None if they quit instead.
+pub fn run(text: &str, rules: Rules, path: &Path) -> Option<SolitareState> {
+    let mut out = stdout();
+
+    enable_raw_mode().unwrap();
+    execute!(out, EnterAlternateScreen, cursor::Hide).unwrap();
+
+    let mut lines = read_lines(text, rules);
+    let mut selected = first_slot(&lines).min(lines.len() - 1);
+    let mut message = None;
+
+    let played = loop {
+        draw(&mut out, &lines, selected, rules, message.take()).unwrap();
+
+        let Ok(Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: _,
+            state: _,
+        })) = event::read()
+        else {
+            continue;
+        };
+
+        match (code, modifiers) {
+            (KeyCode::Char('c'), KeyModifiers::CONTROL) | (KeyCode::Esc, _) => {
+                break None;
+            }
+            (KeyCode::Char('s'), KeyModifiers::CONTROL) => {
+                message = Some(match fs::write(path, write_lines(&lines)) {
+                    Ok(()) => format!("Saved to {}", path.display()),
+                    Err(e) => format!("Could not save: {e}"),
+                });
+            }
+            // Adds an empty slot after the selected line, or removes the
+            // selected slot
+            (KeyCode::Char('n'), KeyModifiers::CONTROL) => {
+                let at = (selected + 1).max(first_slot(&lines));
+                lines.insert(
+                    at,
+                    Line {
+                        label: "",
+                        cards: String::new(),
+                    },
+                );
+                selected = at;
+            }
+            (KeyCode::Char('d'), KeyModifiers::CONTROL)
+                if lines[selected].label.is_empty() =>
+            {
+                lines.remove(selected);
+                selected = selected.min(lines.len() - 1);
+            }
+            (KeyCode::Up, _) => {
+                selected = (selected + lines.len() - 1) % lines.len();
+            }
+            (KeyCode::Down, _) => selected = (selected + 1) % lines.len(),
+            (KeyCode::Backspace, _) => {
+                lines[selected].cards.pop();
+            }
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                lines[selected].cards.push(c);
+            }
+            (KeyCode::Tab, _) => {
+                message = Some(match position(&lines, rules) {
+                    Ok(state) => solve(&state),
+                    Err(e) => format!("Not a position: {e}"),
+                });
+            }
+            (KeyCode::Enter, _) => match position(&lines, rules) {
+                Ok(state) => break Some(state),
+                Err(e) => message = Some(format!("Not a position: {e}")),
+            },
+            _ => {}
+        }
+    };
+
+    execute!(out, ResetColor, cursor::Show, LeaveAlternateScreen).unwrap();
+    disable_raw_mode().unwrap();
+
+    played
+}
+
+// Empty layout with the piles of the rules, when not starting from a file
+pub fn template(rules: Rules) -> String {
+    let foundations = ["H-0", "C-0", "D-0", "S-0"].repeat(rules.decks as usize);
+    let mut text = format!("Foundations: {}\n", foundations.join(" "));
+
+    if rules.cells > 0 {
+        let cells = vec!["-"; rules.cells as usize];
+        text += &format!("Freecells: {}\n", cells.join(" "));
+    }
+    if rules.game != Game::FreeCell {
+        text += "Stock:\nWaste:\n";
+    }
+    text += &":\n".repeat(rules.slots as usize);
+
+    text
+}
+
+fn read_lines(text: &str, rules: Rules) -> Vec<Line> {
+    let mut lines: Vec<Line> = LABELS
+        .into_iter()
+        .filter(|&label| match label {
+            "Freecells" => rules.cells > 0,
+            "Stock" | "Waste" => rules.game != Game::FreeCell,
+            _ => true,
+        })
+        .map(|label| Line {
+            label,
+            cards: String::new(),
+        })
+        .collect();
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (label, cards) = line.split_once(':').unwrap_or(("", line));
+        let cards = cards.trim().to_owned();
+        let label = label.trim().to_lowercase().replace("talon", "stock");
+
+        match LABELS.iter().find(|l| l.to_lowercase() == label) {
+            Some(&label) => match lines.iter_mut().find(|l| l.label == label) {
+                Some(line) => line.cards = cards,
+                None => lines.push(Line { label, cards }),
+            },
+            None => lines.push(Line { label: "", cards }),
+        }
+    }
+
+    // Labelled lines first, in the order of LABELS
+    lines.sort_by_key(|l| {
+        LABELS
+            .iter()
+            .position(|&label| label == l.label)
+            .unwrap_or(LABELS.len())
+    });
+    lines
+}
+
+fn write_lines(lines: &[Line]) -> String {
+    lines
+        .iter()
+        .map(|line| format!("{}: {}\n", line.label, line.cards))
+        .collect()
+}
+
+// Position of the layout, only once the rules could lead to it, so that
+// playing or solving it does not go wrong
+fn position(lines: &[Line], rules: Rules) -> Result<SolitareState, String> {
+    let state = board::parse(&write_lines(lines), rules)?;
+    state.check_invariants()?;
+    Ok(state)
+}
+
+fn first_slot(lines: &[Line]) -> usize {
+    lines
+        .iter()
+        .position(|l| l.label.is_empty())
+        .unwrap_or(lines.len())
+}
+
+fn solve(state: &SolitareState) -> String {
+    match solver::solve(state, solver_positions(solver::MAX_POSITIONS)) {
+        Solution::Won(moves) => format!("Winnable in {} moves", moves.len()),
+        Solution::Lost => String::from("Not winnable"),
+        Solution::GaveUp => String::from("Too large to solve"),
+    }
+}
+
+// Cards of the decks not in the layout yet, and the ones in it too often
+fn count_cards(layout: &str, decks: u8) -> (Vec<Card>, Vec<Card>) {
+    let mut counts = [0u8; 52];
+    for card in board::cards(layout) {
+        counts[card.to_ind()] += 1;
+    }
+
+    let cards = |keep: &dyn Fn(u8) -> bool| {
+        (0..52)
+            .filter(|&i| keep(counts[i]))
+            .map(Card::from_index)
+            .collect()
+    };
+
+    (cards(&|n| n < decks), cards(&|n| n > decks))
+}
+
+fn names(cards: &[Card]) -> String {
+    let names: Vec<_> = cards.iter().map(Card::name).collect();
+    names.join(" ")
+}
+
+fn draw(
+    out: &mut Stdout,
+    lines: &[Line],
+    selected: usize,
+    rules: Rules,
+    message: Option<String>,
+) -> io::Result<()> {
+    let (width, _) = terminal::size().unwrap_or((80, 24));
+    let fit = |text: String| -> String {
+        text.chars().take(width as usize).collect()
+    };
+
+    theme::clear(out)?;
+    queue!(
+        out,
+        cursor::MoveTo(0, 0),
+        Print(theme::plain("Deal editor").bold())
+    )?;
+
+    let mut row = FIRST_ROW;
+    for (i, line) in lines.iter().enumerate() {
+        let text = fit(format!("{}: {}", line.label, line.cards));
+
+        queue!(out, cursor::MoveTo(0, row))?;
+        if i == selected {
+            let selected = theme::current().selected;
+            queue!(out, Print(theme::plain(format!("{text}_")).on(selected)))?;
+        } else {
+            queue!(out, Print(theme::plain(text)))?;
+        }
+        row += 1;
+    }
+    row += 1;
+
+    // What is left to place, and the board once it is a position
+    let layout = write_lines(lines);
+    let (missing, extra) = count_cards(&layout, rules.decks);
+    let mut info = Vec::new();
+    if !extra.is_empty() {
+        info.push(format!("Too many: {}", names(&extra)));
+    }
+    if !missing.is_empty() {
+        info.push(format!("Not placed: {}", names(&missing)));
+    }
+    match position(lines, rules) {
+        Ok(state) => info.extend(state.plain_text().lines().map(str::to_owned)),
+        Err(e) => info.push(format!("Not a position: {e}")),
+    }
+    for line in info {
+        queue!(out, cursor::MoveTo(0, row), Print(theme::dim(fit(line))))?;
+        row += 1;
+    }
+
+    if let Some(message) = message {
+        row += 1;
+        queue!(out, cursor::MoveTo(0, row), Print(theme::plain(message)))?;
+    }
+
+    let help = [
+        "Cards like KS or TH, written in lowercase when face down",
+        "Arrows pick a line, ctrl+n adds a slot and ctrl+d removes it",
+        "Enter plays, tab solves, ctrl+s saves, esc quits",
+    ];
+    for (i, help) in help.into_iter().enumerate() {
+        let help = theme::dim(fit(help.to_owned()));
+        queue!(out, cursor::MoveTo(0, row + 2 + i as u16), Print(help))?;
+    }
+
+    out.flush()
+}
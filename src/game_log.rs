@@ -0,0 +1,53 @@
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use solitare::Rules;
+
+use crate::history::History;
+
+// Line of the game log, written for every game when it ends, for looking
+// through one's games with other tools
+#[derive(Serialize)]
+struct Entry<'a> {
+    timestamp: u64, // Seconds since 1970, when the game ended
+    seed: Option<u64>,
+    deal: Option<String>, // Share code, unless the board was read from a file
+    rules: Rules,
+    result: &'a str,
+    seconds: u64,
+    moves: usize,
+    move_list: Vec<&'a str>,
+}
+
+// Appends a game to the log as a line of JSON. The result tells how it
+// ended: won, restarted, new deal or quit.
+pub fn append(
+    path: &Path,
+    seed: Option<u64>,
+    history: &History,
+    result: &str,
+    seconds: u64,
+) -> io::Result<()> {
+    let initial = history.initial();
+    let entry = Entry {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        seed,
+        deal: initial.fresh_share_code(),
+        rules: initial.rules(),
+        result,
+        seconds,
+        moves: history.pos(),
+        move_list: (0..history.pos()).map(|i| history.notation(i)).collect(),
+    };
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)
+}
@@ -0,0 +1,382 @@
+// Window frontend, drawing the cards with macroquad and played with the
+// mouse:
+//
+//   solitare-gui [game] [seed]
+//
+// Click a card and then where it goes, or the same card again to send it
+// to its foundation. In Pyramid, click two cards adding up to 13, or a king
+// twice. It only uses the engine of the library, as the terminal frontend
+// does, so the rules and moves are the same in both.
+
+use macroquad::prelude::*;
+
+use solitare::{
+    Card, Location, Move, Rules, SolitareState, rules::Game, solver,
+};
+
+const TABLE: Color = Color::new(0.05, 0.35, 0.15, 1.0);
+const RED: Color = Color::new(0.8, 0.1, 0.1, 1.0);
+const BACK: Color = Color::new(0.15, 0.3, 0.65, 1.0);
+const SELECTED: Color = Color::new(1.0, 0.85, 0.2, 1.0);
+const HINT: Color = Color::new(0.3, 0.9, 1.0, 1.0);
+
+// Somewhere on the table a card can be, or be put
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Spot {
+    Stock,
+    At(Location),
+}
+
+// A card, or the empty place for one, where it is drawn
+struct Place {
+    spot: Spot,
+    rect: Rect,
+    card: Option<Card>,
+    face_up: bool,
+}
+
+struct Table {
+    game: Game,
+    seed: u64,
+    state: SolitareState,
+    undo: Vec<SolitareState>, // Positions before each move made
+    selected: Option<Location>,
+    hint: Option<[Spot; 2]>, // Shown until the next click
+}
+
+impl Table {
+    fn new(game: Game, seed: u64) -> Self {
+        Self {
+            game,
+            seed,
+            state: SolitareState::new(seed, Rules::for_game(game)),
+            undo: Vec::new(),
+            selected: None,
+            hint: None,
+        }
+    }
+
+    fn play(&mut self, m: Move) {
+        let before = self.state;
+        if self.state.apply_move(m).is_ok() {
+            self.undo.push(before);
+        }
+    }
+
+    fn click(&mut self, spot: Spot) {
+        self.hint = None;
+
+        let location = match spot {
+            Spot::Stock => {
+                self.selected = None;
+                return self.play(Move::Draw);
+            }
+            Spot::At(location) => location,
+        };
+
+        match self.selected.take() {
+            None => {
+                if self.state.is_location_valid(location)[0] {
+                    self.selected = Some(location);
+                }
+            }
+            // The same card again goes to its foundation, or is removed on
+            // its own in Pyramid
+            Some(from) if from == location && self.game != Game::Pyramid => {
+                if let Ok(m) =
+                    self.state.move_between(from, Location::Target(0))
+                {
+                    self.play(m);
+                }
+            }
+            Some(from) => {
+                if let Ok(m) = self.state.move_between(from, location) {
+                    self.play(m);
+                }
+            }
+        }
+    }
+
+    fn show_hint(&mut self) {
+        self.selected = None;
+        self.hint = solver::hint(&self.state).map(|m| {
+            match self.state.move_locations(m) {
+                [Some(from), Some(to)] => [Spot::At(from), Spot::At(to)],
+                [Some(from), None] => [Spot::At(from); 2],
+                _ => [Spot::Stock; 2],
+            }
+        });
+    }
+
+    fn input(&mut self) {
+        if is_mouse_button_pressed(MouseButton::Left) {
+            let (x, y) = mouse_position();
+            let places = self.places();
+            match places.iter().rev().find(|p| p.rect.contains(vec2(x, y))) {
+                Some(place) => self.click(place.spot),
+                None => self.selected = None,
+            }
+        }
+
+        if is_key_pressed(KeyCode::U) {
+            if let Some(before) = self.undo.pop() {
+                self.state = before;
+                self.selected = None;
+            }
+        } else if is_key_pressed(KeyCode::H) {
+            self.show_hint();
+        } else if is_key_pressed(KeyCode::N) {
+            *self = Self::new(self.game, self.seed.wrapping_add(1));
+        }
+    }
+
+    // Everything on the table, in the order it is drawn, so the last
+    // place under the mouse is the card on top
+    fn places(&self) -> Vec<Place> {
+        let state = &self.state;
+        let n_slots = state.n_slots();
+        let has_stock = self.game != Game::FreeCell;
+        let has_waste = has_stock && self.game != Game::Spider;
+        let n_foundations = match self.game {
+            Game::Pyramid => 0,
+            _ => state.n_foundations(),
+        };
+
+        let top_cols = has_stock as usize
+            + has_waste as usize
+            + state.n_cells()
+            + n_foundations;
+        let n_cols = n_slots.max(top_cols) as f32;
+
+        // Card size fitting the columns in the width of the window, and
+        // the rows of a pyramid or a few cards of the slots in its height
+        let rows = match self.game {
+            Game::Pyramid => 1.0 + (n_slots + 1) as f32 / 2.0,
+            _ => 3.0,
+        };
+        let w = (screen_width() / (n_cols * 1.15 + 0.15))
+            .min(screen_height() / (rows * 1.4 + 0.6));
+        let (h, gap) = (w * 1.4, w * 0.15);
+        let col_x = |col: usize| gap + col as f32 * (w + gap);
+        let card = |col: usize, y: f32| Rect::new(col_x(col), y, w, h);
+
+        let mut places = Vec::new();
+        let mut place = |spot, rect, card, face_up| {
+            places.push(Place {
+                spot,
+                rect,
+                card,
+                face_up,
+            });
+        };
+
+        let mut col = 0;
+        if has_stock {
+            place(Spot::Stock, card(0, gap), state.stock_cards().next(), false);
+            col += 1;
+        }
+        if has_waste {
+            let spot = Spot::At(Location::Waste);
+            place(spot, card(1, gap), state.waste_top(), true);
+            col += 1;
+        }
+        for i in 0..state.n_cells() {
+            let spot = Spot::At(Location::Cell(i as u8));
+            place(spot, card(col + i, gap), state.cell(i), true);
+        }
+        // Foundations to the right
+        let first = n_cols as usize - n_foundations;
+        for i in 0..n_foundations {
+            let spot = Spot::At(Location::Target(i as u8));
+            place(spot, card(first + i, gap), state.target(i as u8), true);
+        }
+
+        let slots_top = 2.0 * gap + h + gap;
+
+        if self.game == Game::Pyramid {
+            // Rows overlapping by half, centered
+            let center = screen_width() / 2.0;
+            for row in 0..n_slots {
+                let left = center - (row + 1) as f32 * (w + gap) / 2.0;
+                for i in 0..=row {
+                    let Some(c) = state.pyramid_card(row, i) else {
+                        continue;
+                    };
+                    let x = left + gap / 2.0 + i as f32 * (w + gap);
+                    let y = slots_top + row as f32 * h / 2.0;
+                    let spot = Spot::At(Location::Slot(row as u8, i as u8));
+                    place(spot, Rect::new(x, y, w, h), Some(c), true);
+                }
+            }
+            return places;
+        }
+
+        // Slots fanned downwards, closer together when they would not fit
+        let status_top = screen_height() - 2.0 * gap;
+        for col in 0..n_slots {
+            let len = state.slot_len(col) as usize;
+            let hidden = state.slot_hidden(col) as usize;
+            let room = (status_top - slots_top - h) / len.max(2) as f32;
+            let (down, up) = ((h * 0.12).min(room), (h * 0.25).min(room));
+
+            let mut y = slots_top;
+            if len == 0 {
+                let spot = Spot::At(Location::Slot(col as u8, 0));
+                place(spot, card(col, y), None, true);
+            }
+            for row in 0..len {
+                let spot = Spot::At(Location::Slot(col as u8, row as u8));
+                let c = state.slot_card(col, row);
+                place(spot, card(col, y), Some(c), row >= hidden);
+                y += if row < hidden { down } else { up };
+            }
+        }
+
+        places
+    }
+
+    fn draw(&self) {
+        clear_background(TABLE);
+
+        for place in self.places() {
+            let Rect { x, y, w, h } = place.rect;
+
+            match place.card {
+                None => draw_rectangle_lines(x, y, w, h, 2.0, DARKGREEN),
+                Some(_) if !place.face_up => {
+                    draw_rectangle(x, y, w, h, BACK);
+                    draw_rectangle_lines(x, y, w, h, 2.0, WHITE);
+                }
+                Some(card) => draw_card(place.rect, card),
+            }
+
+            let highlight = if self.selected.map(Spot::At) == Some(place.spot) {
+                Some(SELECTED)
+            } else if self.hint.is_some_and(|hint| hint.contains(&place.spot)) {
+                Some(HINT)
+            } else {
+                None
+            };
+            if let Some(color) = highlight {
+                draw_rectangle_lines(x, y, w, h, 4.0, color);
+            }
+        }
+
+        let state = &self.state;
+        let status = if state.is_won() {
+            String::from("You won! N for a new deal")
+        } else {
+            format!(
+                "{}  #{}  Score {}  Stock {}    U undo  H hint  N new deal",
+                self.game.title(),
+                self.seed,
+                state.score(),
+                state.stock_len(),
+            )
+        };
+        draw_text(&status, 10.0, screen_height() - 12.0, 24.0, WHITE);
+    }
+}
+
+// Face of a card: rank and suit in the corner and a large suit in the
+// middle
+fn draw_card(rect: Rect, card: Card) {
+    let Rect { x, y, w, h } = rect;
+    let color = if card.is_red() { RED } else { BLACK };
+
+    draw_rectangle(x, y, w, h, WHITE);
+    draw_rectangle_lines(x, y, w, h, 2.0, GRAY);
+
+    let rank = solitare::card::RANK_NAMES[card.rank() as usize - 1];
+    let size = w * 0.32;
+    draw_text(rank, x + w * 0.08, y + size * 0.8, size, color);
+    draw_suit(card.suit(), x + w * 0.18, y + size * 1.25, w * 0.1, color);
+    draw_suit(card.suit(), x + w / 2.0, y + h * 0.6, w * 0.22, color);
+}
+
+// Suit symbol put together from circles and triangles, as the default font
+// only has ASCII
+fn draw_suit(suit: u8, x: f32, y: f32, s: f32, color: Color) {
+    let stem = || {
+        draw_triangle(
+            vec2(x, y),
+            vec2(x - 0.35 * s, y + s),
+            vec2(x + 0.35 * s, y + s),
+            color,
+        )
+    };
+
+    match suit {
+        // ♠
+        0 => {
+            draw_circle(x - 0.45 * s, y + 0.2 * s, 0.5 * s, color);
+            draw_circle(x + 0.45 * s, y + 0.2 * s, 0.5 * s, color);
+            draw_triangle(
+                vec2(x - 0.93 * s, y + 0.05 * s),
+                vec2(x + 0.93 * s, y + 0.05 * s),
+                vec2(x, y - s),
+                color,
+            );
+            stem();
+        }
+        // ♥
+        1 => {
+            draw_circle(x - 0.45 * s, y - 0.3 * s, 0.5 * s, color);
+            draw_circle(x + 0.45 * s, y - 0.3 * s, 0.5 * s, color);
+            draw_triangle(
+                vec2(x - 0.93 * s, y - 0.15 * s),
+                vec2(x + 0.93 * s, y - 0.15 * s),
+                vec2(x, y + s),
+                color,
+            );
+        }
+        // ♣
+        2 => {
+            draw_circle(x, y - 0.45 * s, 0.38 * s, color);
+            draw_circle(x - 0.45 * s, y + 0.1 * s, 0.38 * s, color);
+            draw_circle(x + 0.45 * s, y + 0.1 * s, 0.38 * s, color);
+            draw_circle(x, y, 0.25 * s, color);
+            stem();
+        }
+        // ♦
+        _ => {
+            let (left, right) = (vec2(x - 0.7 * s, y), vec2(x + 0.7 * s, y));
+            draw_triangle(vec2(x, y - s), left, right, color);
+            draw_triangle(vec2(x, y + s), left, right, color);
+        }
+    }
+}
+
+fn window_conf() -> Conf {
+    Conf {
+        window_title: String::from("Solitaire"),
+        window_width: 1000,
+        window_height: 760,
+        high_dpi: true,
+        ..Default::default()
+    }
+}
+
+#[macroquad::main(window_conf)]
+async fn main() {
+    let mut args = std::env::args().skip(1);
+    let game = match args.next() {
+        Some(name) => Game::from_name(&name).unwrap_or_else(|| {
+            eprintln!("Unknown game: {name}");
+            std::process::exit(2);
+        }),
+        None => Game::Klondike,
+    };
+    let seed = args
+        .next()
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or_else(::rand::random);
+
+    let mut table = Table::new(game, seed);
+
+    loop {
+        table.input();
+        table.draw();
+        next_frame().await;
+    }
+}
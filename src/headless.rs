@@ -0,0 +1,190 @@
+use std::io::{self, BufRead, Write};
+
+use serde_json::{Value, json};
+
+use solitare::{Card, Location, Move, SolitareState, rules::Game};
+
+// Plays a game from commands read from stdin, one per line, printing the
+// board after each of them. The game ends when it is won, at `quit` or at
+// the end of the input. True if it was won.
+//
+//   draw            Draws from the stock, `d` for short
+//   move FROM TO    Moves cards, like `move s3 s5`, `move w f` or `move c1
+//                   f`. Places are s for a slot, with the row after a colon
+//                   when not the lowest one that can move, w for the waste,
+//                   f for a foundation and c for a free cell, numbered from
+//                   1. In Pyramid s7:2 is the second card of the last row.
+//   show            Prints the board again
+//   quit            Ends the game
+//
+// Moves can also be given in the notation of records, like `W>S5`.
+pub fn run(mut state: SolitareState, json: bool) -> bool {
+    let mut out = io::stdout().lock();
+    let mut moves = 0;
+
+    print_state(&mut out, &state, moves, json).unwrap();
+
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        } else if line == "quit" {
+            break;
+        } else if line == "show" {
+            print_state(&mut out, &state, moves, json).unwrap();
+            continue;
+        }
+
+        let result = parse(&state, line).and_then(|m| {
+            state.apply_move(m).map_err(|e| format!("{line}: {e}"))
+        });
+
+        match result {
+            Ok(()) => {
+                moves += 1;
+                print_state(&mut out, &state, moves, json).unwrap();
+            }
+            Err(e) if json => {
+                writeln!(out, "{}", json!({ "error": e })).unwrap()
+            }
+            Err(e) => writeln!(out, "Error: {e}").unwrap(),
+        }
+
+        if state.is_won() {
+            break;
+        }
+    }
+
+    state.is_won()
+}
+
+// Move given by a command
+fn parse(state: &SolitareState, command: &str) -> Result<Move, String> {
+    let words: Vec<_> = command.split_whitespace().collect();
+    let unknown = || format!("{command}: unknown command");
+
+    match words[..] {
+        ["draw" | "d"] => Ok(Move::Draw),
+        ["move" | "m", from, to] => {
+            let place = |word| {
+                location(state, word)
+                    .ok_or_else(|| format!("{command}: no place {word}"))
+            };
+            let (from, to) = (place(from)?, place(to)?);
+
+            // Without a row, the lowest cards of the slot that can go there
+            // are moved
+            if let Location::Slot(col, _) = from
+                && !words[1].contains(':')
+                && state.rules().game != Game::Pyramid
+            {
+                let (hidden, len) = (
+                    state.slot_hidden(col as usize),
+                    state.slot_len(col as usize),
+                );
+                let legal = (hidden..len)
+                    .filter_map(|row| {
+                        state.move_between(Location::Slot(col, row), to).ok()
+                    })
+                    .find(|&m| state.check_move(m).is_ok());
+
+                if let Some(m) = legal {
+                    return Ok(m);
+                }
+            }
+
+            state
+                .move_between(from, to)
+                .map_err(|e| format!("{command}: {e}"))
+        }
+        [notation] => Move::from_notation(notation).ok_or_else(unknown),
+        _ => Err(unknown()),
+    }
+}
+
+// Place named like `s3`, `s3:4`, `w`, `f`, `f2` or `c1`. Slots without a
+// row are their top card.
+fn location(state: &SolitareState, word: &str) -> Option<Location> {
+    let (kind, rest) = word.split_at_checked(1)?;
+    let number = |s: &str| s.parse::<u8>().ok()?.checked_sub(1);
+    let slot = |s: &str| number(s).filter(|&col| col < state.n_slots() as u8);
+
+    match kind.to_ascii_lowercase().as_str() {
+        "w" if rest.is_empty() => Some(Location::Waste),
+        "f" if rest.is_empty() => Some(Location::Target(0)),
+        "f" => Some(Location::Target(number(rest)?)),
+        "c" => Some(Location::Cell(
+            number(rest).filter(|&i| (i as usize) < state.n_cells())?,
+        )),
+        "s" => match rest.split_once(':') {
+            Some((col, row)) => Some(Location::Slot(slot(col)?, number(row)?)),
+            None if state.rules().game == Game::Pyramid => None,
+            None => {
+                let col = slot(rest)?;
+                let len = state.slot_len(col as usize);
+                Some(Location::Slot(col, len.saturating_sub(1)))
+            }
+        },
+        _ => None,
+    }
+}
+
+fn print_state(
+    out: &mut impl Write,
+    state: &SolitareState,
+    moves: usize,
+    json: bool,
+) -> io::Result<()> {
+    if json {
+        return writeln!(out, "{}", to_json(state, moves));
+    }
+
+    writeln!(out, "{}", state.plain_text())
+}
+
+// Board as a single line of JSON, with cards named like "10♥"
+pub fn to_json(state: &SolitareState, moves: usize) -> Value {
+    let name = |card: Option<Card>| card.map(|card| card.name());
+
+    let slots: Vec<Value> = if state.rules().game == Game::Pyramid {
+        (0..state.n_slots())
+            .map(|row| {
+                json!(
+                    (0..=row)
+                        .map(|i| name(state.pyramid_card(row, i)))
+                        .collect::<Vec<_>>()
+                )
+            })
+            .collect()
+    } else {
+        (0..state.n_slots())
+            .map(|col| {
+                let hidden = state.slot_hidden(col);
+                let cards: Vec<_> = (hidden..state.slot_len(col))
+                    .map(|row| state.slot_card(col, row as usize).name())
+                    .collect();
+                json!({ "hidden": hidden, "cards": cards })
+            })
+            .collect()
+    };
+
+    json!({
+        "game": state.rules().game.name(),
+        "foundations": (0..state.n_foundations() as u8)
+            .map(|i| name(state.target(i)))
+            .collect::<Vec<_>>(),
+        "cells": (0..state.n_cells())
+            .map(|i| name(state.cell(i)))
+            .collect::<Vec<_>>(),
+        "stock": state.stock_len(),
+        "waste": state.waste_cards().map(|card| card.name()).collect::<Vec<_>>(),
+        "slots": slots,
+        "moves": moves,
+        "score": state.score(),
+        "won": state.is_won(),
+    })
+}
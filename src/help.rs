@@ -0,0 +1,163 @@
+use crossterm::style::Stylize;
+
+use solitare::{
+    Rules,
+    rules::{Game, Scoring},
+};
+
+use crate::{CLI, CONFIG};
+
+// Text of the help shown over the board: the controls, with the keys as
+// they are set in the config, and the rules of the game being played
+pub fn lines(rules: Rules) -> Vec<String> {
+    let keys = &CONFIG.keys;
+
+    let mut lines = vec![
+        "Controls".bold().to_string(),
+        String::new(),
+        "Click a card to pick it up, then click where it goes".to_owned(),
+        "Drag cards with the mouse to move them".to_owned(),
+        "Double or right click a card to play it to its foundation".to_owned(),
+        "Arrow keys move the cursor, enter or space picks up and places"
+            .to_owned(),
+    ];
+    if CONFIG.vim_keys {
+        lines.push("h, j, k and l move the cursor, after a count".to_owned());
+    }
+    if CLI.play.debug || CLI.play.practice {
+        lines.push("ctrl+d shows and hides the face down cards".to_owned());
+    }
+    lines.push(String::new());
+
+    let bindings = [
+        (
+            format!("{} {}", key_name(keys.draw), key_name(keys.draw_alt)),
+            "Draw from the stock",
+        ),
+        (format!("{} ctrl+z", key_name(keys.undo)), "Undo"),
+        (format!("{} ctrl+y", key_name(keys.redo)), "Redo"),
+        (key_name(keys.restart), "Restart the deal"),
+        (key_name(keys.new_deal), "New deal"),
+        (key_name(keys.hint), "Hint"),
+        (
+            key_name(keys.solution),
+            "Show the winning line move by move",
+        ),
+        (key_name(keys.safe_moves), "Play the safe moves"),
+        (
+            key_name(keys.finish),
+            "Finish the game when every card is up",
+        ),
+        (
+            key_name(keys.one_tap),
+            "Single clicks play to the foundations",
+        ),
+        (key_name(keys.history), "Show the move history"),
+        (key_name(keys.hidden_cards), "Show the cards still hidden"),
+        (key_name(keys.scrub), "Step through the history"),
+        (key_name(keys.save), "Save the game"),
+        (key_name(keys.copy_board), "Copy the board"),
+        (key_name(keys.copy_code), "Copy the share code of the deal"),
+        (key_name(keys.copy_moves), "Copy the moves made"),
+        (
+            key_name(keys.copy_replay),
+            "Copy the game as a replay string",
+        ),
+        (
+            key_name(keys.export_deal),
+            "Write the deal to a file and copy it",
+        ),
+        (
+            String::from("esc"),
+            "Put back cards, twice for the pause menu",
+        ),
+        (key_name(keys.help), "This help"),
+        (format!("{} ctrl+c", key_name(keys.quit)), "Quit"),
+    ];
+    for (key, action) in bindings {
+        lines.push(format!("{key:<10} {action}"));
+    }
+
+    lines.push(String::new());
+    lines.push(
+        format!("Rules of {}", rules.game.title())
+            .bold()
+            .to_string(),
+    );
+    lines.push(String::new());
+    lines.push(rules.game.summary().to_owned());
+    lines.extend(rule_lines(rules));
+
+    lines
+}
+
+// The rules that can be changed, one per line
+fn rule_lines(rules: Rules) -> Vec<String> {
+    let game = rules.game;
+    let mut lines = Vec::new();
+
+    let decks = if rules.decks == 1 { "deck" } else { "decks" };
+    let slots = if game == Game::Pyramid {
+        "rows"
+    } else {
+        "slots"
+    };
+    lines.push(format!("{} {decks}, {} {slots}", rules.decks, rules.slots));
+
+    if rules.suits < 4 {
+        lines.push(format!("{} suits", rules.suits));
+    }
+    if rules.cells > 0 {
+        lines.push(format!("{} free cells", rules.cells));
+    }
+
+    // Games with a waste draw from the stock and turn it over
+    if !matches!(game, Game::Spider | Game::FreeCell) {
+        let cards = if rules.draw == 1 { "card" } else { "cards" };
+        lines.push(format!("Draw {} {cards} at a time", rules.draw));
+        lines.push(match rules.max_passes {
+            Some(1) => "A single pass through the stock".to_owned(),
+            Some(passes) => format!("{passes} passes through the stock"),
+            None => "Any number of passes through the stock".to_owned(),
+        });
+    }
+
+    if matches!(game, Game::Klondike | Game::FortyThieves) {
+        lines.push(if rules.any_card_on_empty_slot {
+            "Any card can go on an empty slot".to_owned()
+        } else {
+            "Only kings can go on an empty slot".to_owned()
+        });
+    }
+    if rules.foundation_to_slot {
+        lines.push("Cards can be taken back off the foundations".to_owned());
+    }
+    if rules.thoughtful {
+        lines.push("Every card is face up".to_owned());
+    }
+    if rules.run_limit || game == Game::FreeCell {
+        lines.push(
+            "Runs are moved as far as the free cells and empty slots allow"
+                .to_owned(),
+        );
+    }
+
+    lines.push(
+        match rules.scoring {
+            Scoring::Standard => "Standard scoring",
+            Scoring::Vegas => "Vegas scoring",
+            Scoring::None => "No scoring",
+        }
+        .to_owned(),
+    );
+
+    lines
+}
+
+// Key as written in the help, like "space"
+pub fn key_name(key: char) -> String {
+    match key {
+        ' ' => "space".to_owned(),
+        key => key.to_string(),
+    }
+}
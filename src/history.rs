@@ -0,0 +1,123 @@
+use solitare::SolitareState;
+
+struct Node {
+    notation: String, // Move leading to this node, empty for the root
+    state: SolitareState,
+    children: Vec<usize>,
+    last_child: usize, // Index into children of the most recently visited
+}
+
+// Tree of every position reached in the game. Making a move after jumping
+// back starts a new branch instead of throwing the old one away. The
+// current line runs from the root through the most recently visited
+// children, so jumping back and forth along it works like undo/redo.
+pub struct History {
+    nodes: Vec<Node>,
+    line: Vec<usize>, // Node indices from the root to the end of the line
+    pos: usize,       // Index into line of the current position
+}
+
+impl History {
+    pub fn new(initial: SolitareState) -> Self {
+        Self {
+            nodes: vec![Node {
+                notation: String::new(),
+                state: initial,
+                children: Vec::new(),
+                last_child: 0,
+            }],
+            line: vec![0],
+            pos: 0,
+        }
+    }
+
+    pub fn push(&mut self, notation: String, state: SolitareState) {
+        let parent = self.line[self.pos];
+        let node = self.nodes.len();
+
+        self.nodes.push(Node {
+            notation,
+            state,
+            children: Vec::new(),
+            last_child: 0,
+        });
+
+        let parent = &mut self.nodes[parent];
+        parent.last_child = parent.children.len();
+        parent.children.push(node);
+
+        self.line.truncate(self.pos + 1);
+        self.line.push(node);
+        self.pos += 1;
+    }
+
+    // Jump to the position after the first `pos` moves of the current line
+    pub fn jump(&mut self, pos: usize) -> SolitareState {
+        self.pos = pos.min(self.len());
+        self.current()
+    }
+
+    // Switch the current position to a sibling branch, `delta` steps away
+    // among the alternative moves played from the previous position. The
+    // rest of the line follows the most recently visited children.
+    pub fn switch_branch(&mut self, delta: isize) -> SolitareState {
+        if self.pos == 0 {
+            return self.current();
+        }
+
+        let parent = &mut self.nodes[self.line[self.pos - 1]];
+        let n = parent.children.len() as isize;
+        parent.last_child =
+            (parent.last_child as isize + delta).rem_euclid(n) as usize;
+
+        self.line.truncate(self.pos);
+
+        let mut node = self.line[self.pos - 1];
+        while let Some(&child) =
+            self.nodes[node].children.get(self.nodes[node].last_child)
+        {
+            self.line.push(child);
+            node = child;
+        }
+
+        self.current()
+    }
+
+    // Number of branches at the current position and the index of the one
+    // currently followed
+    pub fn branches(&self) -> (usize, usize) {
+        if self.pos == 0 {
+            (1, 0)
+        } else {
+            let parent = &self.nodes[self.line[self.pos - 1]];
+            (parent.children.len(), parent.last_child)
+        }
+    }
+
+    pub fn initial(&self) -> SolitareState {
+        self.nodes[0].state
+    }
+
+    pub fn current(&self) -> SolitareState {
+        self.state(self.pos)
+    }
+
+    // State after the first `pos` moves of the current line
+    pub fn state(&self, pos: usize) -> SolitareState {
+        self.nodes[self.line[pos]].state
+    }
+
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    // Number of moves in the current line
+    pub fn len(&self) -> usize {
+        self.line.len() - 1
+    }
+
+    // Notation of move i in the current line
+    pub fn notation(&self, i: usize) -> &str {
+        &self.nodes[self.line[i + 1]].notation
+    }
+}
@@ -0,0 +1,92 @@
+use solitare::{SolitareState, rules::Game};
+
+use crate::CONFIG;
+
+// Columns of the piles in the top row, the foundations on one side of a
+// separator and the stock and waste, or the free cells, on the other.
+// Drawing the board, clicks and the cursor all go by it.
+#[derive(Debug, Clone, Copy)]
+pub struct Layout {
+    pub foundations: u16,       // First foundation
+    pub separator: Option<u16>, // None when stacked
+    pub stock: u16,             // Also the first free cell
+    pub waste: u16,             // Fanning out to the right
+    pub stock_row: u16,         // Below the foundations when stacked
+    pub slots_row: u16,         // First card in the slots, after a gap
+    pub width: u16,             // Of the whole top
+}
+
+impl Layout {
+    // Foundations on the left, unless the board is mirrored
+    pub fn new(state: &SolitareState) -> Self {
+        let w = CONFIG.card_width();
+        let foundations_width = state.n_foundations() as u16 * w;
+
+        // The stock, a gap and the waste, fanning out up to three cards
+        let stock_width = match state.rules().game {
+            Game::FreeCell => state.n_cells() as u16 * w,
+            Game::Spider => 2 * w + "Deals left 10".len() as u16,
+            _ => 5 * w,
+        };
+        let width = foundations_width + 3 + stock_width;
+
+        let (foundations, stock) = if CONFIG.mirrored {
+            (stock_width + 3, 0)
+        } else {
+            (0, foundations_width + 3)
+        };
+
+        Self {
+            foundations,
+            separator: Some(foundations.max(stock) - 2),
+            stock,
+            waste: stock + 2 * w,
+            stock_row: 0,
+            slots_row: CONFIG.card_height() + 1,
+            width,
+        }
+    }
+
+    // The usual layout when it is no wider than the terminal, and otherwise
+    // the stock row below the foundations, with no separator or gaps, for
+    // split panes and phones
+    pub fn fitting(state: &SolitareState, columns: u16) -> Self {
+        let layout = Self::new(state);
+        if layout.width <= columns {
+            return layout;
+        }
+
+        let w = CONFIG.card_width();
+        let h = CONFIG.card_height();
+        let stock_width = match state.rules().game {
+            Game::FreeCell => state.n_cells() as u16 * w,
+            Game::Spider => w + "Deals left 10".len() as u16,
+            _ => 4 * w,
+        };
+
+        Self {
+            foundations: 0,
+            separator: None,
+            stock: 0,
+            waste: w,
+            stock_row: h,
+            slots_row: 2 * h + 1,
+            width: stock_width.max(state.n_foundations() as u16 * w),
+        }
+    }
+
+    pub fn stacked(&self) -> bool {
+        self.separator.is_none()
+    }
+
+    // Column of a foundation
+    pub fn foundation(&self, i: u8) -> u16 {
+        self.foundations + i as u16 * CONFIG.card_width()
+    }
+
+    // Foundation at a column, if there is one there
+    pub fn foundation_at(&self, state: &SolitareState, col: u16) -> Option<u8> {
+        let i = col.checked_sub(self.foundations)? / CONFIG.card_width();
+        (i < state.n_foundations() as u16).then_some(i as u8)
+    }
+}
@@ -0,0 +1,176 @@
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
+
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+
+use solitare::{Rules, rules::Game};
+
+// Games shown of each variant, by time and by moves
+pub const PLACES: usize = 10;
+
+// A won game
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub seconds: u64,
+    pub moves: usize,
+    pub date: String,
+    pub seed: Option<u64>, // None for deals from a share code
+}
+
+// Won games of every variant, by the name from `variant`. Only the games
+// making either list are kept.
+pub type Leaderboard = BTreeMap<String, Vec<Entry>>;
+
+// Kept with the results of the daily deals
+fn path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("solitare").join("leaderboard.json"))
+}
+
+pub fn load() -> Leaderboard {
+    path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+// Saves a won game of a variant, by its name from `variant`
+pub fn record(variant: &str, entry: Entry) -> io::Result<()> {
+    let Some(path) = path() else {
+        return Ok(());
+    };
+
+    let mut leaderboard = load();
+    let entries = leaderboard.entry(variant.to_owned()).or_default();
+    entries.push(entry);
+
+    let (by_time, by_moves) = (by_time(entries), by_moves(entries));
+    let kept: Vec<_> = entries
+        .iter()
+        .filter(|&e| by_time.contains(&e) || by_moves.contains(&e))
+        .cloned()
+        .collect();
+    *entries = kept;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(&leaderboard)?)
+}
+
+// Fastest games first, the fewer moves first for the same time
+fn by_time(entries: &[Entry]) -> Vec<&Entry> {
+    let mut sorted: Vec<_> = entries.iter().collect();
+    sorted.sort_by_key(|e| (e.seconds, e.moves));
+    sorted.truncate(PLACES);
+    sorted
+}
+
+fn by_moves(entries: &[Entry]) -> Vec<&Entry> {
+    let mut sorted: Vec<_> = entries.iter().collect();
+    sorted.sort_by_key(|e| (e.moves, e.seconds));
+    sorted.truncate(PLACES);
+    sorted
+}
+
+// Name of the game with how the rules differ from its standard ones, like
+// "Klondike, draw 3". Games are only compared with others of the same
+// rules, apart from scoring.
+pub fn variant(rules: Rules) -> String {
+    let standard = match rules.game {
+        // Spider is played with any number of suits
+        Game::Spider => Rules::spider(rules.suits),
+        game => Rules::for_game(game),
+    };
+    let mut parts = vec![rules.game.title().to_owned()];
+
+    if rules.game == Game::Spider && rules.suits < 4 {
+        let suits = if rules.suits == 1 { "suit" } else { "suits" };
+        parts.push(format!("{} {suits}", rules.suits));
+    }
+    if rules.decks != standard.decks {
+        parts.push(format!("{} decks", rules.decks));
+    }
+    if rules.slots != standard.slots {
+        let slots = if rules.game == Game::Pyramid {
+            "rows"
+        } else {
+            "slots"
+        };
+        parts.push(format!("{} {slots}", rules.slots));
+    }
+    if rules.cells != standard.cells {
+        parts.push(format!("{} free cells", rules.cells));
+    }
+    if rules.draw != standard.draw {
+        parts.push(format!("draw {}", rules.draw));
+    }
+    if rules.max_passes != standard.max_passes {
+        parts.push(match rules.max_passes {
+            Some(passes) => format!("{passes} passes"),
+            None => "unlimited passes".to_owned(),
+        });
+    }
+    if rules.any_card_on_empty_slot != standard.any_card_on_empty_slot {
+        parts.push(if rules.any_card_on_empty_slot {
+            "any card on empty slots".to_owned()
+        } else {
+            "kings on empty slots".to_owned()
+        });
+    }
+    if rules.foundation_to_slot != standard.foundation_to_slot {
+        parts.push(if rules.foundation_to_slot {
+            "foundation to slot".to_owned()
+        } else {
+            "no foundation to slot".to_owned()
+        });
+    }
+    if rules.thoughtful {
+        parts.push("thoughtful".to_owned());
+    }
+    if rules.run_limit && rules.game != Game::FreeCell {
+        parts.push("run limit".to_owned());
+    }
+
+    parts.join(", ")
+}
+
+// The best times and fewest moves of a variant side by side, with a game
+// just won in bold
+pub fn lines(
+    variant: &str,
+    entries: &[Entry],
+    marked: Option<&Entry>,
+) -> Vec<String> {
+    let mut lines = vec![
+        format!("Leaderboard of {variant}").bold().to_string(),
+        String::new(),
+    ];
+
+    if entries.is_empty() {
+        lines.push("No games won yet".to_owned());
+        return lines;
+    }
+
+    let by_time = by_time(entries);
+    let by_moves = by_moves(entries);
+    lines.push(format!("{:<27}{}", "   Best times", "   Fewest moves"));
+
+    let cell = |place: usize, value: String, entry: &Entry| {
+        let text = format!("{:>2} {value:>6}  {:<16}", place + 1, entry.date);
+        if marked == Some(entry) {
+            text.bold().to_string()
+        } else {
+            text
+        }
+    };
+
+    for (place, (time, moves)) in by_time.iter().zip(&by_moves).enumerate() {
+        let seconds = format!("{}:{:02}", time.seconds / 60, time.seconds % 60);
+        lines.push(
+            cell(place, seconds, time)
+                + &cell(place, moves.moves.to_string(), moves),
+        );
+    }
+
+    lines
+}
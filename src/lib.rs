@@ -0,0 +1,18 @@
+// Klondike solitaire engine. Holds the rules and the board state, without
+// any of the terminal frontend, so it can be used by other frontends and
+// tools as well.
+
+pub mod bits;
+pub mod card;
+pub mod moves;
+pub mod rules;
+pub mod scoring;
+pub mod solitare_state;
+pub mod solver;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use card::Card;
+pub use moves::{IllegalMove, Move};
+pub use rules::Rules;
+pub use solitare_state::{Location, SolitareState};
@@ -1,7 +1,12 @@
 use std::{
-    env,
-    fmt::Display,
-    io::{Stdout, stdout},
+    collections::VecDeque,
+    fs,
+    io::{self, Stdout, stdout},
+    mem,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    thread,
+    time::{Duration, Instant},
 };
 
 use once_cell::sync::Lazy;
@@ -12,612 +17,3915 @@ use crossterm::{
         self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode,
         KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
     },
-    execute,
-    style::Stylize,
+    execute, queue,
+    style::{Print, ResetColor, Stylize},
     terminal::{
         self, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode,
         enable_raw_mode,
     },
 };
 
-pub mod solitare_state;
+use solitare::{
+    Card, IllegalMove, Location, Move, Rules, SolitareState, card,
+    rules::{Game, Scoring},
+    scoring, solitare_state,
+    solver::{self, Solution},
+};
 
-static TWICE_WIDTH: Lazy<bool> = Lazy::new(|| {
-    env::args().any(|x| matches!(x.as_str(), "-tw" | "--twice-width"))
-});
+mod animation;
+mod background;
+mod base64;
+mod board;
+mod broadcast;
+mod chance;
+mod cli;
+mod clipboard;
+mod config;
+mod daily;
+mod editor;
+mod game_log;
+mod headless;
+mod help;
+mod history;
+mod layout;
+mod leaderboard;
+mod menu;
+mod pause;
+mod puzzle;
+mod record;
+mod render;
+mod renderer;
+mod save_file;
+mod sound;
+mod theme;
+
+use animation::{Animation, Cascade};
+use background::Background;
+use broadcast::{Broadcast, Frame};
+use chance::WinChance;
+use cli::{Cli, Command};
+use config::{Config, Feedback};
+use daily::{DailyResult, Date};
+use history::History;
+use layout::Layout;
+use render::{DimmedCard, Highlight, HighlightedSolitareState, Shade, View};
+use renderer::{Renderer, TextScreen};
+use save_file::SaveFile;
+use sound::Effect;
+
+static CLI: Lazy<Cli> = Lazy::new(|| {
+    // Tests draw big cards wherever they are run, leaving out the arguments
+    // given to the test harness
+    if cfg!(test) {
+        return clap::Parser::parse_from(["solitare", "--cards", "big"]);
+    }
 
-// Card in u8:
-// suit rank
-// 0000 0000
-//    | Color (0 black, 1 red)
-//
-// Example, ♥ J:
-// 0001 1011
-struct Card(u8);
+    Cli::from_env()
+});
 
-impl Card {
-    fn from_index(i: usize) -> Self {
-        let rank = (i % 13 + 1) as u8;
-        let suit = (i / 13) as u8;
+// Settings from the config file, with the flags given on top
+static CONFIG: Lazy<Config> = Lazy::new(|| {
+    let mut config = Config::load().unwrap_or_else(|e| {
+        eprintln!("Could not load config: {e}");
+        std::process::exit(1)
+    });
 
-        Self::from_suit_rank(suit, rank)
+    if let Some(cards) = CLI.play.cards {
+        config.cards = cards;
     }
+    config.twice_width |= CLI.play.twice_width;
+    config.compact |= CLI.play.compact;
+    config.mirrored |= CLI.play.mirrored;
+    config.high_contrast |= CLI.play.high_contrast;
+    config.screen_reader |= CLI.play.screen_reader;
+    config.one_tap |= CLI.play.one_tap;
+    config.auto_move |= CLI.play.auto_move;
+    config.animations &= !CLI.play.no_animations;
+    if let Some(path) = &CLI.play.game_log {
+        config.game_log = Some(path.clone());
+    }
+    #[cfg(feature = "sound")]
+    {
+        config.sound &= !CLI.play.no_sound;
+    }
+    if CLI.rules.draw3 {
+        config.draw = 3;
+    }
+
+    config
+});
 
-    fn from_suit_rank(suit: u8, rank: u8) -> Self {
-        assert!(suit < 4 && rank <= 13);
+// Set when the game is sent SIGINT, to leave it the same way as quitting.
+// In raw mode Ctrl+C comes as a key press instead.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
 
-        Self((suit << 4) | rank)
-    }
+const SAVE_PATH: &str = "solitare_save.json";
 
-    fn to_ind(&self) -> usize {
-        (self.suit() * 13 + self.rank() - 1) as usize
-    }
+// File the deal is written to for others to play, with `solitare replay`
+const DEAL_PATH: &str = "solitare_deal.txt";
+
+// Per-user file the game is saved to after every move
+fn autosave_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("solitare").join("autosave.json"))
+}
+
+// Width of the move history panel, including the gap to the board
+const PANEL_WIDTH: u16 = 16;
+
+// Width of the panel of the cards still hidden, after the history panel
+// when both are shown. Enough for every rank of a suit.
+const CARDS_PANEL_WIDTH: u16 = 30;
+
+// Time between moves when finishing the game automatically
+const FINISH_STEP: Duration = Duration::from_millis(80);
 
-    fn rank(&self) -> u8 {
-        self.0 & 0b0000_1111
+// Seeds tried when looking for a winnable deal, each taking up to a second
+const WINNABLE_TRIES: u64 = 30;
+
+// Longest time between the clicks of a double click
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+// Longest time between two presses of Esc opening the pause menu, long
+// enough for the key to repeat when held down
+const SECOND_ESC: Duration = Duration::from_secs(1);
+
+// Time left when the clock of the time limit turns red
+const TIME_WARNING: u64 = 30;
+
+// Time the place of a move that is not allowed is shown red
+const FLASH: Duration = Duration::from_millis(300);
+
+// Time between the moves of the bot, slow enough to follow
+const BOT_STEP: Duration = Duration::from_millis(250);
+
+// Largest count typed with vim keys, more than any move of the cursor
+// across the board takes
+const MAX_COUNT: usize = 104;
+
+// Question shown below the board, answered with a key press
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Prompt {
+    DeadEnd,
+    NewDeal,
+    Resume,
+    Quit,
+}
+
+impl Prompt {
+    fn text(self) -> &'static str {
+        match self {
+            Prompt::DeadEnd => {
+                "No more useful moves! [u]ndo, [r]estart or [n]ew deal?"
+            }
+            Prompt::NewDeal => "Start a new deal? [y/n]",
+            Prompt::Resume => "Resume your unfinished game? [y/n]",
+            Prompt::Quit => "Quit the game in progress? [y/n]",
+        }
     }
+}
+
+struct GameState {
+    out: Stdout, // For what is not drawn, like the bell and the clipboard
+    screen: Box<dyn Renderer>,
+    state: SolitareState,
+    seed: Option<u64>,   // None for deals from a share code
+    daily: Option<Date>, // Day of the daily deal, when playing it
+    selected: Highlight,
+    cursor: Option<Highlight>, // Moved with the arrow keys, hidden until then
+    count: usize, // Times to repeat the next move of the cursor, for vim keys
+    hint: [Highlight; 2], // Source and destination, until the next input
+    one_tap: bool, // Single click plays a card to its foundation
+    history: History,
+    show_history: bool,
+    show_cards: bool,         // Panel of the cards still hidden
+    history_scroll: usize,    // Number of entries scrolled up from the bottom
+    scroll: u16, // Lines of the slots scrolled past, when they do not fit
+    scrubbing: Option<usize>, // History position to return to when done
+    finishing: bool, // Moving the remaining cards to the foundations
+    bot: bool,   // Playing by itself until a key is pressed
+    bot_plan: Option<Vec<Move>>, // Winning moves left, last first, once solved
+    // Winning line being shown, with the position it goes on from and the
+    // moves left, last first
+    solution: Option<(SolitareState, Vec<Move>)>,
+    message: Option<String>,
+    prompt: Option<Prompt>,
+    help: bool, // Shown over the board until a key is pressed
+    // Summary of the game just won or given up, shown over the board until
+    // a key is pressed
+    summary: Option<Vec<String>>,
+    // Line of the summary still waiting for how hard the deal was, and the
+    // lines to put there once it is worked out
+    summary_deal: Option<(usize, Background<Vec<String>>)>,
+    undos: usize, // Used this deal, for the summary
+    hints: usize,
+    move_budget: Option<usize>, // Moves allowed this deal, in the challenge
+    budget_search: Option<Background<Option<usize>>>, // Solution length
+    puzzle: Option<&'static str>, // Name of the puzzle being played
+    vegas: Option<i32>,         // Balance before this deal when playing Vegas
+    played: Duration,           // Time played before the clock was last started
+    clock_started: Option<Instant>, // Set while the clock is running
+    last_click: Option<(Instant, Highlight)>, // To tell double clicks apart
+    last_esc: Option<Instant>,  // Pressing Esc again opens the pause menu
+    dragging: Option<Highlight>, // Cards picked up by pressing on them
+    pointer: Option<(u16, u16)>, // Where dragged cards are, once moved
+    animations: VecDeque<Animation>, // Moves being shown, one after another
+    cascade: Option<Cascade>,   // Shown after a win until skipped
+    flash: Option<(Highlight, Instant)>, // Where a move was not allowed
+    broadcast: Option<Broadcast>, // Viewers sent the board, if any
+    win_chance: Option<WinChance>, // Estimate shown, if switched on
+}
 
-    fn suit(&self) -> u8 {
-        self.0 >> 4
+impl GameState {
+    fn new(state: SolitareState, seed: Option<u64>) -> Self {
+        Self {
+            out: stdout(),
+            screen: screen(),
+            state,
+            seed,
+            daily: None,
+            selected: Highlight::None,
+            cursor: None,
+            count: 0,
+            hint: [Highlight::None; 2],
+            one_tap: CONFIG.one_tap,
+            history: History::new(state),
+            show_history: CLI.play.history_panel,
+            show_cards: false,
+            history_scroll: 0,
+            scroll: 0,
+            scrubbing: None,
+            finishing: false,
+            bot: false,
+            bot_plan: None,
+            solution: None,
+            message: None,
+            prompt: None,
+            help: false,
+            summary: None,
+            summary_deal: None,
+            undos: 0,
+            hints: 0,
+            move_budget: None,
+            budget_search: None,
+            puzzle: None,
+            vegas: (state.rules().scoring == Scoring::Vegas).then_some(0),
+            played: Duration::ZERO,
+            clock_started: None,
+            last_click: None,
+            last_esc: None,
+            dragging: None,
+            pointer: None,
+            animations: VecDeque::new(),
+            cascade: None,
+            flash: None,
+            broadcast: CLI.play.broadcast.map(|port| {
+                Broadcast::serve(port).unwrap_or_else(|e| {
+                    eprintln!("Could not broadcast on port {port}: {e}");
+                    std::process::exit(1)
+                })
+            }),
+            win_chance: CLI.play.win_chance.then(WinChance::start),
+        }
     }
 
-    fn is_red(&self) -> bool {
-        (self.0 >> 4) & 1 == 1
+    fn save(&self, path: &Path) -> io::Result<()> {
+        let save_file = SaveFile::new(
+            self.seed,
+            self.vegas,
+            self.elapsed().as_secs(),
+            &self.history,
+        );
+
+        fs::write(path, serde_json::to_string_pretty(&save_file)?)
     }
 
-    fn render(
-        &self,
-        f: &mut std::fmt::Formatter<'_>,
-        highlight: bool,
-    ) -> std::fmt::Result {
-        let rank = self.rank();
-        let rank_offset = if let 1..=11 = rank { rank } else { rank + 1 };
+    fn load(path: &Path) -> io::Result<Self> {
+        let save_file: SaveFile =
+            serde_json::from_str(&fs::read_to_string(path)?)?;
 
-        let suit = self.suit();
-        let suit_offset = [0, 1, 3, 2][suit as usize] << 4;
+        let (seed, vegas) = (save_file.seed, save_file.vegas);
+        let played = Duration::from_secs(save_file.seconds);
+        let history = save_file.history();
 
-        let card_char =
-            char::from_u32('🂠' as u32 + suit_offset + rank_offset as u32)
-                .unwrap();
+        let mut game = Self::new(history.current(), seed);
+        game.history = history;
+        game.vegas = vegas;
+        game.played = played;
 
-        let colored_card = if self.is_red() {
-            card_char.red()
-        } else {
-            card_char.black()
+        Ok(game)
+    }
+
+    // Starts at the beginning of a recorded game, stepping through it the
+    // same way as when scrubbing through the history
+    fn replay(record: record::Record) -> Self {
+        let record::Record {
+            seed,
+            mut history,
+            error,
+        } = record;
+
+        let mut game = Self::new(history.jump(0), seed);
+        game.history = history;
+        game.scrubbing = Some(0);
+        game.message = error;
+
+        game
+    }
+
+    // Keeps the autosave up to date with the current game, and removes it
+    // once the game is won so there is nothing to resume
+    fn autosave(&mut self) {
+        let Some(path) = autosave_path() else {
+            return;
         };
 
-        let (highlighted_card, pad) = if highlight {
-            (colored_card.on_dark_green(), " ".on_dark_green())
+        let result = if self.state.is_won() {
+            fs::remove_file(&path)
         } else {
-            (colored_card.on_white(), " ".on_white())
+            path.parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|()| self.save(&path))
         };
 
-        if *TWICE_WIDTH {
-            write!(f, "{}{}", highlighted_card, pad)?;
-        } else {
-            write!(f, "{}", highlighted_card)?;
+        if let Err(e) = result {
+            self.message = Some(format!("Autosave failed: {e}"));
         }
-
-        Ok(())
     }
 
-    fn highlight(self, highlight: bool) -> HighlightedCard {
-        HighlightedCard(self, highlight)
+    // Whether there is an autosaved game that has not been won
+    fn has_unfinished_autosave() -> bool {
+        autosave_path()
+            .and_then(|path| Self::load(&path).ok())
+            .is_some_and(|game| !game.state.is_won())
     }
-}
 
-impl Display for Card {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.render(f, false)
+    fn resume_autosave(&mut self) {
+        match autosave_path().map(|path| Self::load(&path)) {
+            Some(Ok(game)) => {
+                self.state = game.state;
+                self.seed = game.seed;
+                self.history = game.history;
+                self.vegas = game.vegas;
+                self.played = game.played;
+                self.clock_started = None;
+                self.selected = Highlight::None;
+                self.set_move_budget();
+            }
+            Some(Err(e)) => {
+                self.message = Some(format!("Could not resume game: {e}"))
+            }
+            None => {}
+        }
     }
-}
-
-struct HighlightedCard(Card, bool);
 
-impl Display for HighlightedCard {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.0.render(f, self.1)
+    // Columns taken up by a card
+    fn card_width() -> u16 {
+        CONFIG.card_width()
     }
-}
-
-// Number of working slots
-const N: usize = 7;
-const MAX_HEIGHT: usize = N - 1 + 13;
 
-#[derive(Debug, Clone, Copy)]
-struct SolitareState {
-    deck: u64,        // 1 bit per card, suits ordered: ♠, ♥, ♣, ♦
-    targets: [u8; 4], // Number of "solved" cards for each suit
-    slots: [[u8; MAX_HEIGHT]; N], // Working slots
-    slots_lens: [u8; N], // Combo: 4 low bits: len, 4 high bits: n hidden
-}
+    // Lines taken up by a card that is not covered
+    fn card_height() -> u16 {
+        CONFIG.card_height()
+    }
 
-fn shuffle(data: &mut [u8]) {
-    for i in 0..data.len() {
-        let j = rand::random_range(i..data.len());
+    // Row of the first card in the slots, after the top row and a gap
+    fn slots_row(&self) -> u16 {
+        self.layout().slots_row
+    }
 
-        data.swap(i, j);
+    // Lines of the slots down to the bottom of the longest. Every card in a
+    // slot takes up a line, except the last which is shown whole.
+    fn slots_lines(&self) -> u16 {
+        let lines = render::slots_height(&self.state, self.compact());
+        lines as u16 + Self::card_height() - 1
     }
-}
 
-fn shuffled_deck() -> [u8; 52] {
-    let mut deck = [0; 52];
+    // Lines of the slots shown, leaving the rest to be scrolled to when the
+    // terminal is too short
+    fn view_lines(&self) -> u16 {
+        let (_, rows) = terminal::size().unwrap_or((80, 24));
+        let room =
+            rows.saturating_sub(self.slots_row() + 1 + self.lines_below());
 
-    for (i, x) in deck.iter_mut().enumerate() {
-        *x = Card::from_index(i).0;
+        self.slots_lines().min(room)
     }
 
-    shuffle(&mut deck);
+    // Lines scrolled past, kept within the slots as they shrink
+    fn scroll(&self) -> u16 {
+        self.scroll.min(self.slots_lines() - self.view_lines())
+    }
 
-    deck
-}
+    fn scroll_by(&mut self, lines: i32) {
+        let max = self.slots_lines() - self.view_lines();
+        let scroll = self.scroll() as i32 + lines;
+        self.scroll = scroll.clamp(0, max as i32) as u16;
+    }
 
-#[derive(Debug, Clone, Copy)]
-enum Highlight {
-    None,
-    Target(u8),
-    Deck(u8),
-    Slot(u8, u8),
-}
+    // Scrolls the slots until the whole card at the cursor is shown
+    fn scroll_to_cursor(&mut self) {
+        let cursor = self.cursor.map(|cursor| self.clamp_cursor(cursor));
+        let Some(Highlight::Slot(col, row)) = cursor else {
+            return;
+        };
 
-impl SolitareState {
-    fn new() -> Self {
-        let mut state = Self {
-            deck: 0,
-            targets: [0; 4],
-            slots: [[0; MAX_HEIGHT]; N],
-            slots_lens: [0; N],
+        let line = if self.state.rules().game == Game::Pyramid {
+            col as u16
+        } else {
+            let (col, compact) = (col as usize, self.compact());
+            render::slot_line(&self.state, col, row, compact) as u16
         };
 
-        let deck = shuffled_deck();
-        let mut cur_card = 0;
+        let (scroll, view) = (self.scroll(), self.view_lines());
+        let bottom = line + Self::card_height();
+        if line < scroll {
+            self.scroll = line;
+        } else if bottom > scroll + view {
+            self.scroll = bottom - view;
+        }
+    }
 
-        // Dealing to slots:
-        for i in 0..N {
-            for j in i..N {
-                state.slots[j][i] = deck[cur_card];
-                cur_card += 1;
-            }
+    // Line right below the board, after a gap
+    fn info_row(&self) -> u16 {
+        self.slots_row() + self.view_lines() + 1
+    }
 
-            state.slots_lens[i] = ((i << 4) as u8) | ((i + 1) as u8);
+    // Lines below the board, for messages and the status line
+    fn lines_below(&self) -> u16 {
+        let mut lines = 2;
+        if render::shows_face_down(&self.state) && self.state.stock_len() > 0 {
+            lines += 1;
         }
-
-        // Counting which are left for remaining deck
-        for &card in deck.iter().skip(cur_card) {
-            state.deck |= 1 << Card(card).to_ind();
+        if CONFIG.high_contrast || CONFIG.screen_reader {
+            lines += 1;
+        }
+        if CONFIG.screen_reader {
+            lines += 1;
         }
 
-        state
+        lines
     }
 
-    fn render(
-        &self,
-        f: &mut std::fmt::Formatter<'_>,
-        highlight: Highlight,
-    ) -> std::fmt::Result {
-        let hl_ind = if let Highlight::Target(i) = highlight {
-            i as usize
-        } else {
-            4 // Out of bounds, will never hit
-        };
-
-        for suit in 0..4 {
-            if self.targets[suit] == 0 {
-                write!(f, "{}", "🂠".dark_grey())?;
-                if *TWICE_WIDTH {
-                    write!(f, " ")?;
-                }
-            } else {
-                write!(
-                    f,
-                    "{}",
-                    Card::from_suit_rank(suit as u8, self.targets[suit])
-                        .highlight(suit == hl_ind),
-                )?;
-            }
+    // Whether runs of face down cards are collapsed to a line each, which
+    // is done when the board would not fit the terminal otherwise
+    fn compact(&self) -> bool {
+        if CONFIG.compact {
+            return true;
         }
 
-        write!(f, " ┃ ")?;
+        let lines = render::slots_height(&self.state, false) as u16;
+        let height =
+            self.slots_row() + lines + Self::card_height() + self.lines_below();
+        let (_, rows) = terminal::size().unwrap_or((80, 24));
 
-        let mut remaining_deck = self.deck;
-        let mut i: usize = 0;
+        rows < height
+    }
 
-        let hl_ind = if let Highlight::Deck(i) = highlight {
-            i as u32
-        } else {
-            52 // Will never hit
-        };
+    // Where the piles of the top row are, stacked on narrow terminals
+    fn layout(&self) -> Layout {
+        let (columns, _) = terminal::size().unwrap_or((80, 24));
+        Layout::fitting(&self.state, columns)
+    }
 
-        for j in 0..self.deck.count_ones() {
-            let skip = remaining_deck.trailing_zeros() + 1;
+    // Columns of the top row or the slots, whichever is wider
+    fn board_width(&self) -> u16 {
+        let w = Self::card_width();
+        let n_slots = self.state.n_slots() as u16;
 
-            i += skip as usize;
-            remaining_deck >>= skip;
+        let slots_width = match self.state.rules().game {
+            Game::Pyramid => (2 * n_slots - 1) * w,
+            _ => n_slots * w,
+        };
+        self.layout().width.max(slots_width)
+    }
 
-            write!(f, "{}", Card::from_index(i - 1).highlight(j == hl_ind))?;
+    // Smallest terminal the board fits in, with the lines below it. The
+    // height grows with the longest slot.
+    fn min_size(&self) -> (u16, u16) {
+        let mut width = self.board_width();
+        if self.show_history {
+            width = self.panel_col() + PANEL_WIDTH;
+        }
+        if self.show_cards {
+            width = self.cards_panel_col() + CARDS_PANEL_WIDTH;
         }
 
-        writeln!(f, "\n\r")?;
+        // Taller slots are scrolled, with at least two cards shown
+        let view = self.slots_lines().min(2 * Self::card_height());
+        let height = self.slots_row() + view + 1 + self.lines_below();
 
-        let max_height =
-            self.slots_lens.iter().map(|l| l & 0x0f).max().unwrap();
+        (width, height)
+    }
 
-        let (hl_col, hl_row) = if let Highlight::Slot(i, j) = highlight {
-            (i as usize, j)
-        } else {
-            (N + 1, max_height + 1) // Too high, will never hit
-        };
+    fn fits_terminal(&self) -> bool {
+        let (width, height) = terminal::size().unwrap_or((80, 24));
+        let (min_width, min_height) = self.min_size();
 
-        for row_ind in 0..max_height {
-            for col_ind in 0..N {
-                let col_len = self.slots_lens[col_ind] & 0x0f;
-                let n_hidden = self.slots_lens[col_ind] >> 4;
-                if row_ind >= col_len {
-                    write!(f, " ")?;
-                    if *TWICE_WIDTH {
-                        write!(f, " ")?;
-                    }
-                } else if row_ind < n_hidden {
-                    write!(f, "{}", "🂠".blue())?;
-                    if *TWICE_WIDTH {
-                        write!(f, " ")?;
-                    }
-                } else {
-                    write!(
-                        f,
-                        "{}",
-                        Card(self.slots[col_ind][row_ind as usize])
-                            .highlight(col_ind == hl_col && row_ind >= hl_row)
-                    )?;
-                }
+        width >= min_width && height >= min_height
+    }
+
+    // Asks for a larger terminal, wrapping the words to fit the one there is
+    fn render_too_small(&mut self) {
+        let (width, height) = self.min_size();
+        let text =
+            format!("Enlarge your terminal to at least {width}x{height}");
+        let (columns, _) = terminal::size().unwrap_or((80, 24));
+
+        let mut lines = vec![String::new()];
+        for word in text.split(' ') {
+            let line = lines.last_mut().unwrap();
+            if line.is_empty() {
+                *line = word.to_owned();
+            } else if line.len() + 1 + word.len() <= columns as usize {
+                *line += &format!(" {word}");
+            } else {
+                lines.push(word.to_owned());
             }
-            writeln!(f, "\r")?;
         }
 
-        Ok(())
-    }
+        for (row, line) in lines.iter().enumerate() {
+            let line = theme::plain(line);
+            self.screen.draw(0, row as u16, &line);
+        }
 
-    fn highlight(self, highlight: Highlight) -> HighlightedSolitareState {
-        HighlightedSolitareState(self, highlight)
+        self.screen.present();
     }
-}
 
-impl Display for SolitareState {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.render(f, Highlight::None)
+    fn coord_to_selection(&self, col: u16, row: u16) -> Highlight {
+        let w = Self::card_width();
+        let layout = self.layout();
+        let (stock_col, waste_col) = (layout.stock, layout.waste);
+        let free_cell = self.state.rules().game == Game::FreeCell;
+        let cells_end = stock_col + self.state.n_cells() as u16 * w;
+
+        let (h, slots_row) = (Self::card_height(), layout.slots_row);
+        let foundation = layout.foundation_at(&self.state, col);
+        let stock_rows = layout.stock_row..layout.stock_row + h;
+
+        match row {
+            _ if row >= slots_row + self.view_lines() => Highlight::None,
+            _ if row >= slots_row => {
+                self.slot_selection(col / w, row - slots_row + self.scroll())
+            }
+            _ if row < h
+                && let Some(i) = foundation =>
+            {
+                Highlight::Target(i)
+            }
+            _ if !stock_rows.contains(&row) => Highlight::None,
+            // The free cells are where the stock would be
+            _ if free_cell && (stock_col..cells_end).contains(&col) => {
+                Highlight::FreeCell(((col - stock_col) / w) as u8)
+            }
+            _ if free_cell => Highlight::None,
+            _ if (stock_col..stock_col + w).contains(&col) => Highlight::Stock,
+            _ if (waste_col..waste_col + 3 * w).contains(&col) => {
+                Highlight::Waste
+            }
+            _ => Highlight::None,
+        }
     }
-}
 
-struct HighlightedSolitareState(SolitareState, Highlight);
+    // Card in the slots at a column counted in card widths and a row
+    // counted from the first card. A card takes up the rows down to the
+    // next one, and the last one its whole height. A collapsed run of face
+    // down cards is picked as its first card.
+    fn slot_selection(&self, x: u16, y: u16) -> Highlight {
+        let h = Self::card_height();
+
+        if self.state.rules().game == Game::Pyramid {
+            // Cards of lower rows are drawn over the ones above
+            let shown = (y.saturating_sub(h - 1)..=y).rev().find_map(|row| {
+                let selection = self.pyramid_selection(x, row);
+                let Highlight::Slot(row, i) = selection else {
+                    return None;
+                };
+                self.state
+                    .pyramid_card(row as usize, i as usize)
+                    .map(|_| selection)
+            });
 
-impl Display for HighlightedSolitareState {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.0.render(f, self.1)
-    }
-}
+            return shown.unwrap_or_else(|| self.pyramid_selection(x, y));
+        }
 
-struct GameState {
-    out: Stdout,
-    state: SolitareState,
-    selected: Highlight,
-}
+        let (col, compact) = (x as usize, self.compact());
+        let lines = render::slot_lines(&self.state, col, compact) as u16;
+        let line = if y >= lines && y < lines.max(1) - 1 + h {
+            lines.saturating_sub(1)
+        } else {
+            y
+        };
 
-impl GameState {
-    fn new() -> Self {
-        Self {
-            out: stdout(),
-            state: SolitareState::new(),
-            selected: Highlight::None,
-        }
+        let row = render::slot_row(&self.state, col, line as u8, compact);
+        Highlight::Slot(x as u8, row)
     }
 
-    fn coord_to_selection(col: u16, row: u16) -> Highlight {
-        match (col, row, *TWICE_WIDTH) {
-            (_, 2.., _) => {
-                let slot = if *TWICE_WIDTH { col / 2 } else { col };
-                let row = row - 2;
+    // Card in a pyramid row at the given column, counted in card widths.
+    // Rows are indented by half a card for every row below them.
+    fn pyramid_selection(&self, x: u16, row: u16) -> Highlight {
+        let rows = self.state.n_slots() as u16;
 
-                Highlight::Slot(slot as u8, row as u8)
+        match (x + row + 1).checked_sub(rows) {
+            Some(offset)
+                if row < rows && offset % 2 == 0 && offset / 2 <= row =>
+            {
+                Highlight::Slot(row as u8, (offset / 2) as u8)
             }
-            (..8, 0, true) => Highlight::Target((col / 2) as u8),
-            (..4, 0, false) => Highlight::Target(col as u8),
-            (11.., 0, true) => Highlight::Deck(((col - 11) / 2) as u8),
-            (7.., 0, false) => Highlight::Deck((col - 7) as u8),
             _ => Highlight::None,
         }
     }
 
-    // [src, dst]
-    fn is_selection_valid(&mut self, selection: Highlight) -> [bool; 2] {
-        match selection {
-            Highlight::None => [false; 2],
-            Highlight::Target(i) => {
-                if i < 4 {
-                    [self.state.targets[i as usize] > 0, true]
-                } else {
-                    [false; 2]
-                }
-            }
-            Highlight::Deck(i) => {
-                [(i as u32) < self.state.deck.count_ones(), false]
-            }
-            Highlight::Slot(col, row) => {
-                if (col as usize) < N {
-                    let slot = self.state.slots_lens[col as usize];
-                    let n_cards = slot & 0x0f;
-                    let n_hidden = slot >> 4;
+    // Picks up the card at selection. Pyramid kings are removed right away,
+    // as they need no card to pair with.
+    fn select(&mut self, selection: Highlight) {
+        if self.state.rules().game == Game::Pyramid
+            && let Some(location) = selection.location()
+            && self.state.card_at(location).rank() == 13
+        {
+            let _ = self.play(Move::Remove(location, None));
+        } else {
+            self.selected = selection;
+        }
+    }
 
-                    [(n_hidden..n_cards).contains(&row), true]
-                } else {
-                    [false; 2]
+    // Picks up the cards at a spot on the board, or puts the ones picked up
+    // there, from a click or the keyboard cursor
+    fn click(&mut self, new_selection: Highlight) {
+        let [valid_src, valid_dst] = self.is_selection_valid(new_selection);
+
+        match (valid_src, valid_dst, self.selected) {
+            _ if matches!(new_selection, Highlight::Stock) => self.draw(),
+            _ if self.one_tap => {
+                self.try_move_to_foundation(new_selection);
+            }
+            (false, _, Highlight::None) => {}
+            (true, _, Highlight::None) => self.select(new_selection),
+            (
+                _,
+                true,
+                Highlight::Target(_)
+                | Highlight::Waste
+                | Highlight::Slot(_, _)
+                | Highlight::FreeCell(_),
+            ) => {
+                if let Err(e) = self.try_move(new_selection) {
+                    self.reject(new_selection, e);
                 }
             }
+            (false, _, _) => self.selected = Highlight::None,
+            (true, _, _) => self.select(new_selection),
         }
     }
 
-    fn try_move(&mut self, selection: Highlight) {
-        let mut multiple = false;
+    // Spots along the top row the cursor can be on, with their columns
+    fn top_row(&self) -> Vec<(Highlight, u16)> {
+        let w = Self::card_width();
+        let game = self.state.rules().game;
+        let layout = self.layout();
+        let mut spots = Vec::new();
+
+        // Pyramid foundations are not shown
+        if game != Game::Pyramid {
+            spots.extend(
+                (0..self.state.n_foundations() as u8)
+                    .map(|i| (Highlight::Target(i), layout.foundation(i))),
+            );
+        }
 
-        let card = match self.selected {
-            Highlight::None => {
-                self.exit_game_mode();
-                panic!("Trying to move without selected card!")
+        if game == Game::FreeCell {
+            spots.extend((0..self.state.n_cells()).map(|i| {
+                (Highlight::FreeCell(i as u8), layout.stock + i as u16 * w)
+            }));
+        } else {
+            spots.push((Highlight::Stock, layout.stock));
+            if game != Game::Spider {
+                spots.push((Highlight::Waste, layout.waste));
             }
-            Highlight::Target(suit) => {
-                let rank = self.state.targets[suit as usize];
+        }
 
-                Card::from_suit_rank(suit, rank)
-            }
-            Highlight::Deck(i) => {
-                let mut deck = self.state.deck;
-                let mut card_ind = 0;
+        // Left to right, whichever side the foundations are on. Stacked,
+        // the stock row comes first, being the one next to the slots.
+        let stacked = layout.stacked();
+        spots.sort_by_key(|&(h, col)| {
+            (stacked && matches!(h, Highlight::Target(_)), col)
+        });
+        spots
+    }
 
-                for _ in 0..=i {
-                    let skip = deck.trailing_zeros() + 1;
-                    deck >>= skip;
-                    card_ind += skip;
-                }
+    // Top card of a slot, or the slot itself when empty
+    fn slot_cursor(&self, col: usize) -> Highlight {
+        let len = self.state.slot_len(col);
+        Highlight::Slot(col as u8, len.saturating_sub(1))
+    }
+
+    // Spots in a row below the top one the cursor can be on, with their
+    // columns. The row is one of the pyramid, while otherwise the cursor
+    // goes to the top card of a slot.
+    fn cursor_row(&self, row: usize) -> Vec<(Highlight, u16)> {
+        let w = Self::card_width();
+        let rows = self.state.n_slots();
+
+        if self.state.rules().game == Game::Pyramid {
+            (0..=row)
+                .map(|i| {
+                    let col = (rows - 1 - row) as u16 * w + i as u16 * 2 * w;
+                    (Highlight::Slot(row as u8, i as u8), col)
+                })
+                .collect()
+        } else {
+            (0..rows)
+                .map(|col| (self.slot_cursor(col), col as u16 * w))
+                .collect()
+        }
+    }
 
-                card_ind -= 1;
+    // Moves the cursor with the arrow keys. Left and right go along the top
+    // row or the slots, and up and down through the face up cards of a slot
+    // and between rows, to the spot closest above or below.
+    fn move_cursor(&mut self, code: KeyCode) {
+        // The first key press only shows the cursor
+        let Some(cursor) = self.cursor else {
+            self.cursor = Some(self.cursor_row(0)[0].0);
+            return;
+        };
 
-                Card::from_index(card_ind as usize)
-            }
-            Highlight::Slot(col, row) => {
-                let slot_height = self.state.slots_lens[col as usize] & 0x0f;
+        let pyramid = self.state.rules().game == Game::Pyramid;
+        let rows = self.state.n_slots();
 
-                if row + 1 < slot_height {
-                    multiple = true;
-                }
+        let top_row = self.top_row();
+        let top_index = top_row.iter().position(|&(h, _)| h == cursor);
 
-                Card(self.state.slots[col as usize][row as usize])
+        // Column of the cursor on screen, to find the closest spot in
+        // another row
+        let w = Self::card_width();
+        let col = match (top_index, cursor) {
+            (Some(i), _) => top_row[i].1,
+            (None, Highlight::Slot(row, i)) if pyramid => {
+                (rows - 1 - row as usize) as u16 * w + i as u16 * 2 * w
             }
+            (None, Highlight::Slot(col, _)) => col as u16 * w,
+            _ => 0,
+        };
+        let closest = |spots: Vec<(Highlight, u16)>| {
+            spots
+                .into_iter()
+                .min_by_key(|&(_, c)| c.abs_diff(col))
+                .map_or(cursor, |(h, _)| h)
         };
 
-        println!("\n\nTrying to move: {}", card);
+        self.cursor = Some(match (top_index, cursor, code) {
+            (Some(i), _, KeyCode::Left) => top_row[i.saturating_sub(1)].0,
+            (Some(i), _, KeyCode::Right) => {
+                top_row[(i + 1).min(top_row.len() - 1)].0
+            }
+            (Some(_), _, KeyCode::Down) => closest(self.cursor_row(0)),
+            (Some(_), _, _) => cursor,
+            (None, Highlight::Slot(row, i), KeyCode::Left) if pyramid => {
+                Highlight::Slot(row, i.saturating_sub(1))
+            }
+            (None, Highlight::Slot(row, i), KeyCode::Right) if pyramid => {
+                Highlight::Slot(row, (i + 1).min(row))
+            }
+            (None, Highlight::Slot(row, _), KeyCode::Up) if pyramid => {
+                match row.checked_sub(1) {
+                    Some(row) => closest(self.cursor_row(row as usize)),
+                    None => closest(top_row),
+                }
+            }
+            (None, Highlight::Slot(row, _), KeyCode::Down)
+                if pyramid && (row as usize) + 1 < rows =>
+            {
+                closest(self.cursor_row(row as usize + 1))
+            }
+            (None, Highlight::Slot(col, _), KeyCode::Left) => {
+                self.slot_cursor((col as usize).saturating_sub(1))
+            }
+            (None, Highlight::Slot(col, _), KeyCode::Right) => {
+                self.slot_cursor((col as usize + 1).min(rows - 1))
+            }
+            (None, Highlight::Slot(col, row), KeyCode::Up)
+                if !pyramid && row > self.state.slot_hidden(col as usize) =>
+            {
+                Highlight::Slot(col, row - 1)
+            }
+            (None, Highlight::Slot(..), KeyCode::Up) => closest(top_row),
+            (None, Highlight::Slot(col, row), KeyCode::Down)
+                if !pyramid && row + 1 < self.state.slot_len(col as usize) =>
+            {
+                Highlight::Slot(col, row + 1)
+            }
+            _ => cursor,
+        });
+    }
 
-        match selection {
-            Highlight::None => {
-                self.exit_game_mode();
-                panic!("Trying to move without selected destination!")
-            }
-            Highlight::Target(_) => {
-                let suit = card.suit();
-                if card.rank() != self.state.targets[suit as usize] + 1
-                    || multiple
-                {
-                    self.selected = selection;
-                } else {
-                    self.state.targets[suit as usize] += 1;
+    // Keeps the cursor on a card after the cards below it have moved
+    fn clamp_cursor(&self, cursor: Highlight) -> Highlight {
+        match cursor {
+            Highlight::Slot(col, row)
+                if self.state.rules().game != Game::Pyramid =>
+            {
+                // Top card, or 0 for an empty slot
+                let last = self.state.slot_len(col as usize).saturating_sub(1);
+                let first = self.state.slot_hidden(col as usize).min(last);
+
+                Highlight::Slot(col, row.clamp(first, last))
+            }
+            cursor => cursor,
+        }
+    }
 
-                    match self.selected {
-                        Highlight::None => unreachable!(),
-                        Highlight::Target(_) => unreachable!(),
-                        Highlight::Deck(_) => {
-                            self.state.deck &= !(1 << card.to_ind())
-                        }
-                        Highlight::Slot(col, _) => {
-                            let slot = &mut self.state.slots_lens[col as usize];
-                            let n_cards = (*slot & 0x0f) - 1;
-                            let mut n_hidden = *slot >> 4;
+    // [src, dst]
+    fn is_selection_valid(&self, selection: Highlight) -> [bool; 2] {
+        selection
+            .location()
+            .map_or([false; 2], |l| self.state.is_location_valid(l))
+    }
 
-                            if n_hidden > 0 && n_hidden == n_cards {
-                                n_hidden -= 1;
-                            }
+    // Moves the selected cards to a selection. When they can not go there,
+    // the cards at the selection are picked up instead if there are any.
+    fn try_move(&mut self, selection: Highlight) -> Result<(), IllegalMove> {
+        let (Some(from), Some(to)) =
+            (self.selected.location(), selection.location())
+        else {
+            return Ok(());
+        };
 
-                            *slot = (n_hidden << 4) | n_cards;
-                        }
-                    }
+        let result =
+            self.state.move_between(from, to).and_then(|m| self.play(m));
 
-                    self.selected = Highlight::None;
-                }
+        if result.is_ok() {
+            if CONFIG.auto_move {
+                self.play_safe_moves();
             }
-            Highlight::Deck(_) => self.selected = selection,
-            Highlight::Slot(col, _) => {
-                let slot = self.state.slots_lens[col as usize];
-                let slot_len = slot & 0x0f;
-                let slot_hidden = slot >> 4;
+            return result;
+        }
 
-                // First check for legality of move:
-                let legal = if slot_len == 0 {
-                    card.rank() == 13
-                } else {
-                    let target_card = Card(
-                        self.state.slots[col as usize][slot_len as usize - 1],
-                    );
+        if self.is_selection_valid(selection)[0] {
+            self.select(selection);
+        } else {
+            self.selected = Highlight::None;
+        }
 
-                    (card.rank() + 1 == target_card.rank())
-                        && (card.is_red() ^ target_card.is_red())
-                };
+        result
+    }
 
-                if legal {
-                    // Then performing the move
+    // Says why cards can not go to a selection, and flashes it or rings the
+    // bell. Picking up other cards of the same slot is not a try to move.
+    fn reject(&mut self, to: Highlight, e: IllegalMove) {
+        if e == IllegalMove::SameSlot {
+            return;
+        }
 
-                    if !multiple {
-                        self.state.slots[col as usize][slot_len as usize] =
-                            card.0;
-                        self.state.slots_lens[col as usize] =
-                            (slot_hidden << 4) | (slot_len + 1);
-                    }
+        self.message = Some(self.illegal_reason(to, e));
 
-                    match self.selected {
-                        Highlight::None => unreachable!(),
-                        Highlight::Target(suit) => {
-                            self.state.targets[suit as usize] -= 1
-                        }
-                        Highlight::Deck(_) => {
-                            self.state.deck &= !(1 << card.to_ind())
-                        }
-                        Highlight::Slot(from_col, row) => {
-                            let slot =
-                                &mut self.state.slots_lens[from_col as usize];
-                            if !multiple {
-                                let n_cards = (*slot & 0x0f) - 1;
-                                let mut n_hidden = *slot >> 4;
-
-                                if n_hidden > 0 && n_hidden == n_cards {
-                                    n_hidden -= 1;
-                                }
-
-                                *slot = (n_hidden << 4) | n_cards;
-                            } else {
-                                let n_cards = *slot & 0x0f;
-                                let n_moved = n_cards - row;
-                                let new_n_cards = n_cards - n_moved;
-
-                                let mut n_hidden = *slot >> 4;
-
-                                if n_hidden > 0 && n_hidden == new_n_cards {
-                                    n_hidden -= 1;
-                                }
-
-                                *slot = (n_hidden << 4) | new_n_cards;
-
-                                for i in 0..n_cards {
-                                    self.state.slots[col as usize]
-                                        [(slot_len + i) as usize] =
-                                        self.state.slots[from_col as usize]
-                                            [(row + i) as usize]
-                                }
-
-                                let new_to_slot_len = slot_len + n_moved;
-
-                                self.state.slots_lens[col as usize] =
-                                    (slot_hidden << 4) | new_to_slot_len;
-                            }
-                        }
-                    }
+        match CONFIG.illegal_moves {
+            Feedback::Flash => self.flash = Some((to, Instant::now())),
+            Feedback::Bell => queue!(self.out, Print('\x07')).unwrap(),
+            Feedback::Off => {}
+        }
+    }
 
-                    self.selected = Highlight::None;
-                } else {
-                    self.selected = selection;
+    // Why a move is not allowed, naming the cards that could go there
+    fn illegal_reason(&self, to: Highlight, e: IllegalMove) -> String {
+        let state = &self.state;
+        let game = state.rules().game;
+
+        let reason = match (e, to) {
+            (
+                IllegalMove::NotOneLower
+                | IllegalMove::SameColor
+                | IllegalMove::OtherSuit,
+                Highlight::Slot(col, _),
+            ) => {
+                let len = state.slot_len(col as usize);
+                let top = state.slot_card(col as usize, len as usize - 1);
+
+                match top.rank() - 1 {
+                    0 => String::from("nothing can go on an ace"),
+                    rank if game == Game::FortyThieves => {
+                        let card = Card::from_suit_rank(top.suit(), rank);
+                        format!("only {} can go here", a(&card_words(card)))
+                    }
+                    rank if game.builds_in_suit() => {
+                        format!("only {} can go here", a(&rank_word(rank)))
+                    }
+                    rank => {
+                        let color = if top.is_red() { "black" } else { "red" };
+                        format!(
+                            "only a {color} {} can go here",
+                            rank_word(rank)
+                        )
+                    }
                 }
             }
-        }
+            (IllegalMove::NotNextRank, Highlight::Target(i)) => {
+                match state.target(i) {
+                    Some(top) if top.rank() == 13 => {
+                        String::from("the foundation is full")
+                    }
+                    Some(top) => {
+                        let next =
+                            Card::from_suit_rank(top.suit(), top.rank() + 1);
+                        format!("only the {} can go here", card_words(next))
+                    }
+                    None => String::from(
+                        "only an ace can go on an empty foundation",
+                    ),
+                }
+            }
+            (IllegalMove::TooManyCards, Highlight::Slot(col, _)) => {
+                let max = state.max_run(col as usize).unwrap_or_default();
+                let cards = if max == 1 { "card" } else { "cards" };
+                // As many as can go one at a time through the free cells
+                // and empty slots
+                format!("there is only room to move {max} {cards} here")
+            }
+            (e, _) => e.to_string(),
+        };
+
+        let mut chars = reason.chars();
+        chars.next().map_or_else(String::new, |c| {
+            c.to_uppercase().chain(chars).collect()
+        })
     }
 
-    fn enter_game_mode(&mut self) {
-        enable_raw_mode().unwrap();
+    // Place a move was not allowed to, red for a moment over the board
+    fn render_flash(&mut self) {
+        let Some((at, _)) = self.flash else {
+            return;
+        };
 
-        execute!(
-            self.out,
-            EnableMouseCapture,
-            EnterAlternateScreen,
-            cursor::Hide,
-            terminal::Clear(terminal::ClearType::All),
-            cursor::MoveTo(0, 0)
-        )
-        .unwrap();
-    }
+        // The top card of a slot stands for the whole slot
+        let at = match at {
+            Highlight::Slot(col, _)
+                if self.state.rules().game != Game::Pyramid =>
+            {
+                let len = self.state.slot_len(col as usize);
+                Highlight::Slot(col, len.saturating_sub(1))
+            }
+            at => at,
+        };
+        let position = self.screen_position(&self.state, at);
 
-    fn exit_game_mode(&mut self) {
-        execute!(
-            self.out,
-            DisableMouseCapture,
-            cursor::Show,
-            LeaveAlternateScreen
-        )
-        .unwrap();
+        if let Some(card) = self.face_up_card(at) {
+            self.render_cards_at(&[card], position, Shade::Illegal);
+            return;
+        }
 
-        disable_raw_mode().unwrap()
+        let blank = " ".repeat(Self::card_width() as usize);
+        for line in 0..Self::card_height() {
+            let (col, row) = (position.0, position.1 + line);
+            let blank = theme::plain(&blank).on(theme::current().illegal);
+            self.screen.draw(col, row, &blank);
+        }
     }
 
-    fn run(&mut self) {
-        self.enter_game_mode();
-
-        println!("{}", self.state);
+    // Plays a move and records it in the history
+    fn play(&mut self, m: Move) -> Result<(), IllegalMove> {
+        let notation = m.notation(&self.state);
+        let before = self.state;
+        self.state.apply_move(m)?;
+        let said = self.say_move(&before, m);
 
-        while let Ok(x) = event::read() {
-            match x {
-                Event::Key(KeyEvent {
-                    code: KeyCode::Char('q'),
-                    modifiers: KeyModifiers::NONE,
-                    kind: _,
-                    state: _,
-                }) => break,
+        if CONFIG.animations {
+            self.animate(before, m);
+        }
 
-                Event::Key(KeyEvent {
-                    code: KeyCode::Esc,
-                    modifiers: KeyModifiers::NONE,
-                    kind: _,
-                    state: _,
-                }) => {
-                    self.selected = Highlight::None;
-                    execute!(self.out, cursor::MoveTo(0, 0)).unwrap();
-                    println!("{}", self.state);
-                }
+        if self.state.is_won() {
+            self.sound(Effect::Win);
+        } else if self.state.n_solved() > before.n_solved() {
+            self.sound(Effect::Foundation);
+        } else if self.state.n_hidden() < before.n_hidden() {
+            self.sound(Effect::Flip);
+        }
 
-                Event::Mouse(MouseEvent {
-                    kind: MouseEventKind::Down(MouseButton::Left),
-                    column,
-                    row,
-                    modifiers: KeyModifiers::NONE,
-                }) => {
-                    let new_selection = Self::coord_to_selection(column, row);
+        self.history.push(notation, self.state);
+        self.selected = Highlight::None;
 
-                    let [valid_src, valid_dst] =
-                        self.is_selection_valid(new_selection);
+        // The clock starts with the first move and stops once solved
+        if self.clock_started.is_none() {
+            self.clock_started = Some(Instant::now());
+        }
 
-                    match (valid_src, valid_dst, self.selected) {
-                        (false, _, Highlight::None) => {}
-                        (true, _, Highlight::None) => {
-                            self.selected = new_selection
+        if self.state.is_won() {
+            self.played = self.elapsed();
+            self.clock_started = None;
+            self.message = Some(match self.score_text() {
+                Some(score) => format!("Solved! {score}"),
+                None => String::from("Solved!"),
+            });
+            if CONFIG.animations {
+                self.cascade = self.win_cascade();
+            }
+            self.record_daily();
+            self.record_puzzle();
+            let entry = self.record_win();
+            self.log_game("won");
+            if !self.bot {
+                self.summary = Some(self.summary_lines(entry.as_ref()));
+            }
+        } else if self.state.is_dead_end() {
+            self.prompt = Some(Prompt::DeadEnd);
+        } else if self.state.can_auto_complete() && !self.finishing {
+            self.message = Some(String::from("Press f to finish the game"));
+        }
+
+        // The winning line goes on once its next move is played, and is left
+        // for any other move
+        if let Some((from, moves)) = &mut self.solution {
+            if *from == before && moves.last() == Some(&m) {
+                moves.pop();
+                *from = self.state;
+            } else {
+                self.solution = None;
+                self.message = Some(format!(
+                    "Left the winning line, {} finds a new one",
+                    help::key_name(CONFIG.keys.solution)
+                ));
+            }
+        }
+        if self
+            .solution
+            .as_ref()
+            .is_some_and(|(_, moves)| moves.is_empty())
+        {
+            self.solution = None;
+        } else if self.message.is_none() {
+            self.say_solution_step();
+        }
+
+        if CONFIG.screen_reader {
+            self.message = Some(match self.message.take() {
+                Some(message) => format!("{said}. {message}"),
+                None => said,
+            });
+        }
+
+        Ok(())
+    }
+
+    // Shows the cards of a move going from where they were to where they
+    // ended up. Pyramid pairs are taken off the board without moving.
+    fn animate(&mut self, before: SolitareState, m: Move) {
+        let after = self.state;
+        let top = |col: u8| {
+            let len = before.slot_len(col as usize);
+            Highlight::Slot(col, len.saturating_sub(1))
+        };
+        let to_slot =
+            |col: u8| Highlight::Slot(col, before.slot_len(col as usize));
+        let to_foundation = |from: Highlight| {
+            let card = before.card_at(from.location()?);
+            (0..after.n_foundations() as u8)
+                .find(|&i| after.target(i) == Some(card))
+                .map(Highlight::Target)
+        };
+
+        let (from, to) = match m {
+            // Turning the waste over moves no card to it
+            Move::Draw if after.waste_len() > before.waste_len() => {
+                (Highlight::Stock, Highlight::Waste)
+            }
+            Move::WasteToSlot(col) => (Highlight::Waste, to_slot(col)),
+            Move::WasteToFoundation => {
+                let Some(to) = to_foundation(Highlight::Waste) else {
+                    return;
+                };
+                (Highlight::Waste, to)
+            }
+            Move::SlotToSlot(from, row, col) => {
+                (Highlight::Slot(from, row), to_slot(col))
+            }
+            Move::SlotToFoundation(col) => {
+                let Some(to) = to_foundation(top(col)) else {
+                    return;
+                };
+                (top(col), to)
+            }
+            Move::FoundationToSlot(i, col) => {
+                (Highlight::Target(i), to_slot(col))
+            }
+            Move::SlotToCell(col, cell) => {
+                (top(col), Highlight::FreeCell(cell))
+            }
+            Move::CellToSlot(cell, col) => {
+                (Highlight::FreeCell(cell), to_slot(col))
+            }
+            Move::CellToFoundation(cell) => {
+                let from = Highlight::FreeCell(cell);
+                let Some(to) = to_foundation(from) else {
+                    return;
+                };
+                (from, to)
+            }
+            Move::Draw | Move::Remove(..) => return,
+        };
+
+        // Drawn cards are only known once they are on the waste
+        let cards = match from {
+            Highlight::Stock => after.waste_top().into_iter().collect(),
+            Highlight::Slot(col, row) => (row..before.slot_len(col as usize))
+                .map(|row| before.slot_card(col as usize, row as usize))
+                .collect(),
+            from => from
+                .location()
+                .map(|location| before.card_at(location))
+                .into_iter()
+                .collect(),
+        };
+
+        let from = self.screen_position(&before, from);
+        let to = self.screen_position(&after, to);
+        self.animations
+            .push_back(Animation::new(before, cards, from, to));
+    }
+
+    // Cards of the foundations thrown off them after a win, kings first.
+    // Pyramid has no foundations to throw them from.
+    fn win_cascade(&self) -> Option<Cascade> {
+        if self.state.rules().game == Game::Pyramid {
+            return None;
+        }
+
+        let mut cards = Vec::new();
+        for rank in (1..=13).rev() {
+            for i in 0..self.state.n_foundations() as u8 {
+                let Some(top) = self.state.target(i) else {
+                    continue;
+                };
+                let card = Card::from_suit_rank(top.suit(), rank);
+                cards.push((card, (self.layout().foundation(i), 0)));
+            }
+        }
+
+        let (width, height) = terminal::size().unwrap_or((80, 24));
+        let floor = height.saturating_sub(Self::card_height());
+        Some(Cascade::new(cards, width, floor))
+    }
+
+    // Top left corner of the card at a highlight on the screen, with the
+    // board in a state. Cards scrolled out of view are at the edge of the
+    // slots shown.
+    fn screen_position(
+        &self,
+        state: &SolitareState,
+        at: Highlight,
+    ) -> (u16, u16) {
+        let w = Self::card_width();
+        let layout = self.layout();
+
+        match at {
+            Highlight::None => (0, 0),
+            Highlight::Target(i) => (layout.foundation(i), 0),
+            Highlight::Stock => (layout.stock, layout.stock_row),
+            Highlight::Waste => {
+                let draw = state.rules().draw as usize;
+                let n_fanned = state.waste_len().clamp(1, draw) as u16;
+                (layout.waste + (n_fanned - 1) * w, layout.stock_row)
+            }
+            Highlight::FreeCell(i) => {
+                (layout.stock + i as u16 * w, layout.stock_row)
+            }
+            Highlight::Slot(col, row) => {
+                let line = if state.rules().game == Game::Pyramid {
+                    col as u16
+                } else {
+                    let (col, compact) = (col as usize, self.compact());
+                    render::slot_line(state, col, row, compact) as u16
+                };
+                let (scroll, view) = (self.scroll(), self.view_lines());
+                let line = line.clamp(scroll, scroll + view.max(1) - 1);
+
+                (col as u16 * w, self.slots_row() + line - scroll)
+            }
+        }
+    }
+
+    // Cards over the board, one per line with their top left corner at a
+    // position, like when dragged
+    fn render_cards_at(
+        &mut self,
+        cards: &[Card],
+        (col, row): (u16, u16),
+        shade: Shade,
+    ) {
+        for (i, &card) in cards.iter().enumerate() {
+            self.screen.draw_card(col, row + i as u16, card, shade);
+        }
+    }
+
+    // Keeps the result when playing the daily deal. It only counts as tried
+    // until it is won. Challenges are not counted, being played
+    // differently.
+    fn record_daily(&mut self) {
+        let counted = counts_results() && !is_challenge();
+        let Some(date) = self.daily.filter(|_| counted) else {
+            return;
+        };
+
+        let result = DailyResult {
+            won: self.state.is_won(),
+            moves: self.history.pos(),
+            seconds: self.elapsed().as_secs(),
+        };
+
+        if let Err(e) = daily::record(date, result) {
+            self.message = Some(format!("Could not save daily result: {e}"));
+        }
+    }
+
+    // Marks the puzzle being played solved, if any
+    fn record_puzzle(&mut self) {
+        let Some(name) = self.puzzle.filter(|_| !self.bot) else {
+            return;
+        };
+
+        if let Err(e) = puzzle::record(name, self.history.pos()) {
+            self.message = Some(format!("Could not save puzzle: {e}"));
+        }
+    }
+
+    // Puts the game just won on the leaderboard, unless it was played by
+    // the bot, won with more moves than the challenge allows or a puzzle
+    fn record_win(&mut self) -> Option<leaderboard::Entry> {
+        if self.bot
+            || !counts_results()
+            || self.over_budget()
+            || self.puzzle.is_some()
+        {
+            return None;
+        }
+
+        let entry = leaderboard::Entry {
+            seconds: self.elapsed().as_secs(),
+            moves: self.history.pos(),
+            date: Date::today().to_string(),
+            seed: self.seed,
+        };
+
+        match leaderboard::record(&self.variant(), entry.clone()) {
+            Ok(()) => Some(entry),
+            Err(e) => {
+                self.message = Some(format!("Could not save leaderboard: {e}"));
+                None
+            }
+        }
+    }
+
+    // What the game came to, once won or given up, followed by the
+    // leaderboard with the game marked if it made it there. The deal is
+    // solved again from the start to tell how hard it was, in the
+    // background as it can take seconds.
+    fn summary_lines(
+        &mut self,
+        entry: Option<&leaderboard::Entry>,
+    ) -> Vec<String> {
+        let won = self.state.is_won();
+        let moves = self.history.pos();
+        let seconds = self.elapsed().as_secs();
+
+        let mut title = String::from(if won {
+            "Solved!"
+        } else if self.over_budget() {
+            "Out of moves"
+        } else if self.out_of_time() {
+            "Out of time"
+        } else {
+            "Given up"
+        });
+        if CLI.play.practice {
+            title += " (practice, not counted)";
+        }
+        let mut lines = vec![title.bold().to_string(), String::new()];
+        lines.push(format!("Time   {}:{:02}", seconds / 60, seconds % 60));
+        lines.push(format!("Moves  {moves}"));
+        if let Some(budget) = self.move_budget {
+            lines.push(format!("Budget {budget}"));
+        }
+        if let Some(score) = self.score_text() {
+            lines.push(score);
+        }
+        lines.push(format!("Undos  {}", self.undos));
+        lines.push(format!("Hints  {}", self.hints));
+
+        let initial = self.history.initial();
+        let deal = Background::start(move || deal_lines(&initial, won, moves));
+        self.summary_deal = Some((lines.len(), deal));
+        lines.push(format!("Deal   {}", theme::dim("…")));
+
+        if won {
+            let variant = self.variant();
+            let entries =
+                leaderboard::load().remove(&variant).unwrap_or_default();
+            lines.push(String::new());
+            lines.extend(leaderboard::lines(&variant, &entries, entry));
+        }
+
+        lines
+    }
+
+    // Puts how hard the deal was in the summary once it is worked out,
+    // telling whether there was anything to put
+    fn fill_summary(&mut self) -> bool {
+        let Some((at, deal)) = &mut self.summary_deal else {
+            return false;
+        };
+        let Some(lines) = deal.get() else {
+            return false;
+        };
+
+        if let Some(summary) = &mut self.summary {
+            summary.splice(*at..*at + 1, lines.iter().cloned());
+        }
+        self.summary_deal = None;
+        true
+    }
+
+    // Leaderboard the game goes on, the challenges having their own for
+    // every number of extra moves and minutes
+    fn variant(&self) -> String {
+        let mut variant = leaderboard::variant(self.state.rules());
+        if let Some(extra) = CLI.play.move_budget {
+            variant += &format!(", move budget +{extra}");
+        }
+        if let Some(minutes) = CLI.play.time_limit {
+            variant += &format!(", {minutes} minutes");
+        }
+        variant
+    }
+
+    // Sets the moves allowed for the deal in the challenge: the ones the
+    // solver wins it in, and the extra ones given. The solver works it out
+    // in the background, and until then there is no budget to go over.
+    fn set_move_budget(&mut self) {
+        self.move_budget = None;
+        self.budget_search = None;
+        if CLI.play.move_budget.is_none() {
+            return;
+        }
+
+        let initial = self.history.initial();
+        self.budget_search = Some(Background::start(move || {
+            let positions = solver_positions(solver::MAX_POSITIONS);
+            match solver::solve(&initial, positions) {
+                Solution::Won(moves) => Some(moves.len()),
+                Solution::Lost | Solution::GaveUp => None,
+            }
+        }));
+    }
+
+    // Sets the move budget once the solver is done, telling whether it is
+    fn fill_move_budget(&mut self) -> bool {
+        let (Some(search), Some(extra)) =
+            (&mut self.budget_search, CLI.play.move_budget)
+        else {
+            return false;
+        };
+        let Some(&solution) = search.get() else {
+            return false;
+        };
+
+        self.move_budget = solution.map(|moves| moves + extra);
+        if solution.is_none() {
+            self.message =
+                Some(String::from("No move budget, the deal is unsolved"));
+        }
+        self.budget_search = None;
+        true
+    }
+
+    // More moves made than the challenge allows
+    fn over_budget(&self) -> bool {
+        self.move_budget
+            .is_some_and(|budget| self.history.pos() > budget)
+    }
+
+    // Seconds left on the clock of the time limit, counting down from the
+    // first move
+    fn time_left(&self) -> Option<u64> {
+        let seconds = self.elapsed().as_secs();
+        CLI.play
+            .time_limit
+            .map(|minutes| (minutes * 60).saturating_sub(seconds))
+    }
+
+    fn out_of_time(&self) -> bool {
+        self.time_left() == Some(0) && !self.state.is_won()
+    }
+
+    // Adds the game to the game log, if one is kept, once it has ended one
+    // way or another. Games the bot played and ones not started are left
+    // out.
+    fn log_game(&mut self, result: &str) {
+        let Some(path) = &CONFIG.game_log else {
+            return;
+        };
+        if self.bot || self.history.pos() == 0 || !counts_results() {
+            return;
+        }
+
+        let seconds = self.elapsed().as_secs();
+        if let Err(e) =
+            game_log::append(path, self.seed, &self.history, result, seconds)
+        {
+            self.message = Some(format!("Could not write game log: {e}"));
+        }
+    }
+
+    // Draws from the stock, or turns the waste over if the stock is empty
+    fn draw(&mut self) {
+        // Nothing happens when both are empty
+        if self.play(Move::Draw).is_ok() && CONFIG.auto_move {
+            self.play_safe_moves();
+        }
+        self.selected = Highlight::None;
+    }
+
+    // Makes the next move when finishing the game, stopping once done
+    fn finish_step(&mut self) {
+        let played = self
+            .state
+            .auto_complete_move()
+            .is_some_and(|m| self.play(m).is_ok());
+
+        if !played || self.state.is_won() {
+            self.finishing = false;
+        }
+    }
+
+    // Makes the next move of the bot. The game is solved before the first
+    // one, and the solution followed if there is one, otherwise the hints
+    // are. Stops once there is nothing more to do.
+    fn bot_step(&mut self) {
+        let plan = self.bot_plan.get_or_insert_with(|| {
+            match solver::solve(
+                &self.state,
+                solver_positions(solver::MAX_POSITIONS),
+            ) {
+                Solution::Won(moves) => moves.into_iter().rev().collect(),
+                Solution::Lost | Solution::GaveUp => Vec::new(),
+            }
+        });
+
+        let m = plan.pop().or_else(|| solver::hint(&self.state));
+        let played = m.is_some_and(|m| self.play(m).is_ok());
+
+        if !played {
+            self.message = Some(String::from("The bot found no useful moves"));
+        }
+        if !played || self.state.is_won() || self.prompt.is_some() {
+            self.bot = false;
+        }
+    }
+
+    // Sends every card to the foundations that is safe to put there, each
+    // as a move of its own
+    fn play_safe_moves(&mut self) {
+        while let Some(m) = self.state.safe_foundation_move() {
+            if self.play(m).is_err() {
+                break;
+            }
+        }
+    }
+
+    // Marks where to move cards from and to next. The solver is asked for
+    // perfect hints, falling back to the usual ones if the game is too
+    // large to solve in time.
+    fn show_hint(&mut self) {
+        let hint = if CLI.play.perfect_hints {
+            match solver::solve(
+                &self.state,
+                solver_positions(solver::MAX_POSITIONS),
+            ) {
+                Solution::Won(moves) => moves.first().copied(),
+                Solution::Lost => {
+                    self.message =
+                        Some(String::from("The game can no longer be won"));
+                    return;
+                }
+                Solution::GaveUp => solver::hint(&self.state),
+            }
+        } else {
+            solver::hint(&self.state)
+        };
+
+        let Some(m) = hint else {
+            self.message = Some(String::from("No useful moves left"));
+            return;
+        };
+
+        self.hint = self.move_hint(m);
+        self.hints += 1;
+        self.message = Some(format!(
+            "Hint: {}, {}",
+            m.notation(&self.state),
+            solver::explain(&self.state, m, card_name)
+        ));
+    }
+
+    // Where to move cards from and to for a move
+    fn move_hint(&self, m: Move) -> [Highlight; 2] {
+        match m {
+            Move::Draw => [Highlight::Stock, Highlight::None],
+            m => self
+                .state
+                .move_locations(m)
+                .map(|l| l.map_or(Highlight::None, Highlight::from)),
+        }
+    }
+
+    // Hint shown on the board: the next move of the winning line while it
+    // is followed, otherwise the last hint asked for
+    fn shown_hint(&self) -> [Highlight; 2] {
+        match &self.solution {
+            Some((from, moves)) if *from == self.state => {
+                moves.last().map_or(self.hint, |&m| self.move_hint(m))
+            }
+            _ => self.hint,
+        }
+    }
+
+    // Solves the game to show the way to win it, one move at a time. The
+    // next move is marked until it is played, and asking again stops.
+    fn show_solution(&mut self) {
+        if self.solution.take().is_some() {
+            self.message =
+                Some(String::from("Stopped showing the winning line"));
+            return;
+        }
+
+        match solver::solve(
+            &self.state,
+            solver_positions(solver::MAX_POSITIONS),
+        ) {
+            Solution::Won(moves) => {
+                self.solution =
+                    Some((self.state, moves.into_iter().rev().collect()));
+                self.hints += 1;
+                self.say_solution_step();
+            }
+            Solution::Lost => {
+                self.message =
+                    Some(String::from("The game can no longer be won"));
+            }
+            Solution::GaveUp => {
+                self.message =
+                    Some(String::from("The game is too large to solve"));
+            }
+        }
+    }
+
+    // Tells the next move of the winning line, when at the position it goes
+    // on from
+    fn say_solution_step(&mut self) {
+        let Some((from, moves)) = &self.solution else {
+            return;
+        };
+        let Some(&m) = moves.last().filter(|_| *from == self.state) else {
+            return;
+        };
+
+        self.message = Some(format!(
+            "Winning line, {} moves to go: {}, {}",
+            moves.len(),
+            m.notation(&self.state),
+            solver::explain(&self.state, m, card_name)
+        ));
+    }
+
+    // Plays the card at selection to its foundation if legal, otherwise
+    // does nothing. Always leaves nothing selected. Returns whether the card
+    // was played.
+    fn try_move_to_foundation(&mut self, selection: Highlight) -> bool {
+        let prev_state = self.state;
+        let [valid_src, _] = self.is_selection_valid(selection);
+
+        if valid_src && !matches!(selection, Highlight::Target(_)) {
+            self.selected = selection;
+            // try_move finds the foundation from the suit of the card
+            let _ = self.try_move(Highlight::Target(0));
+        }
+
+        self.selected = Highlight::None;
+
+        self.state != prev_state
+    }
+
+    // Cards picked up at a selection, from the bottom one up
+    fn picked_up_cards(&self, selection: Highlight) -> Vec<Card> {
+        match selection {
+            Highlight::Slot(col, row)
+                if self.state.rules().game != Game::Pyramid =>
+            {
+                (row..self.state.slot_len(col as usize))
+                    .map(|row| self.state.slot_card(col as usize, row as usize))
+                    .collect()
+            }
+            selection => selection
+                .location()
+                .map(|location| self.state.card_at(location))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    // Puts dragged cards down. They go back if they can not go there.
+    fn drop_selected(&mut self, selection: Highlight) {
+        if let Err(e) = self.try_move(selection) {
+            self.reject(selection, e);
+        }
+        self.selected = Highlight::None;
+    }
+
+    fn undo(&mut self) {
+        if self.history.pos() > 0 {
+            self.undos += 1;
+        }
+        let pos = self.history.pos().saturating_sub(1);
+        self.state = self.history.jump(pos);
+        self.selected = Highlight::None;
+    }
+
+    fn redo(&mut self) {
+        self.state = self.history.jump(self.history.pos() + 1);
+        self.selected = Highlight::None;
+    }
+
+    fn restart(&mut self) {
+        if !self.state.is_won() {
+            self.log_game("restarted");
+        }
+        self.state = self.history.jump(0);
+        self.selected = Highlight::None;
+        self.reset_clock();
+        self.sound(Effect::Deal);
+    }
+
+    // Played with the next redraw
+    fn sound(&mut self, effect: Effect) {
+        sound::play(&mut self.out, effect).unwrap();
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.played + self.clock_started.map_or(Duration::ZERO, |t| t.elapsed())
+    }
+
+    fn reset_clock(&mut self) {
+        self.played = Duration::ZERO;
+        self.clock_started = None;
+    }
+
+    // Standard score including the penalty for the time played
+    fn score(&self) -> u16 {
+        match self.state.rules().game {
+            Game::Klondike | Game::FreeCell | Game::FortyThieves => {
+                scoring::with_time_penalty(
+                    self.state.score(),
+                    self.elapsed().as_secs(),
+                )
+            }
+            Game::Spider | Game::Pyramid => self.state.score(),
+        }
+    }
+
+    // Vegas balance including this deal: a $52 buy-in for each deck and $5
+    // back for each card on the foundations
+    fn vegas_balance(&self) -> Option<i32> {
+        let buy_in = 52 * self.state.rules().decks as i32;
+
+        self.vegas
+            .map(|b| b - buy_in + 5 * self.state.n_solved() as i32)
+    }
+
+    // Moving on from a game that was played but not won gives it up,
+    // showing its summary over the new deal
+    fn new_deal(&mut self) {
+        let Some(seed) = deal_seed(rand::random(), self.state.rules()) else {
+            self.message = Some(String::from("No winnable deal found"));
+            return;
+        };
+        if !self.state.is_won() {
+            self.log_game(if self.over_budget() {
+                "out of moves"
+            } else if self.out_of_time() {
+                "out of time"
+            } else {
+                "new deal"
+            });
+            if self.history.pos() > 0 && !self.bot {
+                self.summary = Some(self.summary_lines(None));
+            }
+        }
+        self.vegas = self.vegas_balance();
+        self.undos = 0;
+        self.hints = 0;
+        self.seed = Some(seed);
+        self.daily = None;
+        self.puzzle = None;
+        self.state = SolitareState::new(seed, self.state.rules());
+        self.history = History::new(self.state);
+        self.selected = Highlight::None;
+        self.history_scroll = 0;
+        self.reset_clock();
+        self.set_move_budget();
+        self.sound(Effect::Deal);
+    }
+
+    // The clock is stopped while the pause menu is open, and starts again
+    // with the next move
+    fn pause(&mut self) -> pause::Choice {
+        self.played = self.elapsed();
+        self.clock_started = None;
+
+        let mut options = pause::Options {
+            one_tap: self.one_tap,
+            show_history: self.show_history,
+            show_cards: self.show_cards,
+            win_chance: self.win_chance.is_some(),
+        };
+        let variant = self.variant();
+        let choice = pause::run(&mut self.out, &mut options, &variant);
+        self.screen.invalidate();
+
+        self.one_tap = options.one_tap;
+        if options.show_history != self.show_history {
+            self.show_history = options.show_history;
+            self.history_scroll = 0;
+        }
+        self.show_cards = options.show_cards;
+        if options.win_chance != self.win_chance.is_some() {
+            self.win_chance = options.win_chance.then(WinChance::start);
+        }
+
+        match choice {
+            pause::Choice::Restart => self.restart(),
+            pause::Choice::NewDeal => self.new_deal(),
+            pause::Choice::Resume | pause::Choice::Quit => {}
+        }
+
+        choice
+    }
+
+    fn prompt_key(&mut self, code: KeyCode) {
+        let Some(prompt) = self.prompt.take() else {
+            return;
+        };
+
+        match (prompt, code) {
+            (Prompt::DeadEnd, KeyCode::Char('u')) => self.undo(),
+            (Prompt::DeadEnd, KeyCode::Char('r')) => self.restart(),
+            (Prompt::DeadEnd, KeyCode::Char('n')) => self.new_deal(),
+            (Prompt::NewDeal, KeyCode::Char('y')) => self.new_deal(),
+            (Prompt::NewDeal, KeyCode::Char('n')) => {}
+            (Prompt::Resume, KeyCode::Char('y')) => self.resume_autosave(),
+            (Prompt::Resume, KeyCode::Char('n')) => {}
+            (Prompt::Quit, KeyCode::Char('n')) => {}
+            (_, KeyCode::Esc) => {}
+            _ => self.prompt = Some(prompt), // Keep waiting for an answer
+        }
+    }
+
+    fn enter_game_mode(&mut self) {
+        enable_raw_mode().unwrap();
+
+        execute!(
+            self.out,
+            EnableMouseCapture,
+            EnterAlternateScreen,
+            cursor::Hide,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, 0)
+        )
+        .unwrap();
+        self.screen.invalidate();
+    }
+
+    fn exit_game_mode(&mut self) {
+        execute!(
+            self.out,
+            DisableMouseCapture,
+            ResetColor,
+            cursor::Show,
+            LeaveAlternateScreen
+        )
+        .unwrap();
+
+        disable_raw_mode().unwrap()
+    }
+
+    // Right of the board, after a gap
+    fn panel_col(&self) -> u16 {
+        self.board_width() + 3
+    }
+
+    // Index of the first visible history line and number of visible lines.
+    // Line 0 is the starting position, line i is the position after move i.
+    fn history_view(&self) -> (usize, usize) {
+        let (_, height) = terminal::size().unwrap_or((80, 24));
+        let n_visible = height.saturating_sub(1).max(1) as usize;
+        let n_lines = self.history.len() + 1;
+
+        let max_scroll = n_lines.saturating_sub(n_visible);
+        let scroll = self.history_scroll.min(max_scroll);
+
+        let first = n_lines.saturating_sub(n_visible + scroll);
+
+        (first, n_visible.min(n_lines))
+    }
+
+    fn render_history(&mut self) {
+        let col = self.panel_col();
+        let (first, n_visible) = self.history_view();
+        let width = (PANEL_WIDTH - 2) as usize;
+
+        let title = theme::plain("History").bold();
+        self.screen.draw(col, 0, &title);
+
+        for (row, i) in (first..first + n_visible).enumerate() {
+            let line = if i == 0 {
+                format!("{:>3}. start", 0)
+            } else {
+                format!("{:>3}. {}", i, self.history.notation(i - 1))
+            };
+            let line = format!("{line:width$}");
+
+            let styled = if i == self.history.pos() {
+                theme::plain(line).on(theme::current().selected)
+            } else if i > self.history.pos() {
+                theme::dim(line)
+            } else {
+                theme::plain(line)
+            };
+
+            self.screen.draw(col, row as u16 + 1, &styled);
+        }
+
+        self.screen.present();
+    }
+
+    fn in_history(&self, column: u16) -> bool {
+        self.show_history
+            && (self.panel_col()..self.panel_col() + PANEL_WIDTH)
+                .contains(&column)
+    }
+
+    fn cards_panel_col(&self) -> u16 {
+        match self.show_history {
+            true => self.panel_col() + PANEL_WIDTH,
+            false => self.panel_col(),
+        }
+    }
+
+    // Ranks of each suit still face down or in the stock, and how many
+    // cards that is
+    fn render_cards_panel(&mut self) {
+        let col = self.cards_panel_col();
+        let rules = self.state.rules();
+
+        let mut hidden = [[false; 13]; 4];
+        let mut count = 0;
+        for card in self.state.hidden_cards() {
+            hidden[card.suit() as usize][card.rank() as usize - 1] = true;
+            count += 1;
+        }
+
+        self.screen.draw(col, 0, &theme::plain("Hidden").bold());
+
+        for suit in 0..rules.suits {
+            let ranks: Vec<_> = (0..13)
+                .filter(|&rank| hidden[suit as usize][rank])
+                .map(|rank| card::RANK_NAMES[rank])
+                .collect();
+            let line = format!(
+                "{} {}",
+                card::SUIT_SYMBOLS[suit as usize],
+                ranks.join(" ")
+            );
+
+            let mut line = theme::plain(line);
+            if Card::from_suit_rank(suit, 1).is_red() {
+                line = line.with(theme::current().red);
+            }
+            self.screen.draw(col, suit as u16 + 1, &line);
+        }
+
+        let count = match count {
+            1 => String::from("1 card"),
+            n => format!("{n} cards"),
+        };
+        self.screen
+            .draw(col, rules.suits as u16 + 2, &theme::dim(count));
+    }
+
+    fn click_history(&mut self, row: u16) {
+        let (first, n_visible) = self.history_view();
+
+        if let 1.. = row
+            && (row as usize) <= n_visible
+        {
+            self.state = self.history.jump(first + row as usize - 1);
+            self.selected = Highlight::None;
+        }
+    }
+
+    fn render_help(&mut self) {
+        for (row, line) in help::lines(self.state.rules()).iter().enumerate() {
+            let line = theme::plain(line);
+            self.screen.draw(0, row as u16, &line);
+        }
+        self.screen.draw(
+            0,
+            Self::status_row(),
+            &theme::dim("Press any key to go back to the game"),
+        );
+
+        self.screen.present();
+    }
+
+    fn render_summary(&mut self) {
+        let lines = self.summary.as_deref().unwrap_or_default();
+        for (row, line) in lines.iter().enumerate() {
+            self.screen.draw(0, row as u16, &theme::plain(line));
+        }
+        self.screen.draw(
+            0,
+            Self::status_row(),
+            &theme::dim("Press any key to go back to the game"),
+        );
+
+        self.screen.present();
+    }
+
+    // Right above the status line
+    fn scrub_bar_row() -> u16 {
+        Self::status_row().saturating_sub(1)
+    }
+
+    fn scrub_bar_width(&self) -> u16 {
+        self.panel_col() - 2
+    }
+
+    fn render_scrub_bar(&mut self) {
+        let row = Self::scrub_bar_row();
+        let width = self.scrub_bar_width() as usize;
+        let pos = self.history.pos();
+        let len = self.history.len();
+
+        let knob = (pos * (width - 1)).checked_div(len).unwrap_or(0);
+
+        let bar: String = (0..width)
+            .map(|i| match i.cmp(&knob) {
+                std::cmp::Ordering::Less => '━',
+                std::cmp::Ordering::Equal => '●',
+                std::cmp::Ordering::Greater => '─',
+            })
+            .collect();
+
+        let mut label = format!("Move {pos}/{len}");
+        let (n_branches, branch) = self.history.branches();
+        if n_branches > 1 {
+            label += &format!(", branch {}/{n_branches}", branch + 1);
+        }
+
+        self.screen
+            .draw(0, row.saturating_sub(1), &theme::plain(label).bold());
+        let bar = theme::plain(bar).with(theme::current().accent);
+        self.screen.draw(0, row, &bar);
+
+        self.screen.present();
+    }
+
+    fn scrub_to_column(&mut self, col: u16) {
+        let width = self.scrub_bar_width() as usize;
+        let col = (col as usize).min(width - 1);
+        let len = self.history.len();
+
+        let pos = (col * len + (width - 1) / 2) / (width - 1);
+
+        self.state = self.history.jump(pos);
+    }
+
+    fn scrub_key(&mut self, code: KeyCode) {
+        let pos = self.history.pos();
+
+        self.state = match code {
+            KeyCode::Left => self.history.jump(pos.saturating_sub(1)),
+            KeyCode::Right => self.history.jump(pos + 1),
+            KeyCode::Home => self.history.jump(0),
+            KeyCode::End => self.history.jump(self.history.len()),
+            KeyCode::Up => self.history.switch_branch(-1),
+            KeyCode::Down => self.history.switch_branch(1),
+            KeyCode::Enter => {
+                // Keep playing from the reviewed position
+                self.scrubbing = None;
+                self.state
+            }
+            _ => {
+                let start = self.scrubbing.take().unwrap_or(pos);
+                self.history.jump(start)
+            }
+        };
+    }
+
+    fn copy_to_clipboard(&mut self, what: &str, text: &str) {
+        self.message = Some(match clipboard::copy(&mut self.out, text) {
+            Ok(()) => format!("Copied {what} to clipboard"),
+            Err(e) => format!("Could not copy {what}: {e}"),
+        });
+    }
+
+    // Seed, layout and rules of the deal being played, as a record without
+    // moves so it can be replayed from the start
+    fn export_deal(&mut self) {
+        let deal = format!(
+            "# Play this deal with `solitare replay {DEAL_PATH}`\n{}",
+            record::header(self.seed, &self.history.initial())
+        );
+
+        if let Err(e) = fs::write(DEAL_PATH, &deal) {
+            self.message = Some(format!("Could not write {DEAL_PATH}: {e}"));
+            return;
+        }
+
+        self.message = Some(match clipboard::copy(&mut self.out, &deal) {
+            Ok(()) => format!("Wrote the deal to {DEAL_PATH} and copied it"),
+            Err(e) => format!("Wrote the deal to {DEAL_PATH}, not copied: {e}"),
+        });
+    }
+
+    fn move_list(&self) -> String {
+        (0..self.history.pos())
+            .map(|i| format!("{}. {}", i + 1, self.history.notation(i)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn score_text(&self) -> Option<String> {
+        match self.state.rules().scoring {
+            Scoring::Standard => Some(format!("Score {}", self.score())),
+            Scoring::Vegas => self.vegas_balance().map(|balance| {
+                let sign = if balance < 0 { "-" } else { "" };
+                format!("Vegas {sign}${}", balance.abs())
+            }),
+            Scoring::None => None,
+        }
+    }
+
+    // Shown on the bottom line of the terminal
+    fn status_line(&self) -> String {
+        let mut parts = Vec::new();
+
+        if CLI.play.practice {
+            parts.push(String::from("Practice"));
+        }
+
+        if let Some(date) = self.daily {
+            parts.push(format!("Daily {date}"));
+        } else if let Some(seed) = self.seed {
+            parts.push(format!("Seed {seed}"));
+        }
+
+        parts.push(match self.move_budget {
+            Some(budget) => format!("Moves {}/{budget}", self.history.pos()),
+            None if self.budget_search.is_some() => {
+                format!("Moves {}/…", self.history.pos())
+            }
+            None => format!("Moves {}", self.history.pos()),
+        });
+
+        if let Some(score) = self.score_text() {
+            parts.push(score);
+        }
+
+        parts.push(self.clock_text());
+
+        if let Some(win_chance) = &self.win_chance
+            && !self.state.is_won()
+        {
+            parts.push(match win_chance.get(&self.state) {
+                Some(chance) => format!("Win ~{:.0}%", chance * 100.0),
+                None => String::from("Win ~?"),
+            });
+        }
+
+        // Turning over the waste, in the games that have one
+        let rules = self.state.rules();
+        if !matches!(rules.game, Game::Spider | Game::FreeCell) {
+            let redeals = self.state.pass() - 1;
+            parts.push(match rules.max_passes {
+                Some(max_passes) => {
+                    format!("Redeals {redeals}/{}", max_passes - 1)
+                }
+                None => format!("Redeals {redeals}"),
+            });
+        }
+
+        parts.join("  ")
+    }
+
+    // Card at a highlight, or the place when there is no card showing
+    fn describe(&self, highlight: Highlight) -> Option<String> {
+        let card = match highlight {
+            Highlight::None => return None,
+            Highlight::Stock => return Some(String::from("stock")),
+            highlight => self.face_up_card(highlight),
+        };
+
+        Some(match (card, highlight) {
+            (Some(card), _) => card_name(card),
+            (None, Highlight::Target(i)) => format!("foundation {}", i + 1),
+            (None, Highlight::Waste) => String::from("waste"),
+            (None, Highlight::FreeCell(i)) => format!("free cell {}", i + 1),
+            (None, Highlight::Slot(col, row)) => {
+                if row < self.state.slot_len(col as usize) {
+                    format!("face down card in slot {}", col + 1)
+                } else {
+                    format!("slot {}", col + 1)
+                }
+            }
+            (None, _) => return None,
+        })
+    }
+
+    // Card shown face up at a highlight, if there is one
+    fn face_up_card(&self, highlight: Highlight) -> Option<Card> {
+        let state = &self.state;
+
+        match highlight {
+            Highlight::None | Highlight::Stock => None,
+            Highlight::Target(i) => state.target(i),
+            Highlight::Waste => state.waste_top(),
+            Highlight::FreeCell(i) => state.cell(i as usize),
+            Highlight::Slot(row, i) if state.rules().game == Game::Pyramid => {
+                state.pyramid_card(row as usize, i as usize)
+            }
+            Highlight::Slot(col, row) => {
+                let col = col as usize;
+                (row < state.slot_len(col) && row >= state.slot_hidden(col))
+                    .then(|| state.slot_card(col, row as usize))
+            }
+        }
+    }
+
+    // A move in words, as made from the position before it
+    fn say_move(&self, before: &SolitareState, m: Move) -> String {
+        let after = &self.state;
+
+        let mut said = match m {
+            Move::Draw if after.rules().game == Game::Spider => {
+                String::from("Dealt a card to every slot")
+            }
+            Move::Draw => match after.waste_top() {
+                Some(card) if after.waste_len() > before.waste_len() => {
+                    format!("Drew {}", spoken(card))
+                }
+                _ => String::from("Turned the waste over"),
+            },
+            Move::Remove(first, second) => {
+                let first = spoken(before.card_at(first));
+                match second {
+                    Some(second) => {
+                        let second = spoken(before.card_at(second));
+                        format!("Removed {first} and {second}")
+                    }
+                    None => format!("Removed {first}"),
+                }
+            }
+            m => {
+                let [Some(from), Some(to)] = before.move_locations(m) else {
+                    return m.notation(before);
+                };
+
+                let mut cards = spoken(before.card_at(from));
+                if let Location::Slot(col, row) = from {
+                    match before.slot_len(col as usize) - row - 1 {
+                        0 => {}
+                        1 => cards += " and the card on it",
+                        n => cards += &format!(" and the {n} cards on it"),
+                    }
+                }
+
+                let to = match to {
+                    Location::Slot(col, _)
+                        if before.slot_len(col as usize) == 0 =>
+                    {
+                        format!("to empty slot {}", col + 1)
+                    }
+                    Location::Slot(..) => {
+                        format!("onto {}", spoken(before.card_at(to)))
+                    }
+                    Location::Target(_) => String::from("to its foundation"),
+                    Location::Cell(i) => format!("to free cell {}", i + 1),
+                    Location::Waste => String::from("to the waste"),
+                };
+
+                format!("Moved {cards} {to}")
+            }
+        };
+
+        // Cards turned up by the move, in Klondike at most one
+        for col in 0..after.n_slots() {
+            if after.rules().game == Game::Pyramid {
+                break;
+            }
+
+            let hidden = after.slot_hidden(col);
+            if hidden < before.slot_hidden(col) {
+                let card = after.slot_card(col, hidden as usize);
+                said += &format!(", turning up {}", spoken(card));
+            }
+        }
+
+        said
+    }
+
+    // Every card of the pile at a highlight, from the bottom up
+    fn say_pile(&self, highlight: Highlight) -> Option<String> {
+        let state = &self.state;
+        let count = |n: usize| match n {
+            1 => String::from("1 card"),
+            n => format!("{n} cards"),
+        };
+
+        Some(match highlight {
+            Highlight::None => return None,
+            Highlight::Stock => format!("Stock: {}", count(state.stock_len())),
+            Highlight::Waste => match state.waste_top() {
+                Some(card) => format!(
+                    "Waste: {}, {} on top",
+                    count(state.waste_len()),
+                    spoken(card)
+                ),
+                None => String::from("Waste: empty"),
+            },
+            Highlight::Target(i) => match state.target(i) {
+                Some(card) => format!("Foundation {}: {}", i + 1, spoken(card)),
+                None => format!("Foundation {}: empty", i + 1),
+            },
+            Highlight::FreeCell(i) => match state.cell(i as usize) {
+                Some(card) => format!("Free cell {}: {}", i + 1, spoken(card)),
+                None => format!("Free cell {}: empty", i + 1),
+            },
+            Highlight::Slot(..) if state.rules().game == Game::Pyramid => {
+                self.describe(highlight)?
+            }
+            Highlight::Slot(col, _) => {
+                let col = col as usize;
+                let (len, hidden) =
+                    (state.slot_len(col), state.slot_hidden(col));
+
+                let mut cards = Vec::new();
+                if hidden > 0 {
+                    cards.push(format!("{hidden} face down"));
+                }
+                cards.extend(
+                    (hidden..len)
+                        .map(|row| spoken(state.slot_card(col, row as usize))),
+                );
+                if cards.is_empty() {
+                    cards.push(String::from("empty"));
+                }
+
+                format!("Slot {}: {}", col + 1, cards.join(", "))
+            }
+        })
+    }
+
+    // What is selected, under the cursor and part of the hint, as text
+    fn markers(&self) -> String {
+        let mut parts = Vec::new();
+
+        if let Some(selected) = self.describe(self.selected) {
+            parts.push(format!("[selected] {selected}"));
+        }
+        if let Some(cursor) = self.cursor.and_then(|c| self.describe(c)) {
+            parts.push(format!("[cursor] {cursor}"));
+        }
+        if let [Some(from), Some(to)] =
+            self.shown_hint().map(|h| self.describe(h))
+        {
+            parts.push(format!("[hint] {from} to {to}"));
+        }
+
+        parts.join("  ")
+    }
+
+    // Time played, or the time left with a time limit
+    fn clock_text(&self) -> String {
+        match self.time_left() {
+            Some(left) => format!("Left {}:{:02}", left / 60, left % 60),
+            None => {
+                let seconds = self.elapsed().as_secs();
+                format!("Time {}:{:02}", seconds / 60, seconds % 60)
+            }
+        }
+    }
+
+    fn status_row() -> u16 {
+        let (_, height) = terminal::size().unwrap_or((80, 24));
+        height.saturating_sub(1)
+    }
+
+    // Redraws only the status line, to keep the clock up to date without
+    // clearing the screen
+    fn render_status_line(&mut self) {
+        // Left of the panels when they are shown
+        let width = if self.show_history || self.show_cards {
+            self.panel_col() as usize - 1
+        } else {
+            terminal::size().map_or(80, |(width, _)| width as usize)
+        };
+        let status: String = self.status_line().chars().take(width).collect();
+
+        self.screen.draw(
+            0,
+            Self::status_row(),
+            &theme::dim(format!("{status:width$}")),
+        );
+
+        // The clock turns red when the time is nearly up
+        let clock = self.clock_text();
+        if self.time_left().is_some_and(|left| left <= TIME_WARNING)
+            && let Some(col) = status.find(&clock)
+        {
+            let red = theme::plain(clock).with(theme::current().red);
+            self.screen.draw(col as u16, Self::status_row(), &red);
+        }
+
+        self.screen.present();
+    }
+
+    // Sends the board to the viewers of a broadcast, if it changed. They
+    // see it without the selection and the cursor of the player.
+    fn broadcast(&mut self) {
+        let Some(broadcast) = &mut self.broadcast else {
+            return;
+        };
+        let (state, moves) = (self.state, self.history.pos());
+
+        broadcast.send(state, || {
+            let board = HighlightedSolitareState(
+                state,
+                Highlight::None,
+                Highlight::None,
+                [Highlight::None; 2],
+                View {
+                    compact: CONFIG.compact,
+                    scroll: 0,
+                    lines: u16::MAX,
+                    layout: Layout::new(&state),
+                },
+            );
+            let mut ansi = Vec::new();
+            Renderer::clear(&mut ansi);
+            ansi.draw(0, 0, &board);
+
+            Frame {
+                ansi: String::from_utf8(ansi).unwrap(),
+                json: headless::to_json(&state, moves).to_string() + "\n",
+            }
+        });
+    }
+
+    fn redraw(&mut self) {
+        self.broadcast();
+        if let Some(win_chance) = &mut self.win_chance {
+            win_chance.ask(self.state);
+        }
+        self.screen.clear();
+
+        // Drawing on a terminal too small would wrap the lines
+        if !self.fits_terminal() {
+            self.render_too_small();
+            return;
+        }
+
+        if self.help {
+            self.render_help();
+            return;
+        }
+
+        // After the cascade of cards, if it is shown
+        if self.summary.is_some() && self.cascade.is_none() {
+            self.render_summary();
+            return;
+        }
+
+        self.cursor = self.cursor.map(|cursor| self.clamp_cursor(cursor));
+        let board = HighlightedSolitareState(
+            self.animations.front().map_or(self.state, |a| a.before),
+            self.selected,
+            self.cursor.unwrap_or(Highlight::None),
+            self.shown_hint(),
+            View {
+                compact: self.compact(),
+                scroll: self.scroll(),
+                lines: self.view_lines(),
+                layout: self.layout(),
+            },
+        );
+        self.screen.draw(0, 0, &board);
+
+        self.render_status_line();
+
+        // Slots that do not fit are cut off, shown by marks in the gaps
+        // above and below them
+        let scroll = self.scroll();
+        if scroll > 0 {
+            let more = theme::dim("▲ More above, page up");
+            self.screen.draw(0, self.slots_row() - 1, &more);
+        }
+        if scroll + self.view_lines() < self.slots_lines() {
+            let more = theme::dim("▼ More below, page down");
+            let row = self.info_row() - 1;
+            self.screen.draw(0, row, &more);
+        }
+
+        if self.state.rules().game == Game::Spider {
+            // Spider has no waste, so the rows left go in its place
+            let rows = self.state.stock_len().div_ceil(self.state.n_slots());
+            let layout = self.layout();
+            let text = theme::dim(format!("Deals left {rows}"));
+            self.screen.draw(layout.waste, layout.stock_row, &text);
+        }
+
+        if self.show_history {
+            self.render_history();
+        }
+
+        if self.show_cards {
+            self.render_cards_panel();
+        }
+
+        if self.scrubbing.is_some() {
+            self.render_scrub_bar();
+        }
+
+        let mut info_row = self.info_row();
+
+        // With face down cards shown, the order of the stock is as well
+        if render::shows_face_down(&self.state) && self.state.stock_len() > 0 {
+            let stock: String = self
+                .state
+                .stock_cards()
+                .map(|card| DimmedCard(card).to_string())
+                .collect();
+
+            let stock = format!("{}{stock}", theme::dim("Stock "));
+            self.screen.draw(0, info_row, &stock);
+
+            info_row += 1;
+        }
+
+        if let Some(prompt) = self.prompt {
+            self.screen.draw(
+                0,
+                info_row,
+                &theme::plain(prompt.text()).bold().reverse(),
+            );
+        } else if let Some(message) = &self.message {
+            self.screen
+                .draw(0, info_row, &theme::plain(message.as_str()));
+        }
+
+        // Without shades to go by, what is highlighted is spelled out
+        if CONFIG.high_contrast || CONFIG.screen_reader {
+            let markers = theme::plain(self.markers());
+            self.screen.draw(0, info_row + 1, &markers);
+        }
+
+        // The pile being looked at, with the cursor or by picking it up
+        if CONFIG.screen_reader
+            && let Some(pile) =
+                self.say_pile(self.cursor.unwrap_or(self.selected))
+        {
+            let pile = theme::plain(pile);
+            self.screen.draw(0, info_row + 2, &pile);
+        }
+
+        // Moving cards are gone from where they were, and on their way
+        if let Some(animation) = self.animations.front() {
+            let cards = animation.cards.clone();
+            let (from, at) = (animation.from, animation.position());
+
+            let lines = cards.len() as u16 + Self::card_height() - 1;
+            let blank = " ".repeat(Self::card_width() as usize);
+            for line in 0..lines {
+                let blank = theme::plain(&blank);
+                self.screen.draw(from.0, from.1 + line, &blank);
+            }
+
+            self.render_cards_at(&cards, at, Shade::Plain);
+        }
+
+        self.render_flash();
+
+        // Dragged cards follow the pointer, over everything else
+        if let (Some(from), Some(pointer)) = (self.dragging, self.pointer) {
+            let cards = self.picked_up_cards(from);
+            self.render_cards_at(&cards, pointer, Shade::Selected);
+        }
+
+        self.screen.present();
+    }
+
+    fn run(&mut self) {
+        self.enter_game_mode();
+
+        // Only a single handler can be set, which is fine as only one game
+        // is run
+        let _ =
+            ctrlc::set_handler(|| INTERRUPTED.store(true, Ordering::Relaxed));
+
+        // Games picked up where they were left are not dealt again
+        if self.history.len() == 0 {
+            self.sound(Effect::Deal);
+        }
+        self.set_move_budget();
+        self.redraw();
+
+        loop {
+            if INTERRUPTED.load(Ordering::Relaxed) {
+                break;
+            }
+
+            // Searches of the solver done in the background, once they are
+            let summary = self.fill_summary();
+            let budget = self.fill_move_budget();
+            if summary || budget {
+                self.redraw();
+            }
+
+            // Going over the move budget or the time limit of a challenge
+            // loses the game, once the last move has been shown
+            if (self.over_budget() || self.out_of_time())
+                && !self.state.is_won()
+                && self.animations.is_empty()
+            {
+                self.finishing = false;
+                self.new_deal();
+                self.redraw();
+            }
+
+            // Wakes up when the clock ticks over to the next second, or for
+            // the next move when finishing or playing by itself, or to end a
+            // flash
+            let timeout = if !self.animations.is_empty() {
+                animation::STEP
+            } else if self.cascade.is_some() {
+                animation::CASCADE_STEP
+            } else if self.finishing {
+                FINISH_STEP
+            } else if self.bot {
+                BOT_STEP
+            } else if let Some((_, at)) = self.flash {
+                FLASH.saturating_sub(at.elapsed())
+            } else {
+                Duration::from_secs(1)
+                    - Duration::from_nanos(self.elapsed().subsec_nanos() as u64)
+            };
+
+            match event::poll(timeout) {
+                Ok(true) => {}
+                Ok(false) if !self.animations.is_empty() => {
+                    if let Some(animation) = self.animations.front_mut()
+                        && !animation.step()
+                    {
+                        self.animations.pop_front();
+                    }
+                    self.redraw();
+                    continue;
+                }
+                Ok(false) if self.cascade.is_some() => {
+                    match self.cascade.as_mut().and_then(Cascade::step) {
+                        Some((card, at)) => {
+                            self.render_cards_at(&[card], at, Shade::Plain);
+                            self.screen.present();
+                        }
+                        None => {
+                            self.cascade = None;
+                            self.redraw();
+                        }
+                    }
+                    continue;
+                }
+                Ok(false) if self.finishing => {
+                    self.finish_step();
+                    self.autosave();
+                    self.redraw();
+                    continue;
+                }
+                // The bot's games are not saved, so they never take the place
+                // of one's own
+                Ok(false) if self.bot => {
+                    self.bot_step();
+                    self.redraw();
+                    continue;
+                }
+                Ok(false) if self.flash.is_some() => {
+                    self.flash = None;
+                    self.redraw();
+                    continue;
+                }
+                // Also shows the estimate of the chance to win once it is
+                // worked out
+                Ok(false) => {
+                    if (self.clock_started.is_some()
+                        || self.win_chance.is_some())
+                        && !self.help
+                        && self.fits_terminal()
+                    {
+                        self.render_status_line();
+                    }
+                    continue;
+                }
+                Err(_) => break,
+            }
+
+            let Ok(x) = event::read() else {
+                break;
+            };
+
+            // Any input stops finishing the game, and pressing anything stops
+            // the bot, puts moving cards where they go and clears the message
+            let pressed = matches!(
+                x,
+                Event::Key(_)
+                    | Event::Mouse(MouseEvent {
+                        kind: MouseEventKind::Down(_),
+                        ..
+                    })
+            );
+            if pressed {
+                self.bot = false;
+                self.animations.clear();
+                self.flash = None;
+                self.message = None;
+            }
+
+            // Pressing anything skips the cascade after a win, and does
+            // nothing else
+            if pressed && self.cascade.take().is_some() {
+                self.redraw();
+                continue;
+            }
+            self.finishing = false;
+            self.hint = [Highlight::None; 2];
+            let prev_state = self.state;
+
+            // A count typed with vim keys is for the key right after it
+            let count = match x {
+                Event::Key(_) => mem::take(&mut self.count),
+                _ => 0,
+            };
+
+            match x {
+                // Nothing on the board can be clicked while it is not shown
+                Event::Mouse(_) if !self.fits_terminal() => {}
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char('c'),
+                    modifiers: KeyModifiers::CONTROL,
+                    kind: _,
+                    state: _,
+                }) => break,
+
+                Event::Key(_)
+                | Event::Mouse(MouseEvent {
+                    kind: MouseEventKind::Down(_),
+                    ..
+                }) if self.help || self.summary.is_some() => {
+                    self.help = false;
+                    self.summary = None;
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.quit
+                    || c == 'y' && self.prompt == Some(Prompt::Quit) =>
+                {
+                    // A game under way is only left once asked, the key
+                    // pressed again also saying yes
+                    if self.history.pos() > 0
+                        && !self.state.is_won()
+                        && self.prompt != Some(Prompt::Quit)
+                    {
+                        self.prompt = Some(Prompt::Quit);
+                        self.redraw();
+                    } else {
+                        break;
+                    }
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.help => {
+                    self.help = true;
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.theme && !CONFIG.high_contrast => {
+                    let name = theme::next().name();
+                    self.message = Some(format!("Theme {name}"));
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code,
+                    modifiers: KeyModifiers::NONE,
+                    kind: _,
+                    state: _,
+                }) if self.prompt.is_some() => {
+                    self.prompt_key(code);
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c @ '0'..='9'),
+                    modifiers: KeyModifiers::NONE,
+                    kind: _,
+                    state: _,
+                }) if CONFIG.vim_keys && self.scrubbing.is_none() => {
+                    let digit = c.to_digit(10).unwrap() as usize;
+                    self.count = (count * 10 + digit).min(MAX_COUNT);
+                    self.message = Some(self.count.to_string());
+                    self.redraw();
+                }
+
+                // No command is on them while vim keys are on
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE,
+                    kind: _,
+                    state: _,
+                }) if CONFIG.vim_keys
+                    && self.scrubbing.is_none()
+                    && let Some(code) = config::vim_direction(c) =>
+                {
+                    for _ in 0..count.max(1) {
+                        self.move_cursor(code);
+                    }
+                    self.scroll_to_cursor();
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.undo && self.scrubbing.is_none() => {
+                    self.undo();
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char('z'),
+                    modifiers: KeyModifiers::CONTROL,
+                    kind: _,
+                    state: _,
+                }) if self.scrubbing.is_none() => {
+                    self.undo();
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.redo && self.scrubbing.is_none() => {
+                    self.redo();
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char('y'),
+                    modifiers: KeyModifiers::CONTROL,
+                    kind: _,
+                    state: _,
+                }) if self.scrubbing.is_none() => {
+                    self.redo();
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char('d'),
+                    modifiers: KeyModifiers::CONTROL,
+                    kind: _,
+                    state: _,
+                }) if CLI.play.debug || CLI.play.practice => {
+                    let revealed = render::toggle_revealed();
+                    self.message = Some(String::from(if revealed {
+                        "Showing face down cards"
+                    } else {
+                        "Hiding face down cards"
+                    }));
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.restart && self.scrubbing.is_none() => {
+                    self.restart();
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.new_deal && self.scrubbing.is_none() => {
+                    self.prompt = Some(Prompt::NewDeal);
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.save => {
+                    self.message =
+                        Some(match self.save(Path::new(SAVE_PATH)) {
+                            Ok(()) => format!("Saved game to {SAVE_PATH}"),
+                            Err(e) => format!("Could not save game: {e}"),
+                        });
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.one_tap => {
+                    self.one_tap = !self.one_tap;
+                    self.selected = Highlight::None;
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.history => {
+                    self.show_history = !self.show_history;
+                    self.history_scroll = 0;
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.hidden_cards => {
+                    self.show_cards = !self.show_cards;
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.copy_board => {
+                    let board = self.state.plain_text();
+                    self.copy_to_clipboard("board", &board);
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.copy_code => {
+                    // Positions like puzzles were not dealt from a code
+                    match self.history.initial().fresh_share_code() {
+                        Some(code) => {
+                            self.copy_to_clipboard("share code", &code)
+                        }
+                        None => {
+                            self.message = Some(String::from(
+                                "Only deals have share codes",
+                            ))
+                        }
+                    }
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.copy_moves => {
+                    let moves = self.move_list();
+                    self.copy_to_clipboard("move list", &moves);
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.copy_replay => {
+                    let code =
+                        record::to_replay_string(self.seed, &self.history);
+                    self.copy_to_clipboard("replay string", &code);
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.export_deal => {
+                    self.export_deal();
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.hint && self.scrubbing.is_none() => {
+                    self.show_hint();
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.solution && self.scrubbing.is_none() => {
+                    self.show_solution();
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.scrub => {
+                    if self.scrubbing.is_some() {
+                        self.scrub_key(KeyCode::Esc);
+                    } else {
+                        self.scrubbing = Some(self.history.pos());
+                    }
+                    self.selected = Highlight::None;
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code:
+                        code @ (KeyCode::Left
+                        | KeyCode::Right
+                        | KeyCode::Home
+                        | KeyCode::End
+                        | KeyCode::Up
+                        | KeyCode::Down
+                        | KeyCode::Enter
+                        | KeyCode::Esc),
+                    modifiers: KeyModifiers::NONE,
+                    kind: _,
+                    state: _,
+                }) if self.scrubbing.is_some() => {
+                    self.scrub_key(code);
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.finish
+                    && self.scrubbing.is_none()
+                    && self.state.can_auto_complete() =>
+                {
+                    self.selected = Highlight::None;
+                    self.finishing = true;
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if c == CONFIG.keys.safe_moves
+                    && self.scrubbing.is_none() =>
+                {
+                    self.selected = Highlight::None;
+                    self.play_safe_moves();
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code:
+                        code @ (KeyCode::Left
+                        | KeyCode::Right
+                        | KeyCode::Up
+                        | KeyCode::Down),
+                    modifiers: KeyModifiers::NONE,
+                    kind: _,
+                    state: _,
+                }) if self.scrubbing.is_none() => {
+                    for _ in 0..count.max(1) {
+                        self.move_cursor(code);
+                    }
+                    self.scroll_to_cursor();
+                    self.redraw();
+                }
+
+                // Space draws from the stock until the cursor is shown
+                Event::Key(KeyEvent {
+                    code: KeyCode::Enter | KeyCode::Char(' '),
+                    modifiers: KeyModifiers::NONE,
+                    kind: _,
+                    state: _,
+                }) if self.scrubbing.is_none() && self.cursor.is_some() => {
+                    if let Some(cursor) = self.cursor {
+                        self.click(cursor);
+                    }
+                    self.redraw();
+                }
+
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    kind: _,
+                    state: _,
+                }) if (c == CONFIG.keys.draw || c == CONFIG.keys.draw_alt)
+                    && self.scrubbing.is_none() =>
+                {
+                    self.draw();
+                    self.redraw();
+                }
+
+                // Esc puts back the picked up cards, and pressed again or
+                // held down it opens the pause menu
+                Event::Key(KeyEvent {
+                    code: KeyCode::Esc,
+                    modifiers: KeyModifiers::NONE,
+                    kind: _,
+                    state: _,
+                }) => {
+                    self.selected = Highlight::None;
+
+                    if self.last_esc.is_some_and(|t| t.elapsed() < SECOND_ESC) {
+                        self.last_esc = None;
+                        if self.pause() == pause::Choice::Quit {
+                            break;
                         }
-                        (
-                            _,
-                            true,
-                            Highlight::Target(_)
-                            | Highlight::Deck(_)
-                            | Highlight::Slot(_, _),
-                        ) => self.try_move(new_selection),
-                        (false, _, _) => self.selected = Highlight::None,
-                        (true, _, _) => self.selected = new_selection,
+                    } else {
+                        self.last_esc = Some(Instant::now());
+                    }
+                    self.redraw();
+                }
+
+                Event::Mouse(MouseEvent {
+                    kind:
+                        kind @ (MouseEventKind::ScrollUp
+                        | MouseEventKind::ScrollDown),
+                    column,
+                    row: _,
+                    modifiers: KeyModifiers::NONE,
+                }) if self.in_history(column) => {
+                    let (first, n_visible) = self.history_view();
+                    let n_lines = self.history.len() + 1;
+                    let scroll = n_lines - first - n_visible;
+
+                    self.history_scroll = match kind {
+                        MouseEventKind::ScrollUp => scroll + 1,
+                        _ => scroll.saturating_sub(1),
+                    };
+
+                    self.redraw();
+                }
+
+                // Slots taller than the terminal are scrolled a page at a
+                // time with the keys, and a line at a time with the wheel
+                Event::Key(KeyEvent {
+                    code: code @ (KeyCode::PageUp | KeyCode::PageDown),
+                    modifiers: KeyModifiers::NONE,
+                    kind: _,
+                    state: _,
+                }) => {
+                    let page =
+                        self.view_lines().saturating_sub(1).max(1) as i32;
+                    match code {
+                        KeyCode::PageUp => self.scroll_by(-page),
+                        _ => self.scroll_by(page),
+                    }
+                    self.redraw();
+                }
+
+                Event::Mouse(MouseEvent {
+                    kind:
+                        kind @ (MouseEventKind::ScrollUp
+                        | MouseEventKind::ScrollDown),
+                    modifiers: KeyModifiers::NONE,
+                    ..
+                }) => {
+                    match kind {
+                        MouseEventKind::ScrollUp => self.scroll_by(-1),
+                        _ => self.scroll_by(1),
+                    }
+                    self.redraw();
+                }
+
+                Event::Mouse(MouseEvent {
+                    kind: MouseEventKind::Down(MouseButton::Left),
+                    column,
+                    row,
+                    modifiers: KeyModifiers::NONE,
+                }) if self.in_history(column) => {
+                    self.click_history(row);
+                    self.redraw();
+                }
+
+                Event::Mouse(MouseEvent {
+                    kind:
+                        MouseEventKind::Down(MouseButton::Left)
+                        | MouseEventKind::Drag(MouseButton::Left),
+                    column,
+                    row,
+                    modifiers: KeyModifiers::NONE,
+                }) if self.scrubbing.is_some() => {
+                    // Clicks on the board are ignored while scrubbing
+                    if row != Self::scrub_bar_row() {
+                        continue;
+                    }
+
+                    self.scrub_to_column(column);
+                    self.redraw();
+                }
+
+                Event::Mouse(MouseEvent {
+                    kind: MouseEventKind::Down(MouseButton::Left),
+                    column,
+                    row,
+                    modifiers: KeyModifiers::NONE,
+                }) => {
+                    // The keyboard cursor is hidden until it is moved again
+                    self.cursor = None;
+
+                    let selection = self.coord_to_selection(column, row);
+
+                    // Double clicking a card plays it to its foundation when
+                    // it can go there, like a single click otherwise
+                    let double_click =
+                        self.last_click.is_some_and(|(time, last)| {
+                            last == selection && time.elapsed() < DOUBLE_CLICK
+                        });
+                    self.last_click = Some((Instant::now(), selection));
+
+                    if double_click && self.try_move_to_foundation(selection) {
+                        self.last_click = None;
+                    } else {
+                        self.click(selection);
                     }
 
-                    execute!(
-                        self.out,
-                        cursor::MoveTo(0, 0),
-                        terminal::Clear(terminal::ClearType::All)
-                    )
-                    .unwrap();
-                    print!("{}", self.state.highlight(self.selected));
+                    // Cards picked up can be dragged to where they go
+                    self.dragging =
+                        (self.selected == selection).then_some(selection);
+                    self.pointer = None;
+                    self.redraw();
+                }
+
+                Event::Mouse(MouseEvent {
+                    kind: MouseEventKind::Drag(MouseButton::Left),
+                    column,
+                    row,
+                    modifiers: KeyModifiers::NONE,
+                }) if self.dragging.is_some() => {
+                    self.pointer = Some((column, row));
+                    self.redraw();
+                }
+
+                // Releasing the button somewhere else than where it was
+                // pressed drops the dragged cards there
+                Event::Mouse(MouseEvent {
+                    kind: MouseEventKind::Up(MouseButton::Left),
+                    column,
+                    row,
+                    modifiers: KeyModifiers::NONE,
+                }) if self.dragging.is_some() => {
+                    let from = self.dragging.take();
+                    let selection = self.coord_to_selection(column, row);
+
+                    // Dropped cards are already where they go
+                    if self.pointer.take().is_some() && from != Some(selection)
+                    {
+                        self.drop_selected(selection);
+                        self.animations.clear();
+                    }
+                    self.redraw();
 
                     // println!("Row: {row:3}\n\rCol: {column:3}\r");
                     // execute!(self.out, cursor::MoveUp(2)).unwrap();
                 }
 
+                // Right clicking a card plays it to its foundation, keeping
+                // what was picked up before
+                Event::Mouse(MouseEvent {
+                    kind: MouseEventKind::Down(MouseButton::Right),
+                    column,
+                    row,
+                    modifiers: KeyModifiers::NONE,
+                }) if self.scrubbing.is_none() => {
+                    self.cursor = None;
+
+                    let selection = self.coord_to_selection(column, row);
+                    let selected = self.selected;
+
+                    self.try_move_to_foundation(selection);
+
+                    if selected != selection
+                        && self.is_selection_valid(selected)[0]
+                    {
+                        self.selected = selected;
+                    }
+                    self.redraw();
+                }
+
+                Event::Resize(..) => self.redraw(),
+
                 _ => {}
             }
+
+            // Positions visited while scrubbing are not saved until play
+            // continues from one of them
+            if self.state != prev_state && self.scrubbing.is_none() {
+                self.autosave();
+            }
+        }
+
+        if !self.state.is_won() && self.history.pos() > 0 {
+            self.record_daily();
+            self.log_game("quit");
         }
 
         self.exit_game_mode();
+
+        if let Some(date) = self.daily {
+            print!("{}", daily::calendar(date));
+        }
+
+        if let Some(path) = &CLI.play.record
+            && let Err(e) = record::write(path, self.seed, &self.history)
+        {
+            eprintln!(
+                "Could not write move history to {}: {e}",
+                path.display()
+            );
+        }
+    }
+}
+
+// Game picked with command line flags, either by name or with the flag of
+// the game itself
+fn game_from_args() -> Option<Game> {
+    if let Some(game) = CLI.rules.game {
+        Some(game)
+    } else if CLI.rules.spider.is_some() {
+        Some(Game::Spider)
+    } else if CLI.rules.freecell {
+        Some(Game::FreeCell)
+    } else if CLI.rules.pyramid {
+        Some(Game::Pyramid)
+    } else if CLI.rules.forty_thieves {
+        Some(Game::FortyThieves)
+    } else if CLI.rules.double_deck {
+        Some(Game::Klondike)
+    } else {
+        None
+    }
+}
+
+// Game played when none is picked, the one a layout file is of when given
+fn default_game() -> Game {
+    match (game_from_args(), &CLI.deal.board) {
+        (Some(game), _) => game,
+        (None, Some(path)) => board::game(path),
+        (None, None) if CLI.deal.ms_deal.is_some() => Game::FreeCell,
+        (None, None) => Game::default(),
+    }
+}
+
+// Rules of a game, changed by command line flags
+fn rules_from_args(game: Game) -> Rules {
+    let game = match game {
+        Game::Spider if let Some(suits) = CLI.rules.spider => {
+            Rules::spider(suits)
+        }
+        Game::Klondike if CLI.rules.double_deck => Rules {
+            decks: 2,
+            slots: 9,
+            ..Rules::default()
+        },
+        game => Rules::for_game(game),
+    };
+
+    let mut rules = Rules {
+        draw: CONFIG.draw,
+        any_card_on_empty_slot: game.any_card_on_empty_slot
+            || CLI.rules.any_on_empty,
+        foundation_to_slot: game.foundation_to_slot
+            && !CLI.rules.no_foundation_to_slot,
+        thoughtful: CLI.rules.thoughtful,
+        run_limit: game.run_limit || CLI.rules.run_limit,
+        ..game
+    };
+
+    if CLI.rules.vegas {
+        // Vegas rules allow a single pass, or three when drawing three
+        rules.scoring = Scoring::Vegas;
+        rules.max_passes = Some(rules.draw);
+    } else if CLI.rules.no_scoring {
+        rules.scoring = Scoring::None;
+    }
+
+    if let Some(passes) = CLI.rules.passes {
+        // 0 for no limit
+        rules.max_passes = (passes > 0).then_some(passes);
+    }
+
+    // Slots in the tableau, or rows of the pyramid. There must be enough
+    // cards to deal them.
+    if let Some(slots) = CLI.rules.columns {
+        rules = Rules { slots, ..rules };
+
+        if !rules.is_valid() {
+            eprintln!("Invalid number of columns: {slots}");
+            std::process::exit(1)
+        }
+    }
+
+    rules
+}
+
+// Deal given by a layout file, a share code or a seed, otherwise a random
+// one
+fn deal_from_args(rules: Rules) -> (SolitareState, Option<u64>) {
+    if let Some(path) = &CLI.deal.board {
+        let state = board::read(path, rules).unwrap_or_else(|e| {
+            eprintln!("Could not load {}: {e}", path.display());
+            std::process::exit(1)
+        });
+
+        (state, None)
+    } else if let Some(number) = CLI.deal.ms_deal {
+        if rules.game != Game::FreeCell || rules.decks != 1 || rules.slots != 8
+        {
+            eprintln!("Microsoft deal numbers are only for FreeCell");
+            std::process::exit(1)
+        }
+
+        let deck = solitare_state::ms_deck(number);
+        (SolitareState::deal(&deck, rules), None)
+    } else if let Some(code) = &CLI.deal.deal {
+        let state =
+            SolitareState::from_share_code(code, rules).unwrap_or_else(|| {
+                eprintln!("Invalid share code: {code}");
+                std::process::exit(1)
+            });
+
+        (state, None)
+    } else {
+        let seed = CLI.deal.seed.unwrap_or_else(rand::random);
+        let seed = deal_seed(seed, rules).unwrap_or_else(|| {
+            eprintln!("No winnable deal found from seed {seed}");
+            std::process::exit(1)
+        });
+
+        (SolitareState::new(seed, rules), Some(seed))
+    }
+}
+
+// Seed to deal from, the given one unless only winnable deals are wanted.
+// Then it is the first one from there the solver can win, if any.
+fn deal_seed(seed: u64, rules: Rules) -> Option<u64> {
+    if CLI.deal.winnable || CLI.play.move_budget.is_some() {
+        solver::winnable_seed(seed, rules, WINNABLE_TRIES)
+    } else {
+        Some(seed)
+    }
+}
+
+// Rank written out, like "ace" or "10"
+fn rank_word(rank: u8) -> String {
+    match rank {
+        1 => String::from("ace"),
+        11 => String::from("jack"),
+        12 => String::from("queen"),
+        13 => String::from("king"),
+        rank => rank.to_string(),
+    }
+}
+
+// Card written out, like "6 of spades"
+fn card_words(card: Card) -> String {
+    const SUITS: [&str; 4] = ["spades", "hearts", "clubs", "diamonds"];
+
+    format!(
+        "{} of {}",
+        rank_word(card.rank()),
+        SUITS[card.suit() as usize]
+    )
+}
+
+// Card as read out, like "red 6 of hearts"
+fn spoken(card: Card) -> String {
+    let color = if card.is_red() { "red" } else { "black" };
+    format!("{color} {}", card_words(card))
+}
+
+// Card as shown in text, spoken for screen readers
+fn card_name(card: Card) -> String {
+    if CONFIG.screen_reader {
+        spoken(card)
+    } else {
+        card.name()
+    }
+}
+
+// Words with "a" or "an" in front, as they are said
+fn a(words: &str) -> String {
+    if words.starts_with("ace") || words.starts_with('8') {
+        format!("an {words}")
+    } else {
+        format!("a {words}")
+    }
+}
+
+// Playing with a move budget or a time limit
+fn is_challenge() -> bool {
+    CLI.play.move_budget.is_some() || CLI.play.time_limit.is_some()
+}
+
+// Games played seeing the face down cards, or for practice, are kept out of
+// the daily results, the leaderboard and the game log
+fn counts_results() -> bool {
+    !CLI.play.debug && !CLI.play.practice
+}
+
+// Positions the solver searches before giving up, `default` unless set
+// with --max-positions, and no more than fit in --max-memory
+// Lines of the summary on how hard a deal was, and how many moves the
+// solver won it in against the ones made
+fn deal_lines(initial: &SolitareState, won: bool, moves: usize) -> Vec<String> {
+    let (difficulty, solution) =
+        solver::difficulty(initial, solver_positions(solver::MAX_POSITIONS));
+    let mut lines = vec![format!("Deal   {}", difficulty.name())];
+
+    if let Some(solution) = solution {
+        let line = solution.len();
+        lines.push(match (won, moves.cmp(&line)) {
+            (false, _) => format!("The solver won it in {line} moves"),
+            (true, std::cmp::Ordering::Less) => format!(
+                "The solver won it in {line} moves, {} more than you",
+                line - moves
+            ),
+            (true, std::cmp::Ordering::Equal) => {
+                format!("The solver won it in {line} moves too")
+            }
+            (true, std::cmp::Ordering::Greater) => format!(
+                "The solver won it in {line} moves, {} fewer than you",
+                moves - line
+            ),
+        });
+    }
+
+    lines
+}
+
+fn solver_positions(default: usize) -> usize {
+    let fit = CLI.solver.max_memory.map_or(usize::MAX, |megabytes| {
+        megabytes.saturating_mul(1 << 20) / solver::POSITION_BYTES
+    });
+
+    CLI.solver.max_positions.unwrap_or(default).min(fit)
+}
+
+// Where the game is drawn, from `--ui`
+fn screen() -> Box<dyn Renderer> {
+    #[cfg(feature = "ratatui")]
+    if CLI.play.ui == cli::Ui::Ratatui {
+        return Box::new(renderer::BufferedScreen::new());
+    }
+
+    Box::new(stdout())
+}
+
+// `solitare show` with the usual flags for the game and deal. Prints the
+// board as it is drawn when playing, without the colors.
+fn show_command() {
+    let rules = rules_from_args(default_game());
+    let (state, _) = deal_from_args(rules);
+
+    let board = HighlightedSolitareState(
+        state,
+        Highlight::None,
+        Highlight::None,
+        [Highlight::None; 2],
+        View {
+            compact: CONFIG.compact,
+            scroll: 0,
+            lines: u16::MAX,
+            layout: Layout::new(&state),
+        },
+    );
+    let mut screen = TextScreen::default();
+    screen.draw(0, 0, &board);
+
+    print!("{}", screen.text());
+}
+
+// `solitare edit`, starting from the file if it exists and otherwise from
+// the empty piles of the game. The position is played once done.
+fn edit_command(file: Option<&Path>) {
+    let path = file.unwrap_or(Path::new(editor::BOARD_PATH));
+    let text = fs::read_to_string(path).ok();
+
+    let game = match (game_from_args(), &text) {
+        (Some(game), _) => game,
+        (None, Some(_)) => board::game(path),
+        (None, None) => Game::default(),
+    };
+    let rules = rules_from_args(game);
+    let text = text.unwrap_or_else(|| editor::template(rules));
+
+    if let Some(state) = editor::run(&text, rules, path) {
+        GameState::new(state, None).run();
+    }
+}
+
+// `solitare puzzles`, going back to the list after each puzzle played
+fn puzzles_command() {
+    let puzzles = puzzle::all();
+
+    while let Some(i) = menu::pick_puzzle(&puzzles) {
+        let puzzle = &puzzles[i];
+        let state = puzzle.state().unwrap_or_else(|e| {
+            eprintln!("Invalid puzzle {}: {e}", puzzle.name);
+            std::process::exit(1)
+        });
+
+        let mut game = GameState::new(state, None);
+        game.puzzle = Some(puzzle.name);
+        game.run();
+    }
+}
+
+// `solitare solve` with the usual flags for the game and deal. Prints
+// whether the deal can be won, with the winning moves written as a record
+// that can be replayed.
+fn solve_command() {
+    let rules = rules_from_args(default_game());
+    let (state, seed) = deal_from_args(rules);
+
+    match solver::solve(&state, solver_positions(solver::MAX_POSITIONS)) {
+        Solution::Won(moves) => {
+            println!("# Winnable in {} moves", moves.len());
+            print!("{}", record::header(seed, &state));
+
+            let mut state = state;
+
+            for (i, m) in moves.into_iter().enumerate() {
+                println!("{}. {}", i + 1, m.notation(&state));
+                state.apply_move(m).unwrap();
+            }
+        }
+        Solution::Lost => println!("Not winnable"),
+        Solution::GaveUp => println!(
+            "Gave up after searching {} positions",
+            solver_positions(solver::MAX_POSITIONS)
+        ),
+    }
+}
+
+// `solitare analyze FILE`. Solves the positions of a recorded game one
+// after another to find the move after which it could no longer be won.
+// Positions on the way of an earlier solution are known to be winnable
+// without solving them again.
+fn analyze_command(path: &Path) {
+    let record::Record { history, error, .. } = record::read(path)
+        .unwrap_or_else(|e| {
+            eprintln!("Could not load {}: {e}", path.display());
+            std::process::exit(1)
+        });
+
+    if let Some(error) = error {
+        eprintln!("{error}");
+    }
+
+    // Winning moves from the current position, last first
+    let mut plan: Vec<Move> = Vec::new();
+    // Last position known to be winnable
+    let mut winnable = None;
+
+    for pos in 0..=history.len() {
+        let state = history.state(pos);
+
+        let on_plan = plan.pop().is_some_and(|m| {
+            let mut next = history.state(pos - 1);
+            next.apply_move(m).is_ok() && next == state
+        });
+
+        let verdict = if on_plan {
+            Some(true)
+        } else {
+            match solver::solve(
+                &state,
+                solver_positions(solver::WINNABLE_POSITIONS),
+            ) {
+                Solution::Won(moves) => {
+                    plan = moves.into_iter().rev().collect();
+                    Some(true)
+                }
+                Solution::Lost => Some(false),
+                Solution::GaveUp => {
+                    plan.clear();
+                    None
+                }
+            }
+        };
+
+        let label = match pos {
+            0 => String::from("start"),
+            pos => history.notation(pos - 1).to_owned(),
+        };
+        let verdict_text = match verdict {
+            Some(true) => "winnable",
+            Some(false) => "lost",
+            None => "unknown",
+        };
+        println!("{pos:>3}. {label:<10} {verdict_text}");
+
+        match verdict {
+            Some(true) => winnable = Some(pos),
+            Some(false) => {
+                match winnable {
+                    None => println!("\nThe deal could not be won"),
+                    Some(last) if last + 1 == pos => {
+                        println!("\nThe game was lost with move {pos}")
+                    }
+                    Some(last) => println!(
+                        "\nThe game was lost with one of moves {} to {pos}",
+                        last + 1
+                    ),
+                }
+                return;
+            }
+            None => {}
+        }
+    }
+
+    if history.state(history.len()).is_won() {
+        println!("\nThe game was won");
+    } else {
+        println!("\nThe game was not lost by the last move");
+    }
+}
+
+// `solitare batch N`. Solves deals from consecutive seeds on several
+// threads, each taking the next seed left. Deals are searched as far as
+// when looking for a winnable one, so some are left unknown.
+fn batch_command(deals: u64, jobs: Option<usize>) {
+    let rules = rules_from_args(default_game());
+    let first = CLI.deal.seed.unwrap_or_else(rand::random);
+    let jobs = jobs
+        .or_else(|| thread::available_parallelism().ok().map(usize::from))
+        .unwrap_or(1)
+        .max(1);
+
+    let next = AtomicU64::new(0);
+    let done = AtomicU64::new(0);
+
+    // Length of the winning line of each deal, None if it was lost and
+    // Some(None) if the solver gave up
+    let lengths: Vec<Option<Option<usize>>> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut lengths = Vec::new();
+
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        if i >= deals {
+                            return lengths;
+                        }
+
+                        let seed = first.wrapping_add(i);
+                        let state = SolitareState::new(seed, rules);
+                        lengths.push(
+                            match solver::solve(
+                                &state,
+                                solver_positions(solver::WINNABLE_POSITIONS),
+                            ) {
+                                Solution::Won(moves) => Some(Some(moves.len())),
+                                Solution::Lost => None,
+                                Solution::GaveUp => Some(None),
+                            },
+                        );
+
+                        let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                        eprint!("\rSolved {done} of {deals}");
+                    }
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().unwrap())
+            .collect()
+    });
+    eprintln!();
+
+    let won: Vec<usize> = lengths.iter().filter_map(|&l| l.flatten()).collect();
+    let lost = lengths.iter().filter(|l| l.is_none()).count();
+    let unknown = lengths.len() - won.len() - lost;
+
+    let percent = |n: usize| 100.0 * n as f64 / deals.max(1) as f64;
+    println!(
+        "{deals} deals of {} from seed {first}, draw {}\n",
+        rules.game.title(),
+        rules.draw
+    );
+    println!("Winnable {:>7} {:>6.1}%", won.len(), percent(won.len()));
+    println!("Lost     {lost:>7} {:>6.1}%", percent(lost));
+    println!("Unknown  {unknown:>7} {:>6.1}%", percent(unknown));
+
+    if won.is_empty() {
+        return;
+    }
+
+    let average = won.iter().sum::<usize>() as f64 / won.len() as f64;
+    println!("\nWinning lines are {average:.1} moves long on average\n");
+
+    // Lengths of the winning lines in at most 20 buckets, a multiple of 10
+    // moves wide, with bars of up to 50 columns
+    let (shortest, longest) =
+        (*won.iter().min().unwrap(), *won.iter().max().unwrap());
+    let width = ((longest - shortest) / 20 / 10 + 1) * 10;
+    let first = shortest / width;
+
+    let mut buckets = vec![0usize; longest / width - first + 1];
+    for length in &won {
+        buckets[length / width - first] += 1;
+    }
+    let most = *buckets.iter().max().unwrap();
+
+    for (i, &count) in buckets.iter().enumerate() {
+        let start = (first + i) * width;
+        let range = format!("{start}-{}", start + width - 1);
+        let bar = "#".repeat((count * 50).div_ceil(most));
+        println!("{}", format!("{range:>9} {count:>5} {bar}").trim_end());
     }
 }
 
 fn main() {
-    let mut game = GameState::new();
+    // A broken config file is reported before the screen is taken over
+    Lazy::force(&CONFIG);
+
+    match &CLI.command {
+        Some(Command::Solve) => solve_command(),
+        Some(Command::Analyze { file, batch, jobs }) => match (file, batch) {
+            (_, Some(deals)) => batch_command(*deals, *jobs),
+            (Some(file), None) => analyze_command(file),
+            // Ruled out by the arguments
+            (None, None) => unreachable!(),
+        },
+        Some(Command::Replay { file }) => {
+            let record = record::read(file).unwrap_or_else(|e| {
+                eprintln!("Could not load {}: {e}", file.display());
+                std::process::exit(1)
+            });
+
+            GameState::replay(record).run();
+        }
+        Some(Command::Stats) => print!("{}", daily::stats(Date::today())),
+        Some(Command::Show) => show_command(),
+        Some(Command::Edit { file }) => edit_command(file.as_deref()),
+        Some(Command::Puzzles) => puzzles_command(),
+        Some(Command::Play) | None if CLI.play.replay_string.is_some() => {
+            let code = CLI.play.replay_string.as_deref().unwrap();
+            let record = record::from_replay_string(code).unwrap_or_else(|e| {
+                eprintln!("Invalid replay string: {e}");
+                std::process::exit(1)
+            });
+
+            GameState::replay(record).run();
+        }
+        Some(Command::Play) | None if CLI.play.watch.is_some() => {
+            watch_command()
+        }
+        Some(Command::Play) | None if CLI.play.headless => headless_command(),
+        Some(Command::Play) | None => play_command(),
+    }
+}
+
+// `--watch HOST:PORT`, printing the boards of a game broadcast there
+fn watch_command() {
+    let address = CLI.play.watch.as_deref().unwrap();
+
+    if let Err(e) = broadcast::watch(address, CLI.play.json) {
+        eprintln!("Could not watch {address}: {e}");
+        std::process::exit(1)
+    }
+}
+
+// Plays the game given by the flags, Klondike by default, with commands
+// from stdin. The exit status tells whether it was won.
+fn headless_command() {
+    let rules = rules_from_args(default_game());
+    let (state, _) = deal_from_args(rules);
+
+    let won = headless::run(state, CLI.play.json);
+    std::process::exit(if won { 0 } else { 1 })
+}
+
+fn play_command() {
+    if CLI.deal.daily {
+        // Everyone plays the same game, Klondike unless another one is given
+        let date = Date::today();
+        let rules = rules_from_args(default_game());
+
+        let seed = date.seed();
+
+        let mut game =
+            GameState::new(SolitareState::new(seed, rules), Some(seed));
+        game.daily = Some(date);
+        game.run();
+        return;
+    }
+
+    if CLI.play.resume {
+        let mut game =
+            GameState::load(Path::new(SAVE_PATH)).unwrap_or_else(|e| {
+                eprintln!("Could not load {SAVE_PATH}: {e}");
+                std::process::exit(1)
+            });
+
+        game.bot = CLI.play.bot;
+        game.run();
+        return;
+    }
+
+    let game = match game_from_args() {
+        Some(game) => game,
+        // Share codes are dealt as Klondike unless another game is given
+        None if CLI.deal.deal.is_some() => Game::Klondike,
+        None if CLI.deal.board.is_some() || CLI.deal.ms_deal.is_some() => {
+            default_game()
+        }
+        None => match menu::pick_game() {
+            Some(game) => game,
+            None => return,
+        },
+    };
+    let (state, seed) = deal_from_args(rules_from_args(game));
+
+    let mut game = GameState::new(state, seed);
+    game.bot = CLI.play.bot;
+
+    if CLI.deal.deal.is_none()
+        && CLI.deal.seed.is_none()
+        && !game.bot
+        && GameState::has_unfinished_autosave()
+    {
+        game.prompt = Some(Prompt::Resume);
+    }
 
     // game.state.targets[2] = 6;
-    // game.state.slots_lens[3] &= 0x0f;
+    // game.state.slots_lens[3] = 0;
 
     game.run();
 }
@@ -0,0 +1,205 @@
+use std::io::{self, Stdout, Write, stdout};
+
+use crossterm::{
+    cursor,
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode,
+        KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
+    execute, queue,
+    style::{Print, ResetColor, Stylize},
+    terminal::{
+        EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode,
+        enable_raw_mode,
+    },
+};
+
+use solitare::rules::Game;
+
+use crate::{
+    puzzle::{self, Puzzle},
+    theme,
+};
+
+// Row of the first entry of a list
+const FIRST_ROW: u16 = 2;
+
+// Lets the player pick a game from a list with a short summary of each,
+// by number, with the arrow keys and enter or with a click. None if they
+// quit instead.
+pub fn pick_game() -> Option<Game> {
+    let entries: Vec<_> = Game::ALL
+        .into_iter()
+        .map(|game| format!("{:<15}{}", game.title(), game.summary()))
+        .collect();
+
+    pick("Pick a game:", &entries).map(|i| Game::ALL[i])
+}
+
+// Picks one of the puzzles, showing the ones solved with their fewest
+// moves
+pub fn pick_puzzle(puzzles: &[Puzzle]) -> Option<usize> {
+    let solved = puzzle::solved();
+    let entries: Vec<_> = puzzles
+        .iter()
+        .map(|puzzle| {
+            let result = match solved.get(puzzle.name) {
+                Some(moves) => format!("Solved in {moves} moves"),
+                None => String::new(),
+            };
+            format!("{:<20}{:<15}{result}", puzzle.name, puzzle.game.title())
+        })
+        .collect();
+
+    pick("Pick a puzzle:", &entries)
+}
+
+// Index of the entry picked from a list, by number, with the arrow keys
+// and enter or with a click
+fn pick(title: &str, entries: &[String]) -> Option<usize> {
+    let mut out = stdout();
+
+    enable_raw_mode().unwrap();
+    execute!(out, EnableMouseCapture, EnterAlternateScreen, cursor::Hide)
+        .unwrap();
+
+    let help = "Number, arrows and enter or click to start, q to quit";
+    let mut selected = 0;
+
+    let picked = loop {
+        draw_list(&mut out, title, entries, selected, Some(help)).unwrap();
+
+        match read_input(&mut selected, entries.len(), true) {
+            Input::Moved => {}
+            Input::Picked(i) => break Some(i),
+            Input::Back | Input::Quit => break None,
+        }
+    };
+
+    execute!(
+        out,
+        DisableMouseCapture,
+        ResetColor,
+        cursor::Show,
+        LeaveAlternateScreen
+    )
+    .unwrap();
+    disable_raw_mode().unwrap();
+
+    picked
+}
+
+// What a key press or click in a list did
+pub enum Input {
+    Moved, // The selection moved, and the list is drawn again
+    Picked(usize),
+    Back, // Esc or q
+    Quit, // Ctrl+C leaves the game from any list
+}
+
+// Waits for input on a list, moving `selected` with the arrow keys. In a
+// numbered list an entry is also picked by its number.
+pub fn read_input(
+    selected: &mut usize,
+    n_entries: usize,
+    numbered: bool,
+) -> Input {
+    loop {
+        let Ok(event) = event::read() else {
+            return Input::Back;
+        };
+
+        match event {
+            Event::Key(KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: _,
+                state: _,
+            }) => return Input::Quit,
+
+            Event::Key(KeyEvent {
+                code,
+                modifiers: KeyModifiers::NONE,
+                kind: _,
+                state: _,
+            }) => match code {
+                KeyCode::Up => {
+                    *selected = (*selected + n_entries - 1) % n_entries;
+                    return Input::Moved;
+                }
+                KeyCode::Down => {
+                    *selected = (*selected + 1) % n_entries;
+                    return Input::Moved;
+                }
+                KeyCode::Enter | KeyCode::Char(' ') => {
+                    return Input::Picked(*selected);
+                }
+                KeyCode::Char('q') | KeyCode::Esc => return Input::Back,
+                KeyCode::Char(c) if numbered => {
+                    let i = c.to_digit(10).and_then(|d| d.checked_sub(1));
+
+                    if let Some(i) = i.filter(|&i| (i as usize) < n_entries) {
+                        *selected = i as usize;
+                        return Input::Picked(i as usize);
+                    }
+                }
+                _ => {}
+            },
+
+            Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                row,
+                ..
+            }) => {
+                if let Some(i) = row
+                    .checked_sub(FIRST_ROW)
+                    .map(usize::from)
+                    .filter(|&i| i < n_entries)
+                {
+                    *selected = i;
+                    return Input::Picked(i);
+                }
+            }
+
+            Event::Resize(..) => return Input::Moved,
+
+            _ => {}
+        }
+    }
+}
+
+// List with the selected entry highlighted, numbered when there is help
+// for picking by number, and otherwise with the help for the arrow keys
+pub fn draw_list(
+    out: &mut Stdout,
+    title: &str,
+    entries: &[impl AsRef<str>],
+    selected: usize,
+    numbered_help: Option<&str>,
+) -> io::Result<()> {
+    theme::clear(out)?;
+    queue!(out, cursor::MoveTo(0, 0), Print(theme::plain(title).bold()))?;
+
+    for (i, entry) in entries.iter().enumerate() {
+        let line = match numbered_help {
+            Some(_) => format!("{}. {}", i + 1, entry.as_ref()),
+            None => format!("{:<20}", entry.as_ref()),
+        };
+
+        queue!(out, cursor::MoveTo(0, FIRST_ROW + i as u16))?;
+
+        if i == selected {
+            let selected = theme::current().selected;
+            queue!(out, Print(theme::plain(line).on(selected)))?;
+        } else {
+            queue!(out, Print(theme::plain(line)))?;
+        }
+    }
+
+    let help = numbered_help
+        .unwrap_or("Arrows and enter or click to pick, esc to go back");
+    let help_row = FIRST_ROW + entries.len() as u16 + 1;
+    queue!(out, cursor::MoveTo(0, help_row), Print(theme::dim(help)))?;
+
+    out.flush()
+}
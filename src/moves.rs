@@ -0,0 +1,231 @@
+use std::fmt::Display;
+
+use crate::{Location, SolitareState, card::SUIT_SYMBOLS};
+
+// A single move on the board. Only the top card of the waste can be
+// played, and cards go to the first foundation of their suit they fit on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Move {
+    Draw,            // From the stock, or turning the waste over
+    WasteToSlot(u8), // Slot
+    WasteToFoundation,
+    SlotToSlot(u8, u8, u8), // From slot, row of the first moved card, slot
+    SlotToFoundation(u8),   // Slot
+    FoundationToSlot(u8, u8), // Foundation, slot
+    SlotToCell(u8, u8),     // Slot, free cell
+    CellToSlot(u8, u8),     // Free cell, slot
+    CellToFoundation(u8),   // Free cell
+    // Pyramid: a pair of cards adding up to 13, or a lone king
+    Remove(Location, Option<Location>),
+}
+
+impl Move {
+    // Notation for the move as made from the given position, e.g. `W>S5`,
+    // `S2:4>F♥` or `C1>S3`, with `D` for drawing. Slots, rows and free
+    // cells are 1-indexed and foundations are named by their suit, followed
+    // by the deck number for the second foundation of a suit, like `F♥2`.
+    // Pyramid pairs are joined by `+`, like `S7:2+W`.
+    pub fn notation(self, state: &SolitareState) -> String {
+        match self {
+            Move::Draw => String::from("D"),
+            Move::WasteToSlot(col) => format!("W>S{}", col + 1),
+            Move::WasteToFoundation => {
+                let suit = state.waste_top().map(|c| c.suit());
+                format!("W>F{}", suit_symbol(suit))
+            }
+            Move::SlotToSlot(from_col, row, col) => {
+                format!("S{}:{}>S{}", from_col + 1, row + 1, col + 1)
+            }
+            Move::SlotToFoundation(col) => {
+                // The top card is the one moved, so its row is the length
+                let len = state.slot_len(col as usize);
+                let suit = len.checked_sub(1).map(|row| {
+                    state.slot_card(col as usize, row as usize).suit()
+                });
+                format!("S{}:{}>F{}", col + 1, len, suit_symbol(suit))
+            }
+            Move::FoundationToSlot(i, col) => {
+                let deck = match i / 4 {
+                    0 => String::new(),
+                    deck => (deck + 1).to_string(),
+                };
+                format!("F{}{deck}>S{}", suit_symbol(Some(i % 4)), col + 1)
+            }
+            Move::SlotToCell(col, cell) => {
+                let len = state.slot_len(col as usize);
+                format!("S{}:{}>C{}", col + 1, len, cell + 1)
+            }
+            Move::CellToSlot(cell, col) => {
+                format!("C{}>S{}", cell + 1, col + 1)
+            }
+            Move::CellToFoundation(cell) => {
+                let suit = state.cell(cell as usize).map(|c| c.suit());
+                format!("C{}>F{}", cell + 1, suit_symbol(suit))
+            }
+            Move::Remove(a, None) => location_notation(a),
+            Move::Remove(a, Some(b)) => {
+                format!("{}+{}", location_notation(a), location_notation(b))
+            }
+        }
+    }
+
+    // Parses the notation given by `notation`. Suits can also be given as
+    // one of the letters S, H, C or D.
+    pub fn from_notation(notation: &str) -> Option<Self> {
+        let notation = notation.trim();
+
+        if notation == "D" {
+            return Some(Move::Draw);
+        }
+
+        if !notation.contains('>') {
+            let (a, b) = match notation.split_once('+') {
+                Some((a, b)) => (a, Some(location_from_notation(b)?)),
+                None => (notation, None),
+            };
+            return Some(Move::Remove(location_from_notation(a)?, b));
+        }
+
+        let (src, dst) = notation.split_once('>')?;
+
+        // Foundation index from its suit and optional deck number
+        let foundation = |s: &str| {
+            let mut chars = s.chars();
+            let suit = match chars.next()? {
+                '♠' | 'S' | 's' => 0,
+                '♥' | 'H' | 'h' => 1,
+                '♣' | 'C' | 'c' => 2,
+                '♦' | 'D' | 'd' => 3,
+                _ => return None,
+            };
+            let deck = match chars.as_str() {
+                "" => 0,
+                deck => number(deck)?,
+            };
+            deck.checked_mul(4)?.checked_add(suit)
+        };
+
+        let (src_kind, src) = src.split_at_checked(1)?;
+        let (dst_kind, dst) = dst.split_at_checked(1)?;
+
+        match (src_kind, dst_kind) {
+            ("W", "S") if src.is_empty() => {
+                Some(Move::WasteToSlot(number(dst)?))
+            }
+            ("W", "F") if src.is_empty() => {
+                foundation(dst)?;
+                Some(Move::WasteToFoundation)
+            }
+            ("S", "S") => {
+                let (col, row) = src.split_once(':')?;
+                Some(Move::SlotToSlot(number(col)?, number(row)?, number(dst)?))
+            }
+            ("S", "F") => {
+                foundation(dst)?;
+                let (col, _) = src.split_once(':').unwrap_or((src, ""));
+                Some(Move::SlotToFoundation(number(col)?))
+            }
+            ("F", "S") => {
+                Some(Move::FoundationToSlot(foundation(src)?, number(dst)?))
+            }
+            ("S", "C") => {
+                let (col, _) = src.split_once(':').unwrap_or((src, ""));
+                Some(Move::SlotToCell(number(col)?, number(dst)?))
+            }
+            ("C", "S") => Some(Move::CellToSlot(number(src)?, number(dst)?)),
+            ("C", "F") => {
+                foundation(dst)?;
+                Some(Move::CellToFoundation(number(src)?))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn number(s: &str) -> Option<u8> {
+    s.parse::<u8>().ok()?.checked_sub(1)
+}
+
+// Only the waste and slots are used in Pyramid pairs
+fn location_notation(location: Location) -> String {
+    match location {
+        Location::Waste => String::from("W"),
+        Location::Slot(col, row) => format!("S{}:{}", col + 1, row + 1),
+        Location::Target(i) => format!("F{}", i + 1),
+        Location::Cell(i) => format!("C{}", i + 1),
+    }
+}
+
+fn location_from_notation(notation: &str) -> Option<Location> {
+    if notation == "W" {
+        return Some(Location::Waste);
+    }
+
+    let (col, row) = notation.strip_prefix('S')?.split_once(':')?;
+
+    Some(Location::Slot(number(col)?, number(row)?))
+}
+
+fn suit_symbol(suit: Option<u8>) -> char {
+    suit.and_then(|suit| SUIT_SYMBOLS.get(suit as usize).copied())
+        .unwrap_or('?')
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IllegalMove {
+    NoCard,        // Nothing to pick up at the source
+    NotTopCard,    // Only single cards can be moved there
+    SameSlot,      // Moving cards onto the slot they are already in
+    NotNextRank,   // Card is not the next one for its foundation
+    NotOneLower,   // Card is not one rank lower than the one it goes on
+    SameColor,     // Card has the same color as the one it goes on
+    OtherSuit,     // Card has another suit than the one it goes on
+    NotKing,       // Only kings can go on empty slots
+    NotARun,       // Cards moved together must be a descending run
+    NoPassesLeft,  // The stock can not be turned over again
+    OffFoundation, // The rules do not allow taking cards off foundations
+    OnlyRuns,      // Spider foundations only take complete runs
+    EmptySlot,     // Spider rows can only be dealt with no empty slots
+    TooManyCards,  // Not enough free cells and empty slots for the move
+    CellTaken,     // Free cells only hold a single card
+    NotThirteen,   // Pyramid cards are removed in pairs adding up to 13
+    NotAMove,      // The source and destination do not make up a move
+}
+
+impl Display for IllegalMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            IllegalMove::NoCard => "there is no card to move there",
+            IllegalMove::NotTopCard => "only a single card can go there",
+            IllegalMove::SameSlot => "the cards are already in that slot",
+            IllegalMove::NotNextRank => "the foundation needs another rank",
+            IllegalMove::NotOneLower => "the card must be one rank lower",
+            IllegalMove::SameColor => "the card must have the other color",
+            IllegalMove::OtherSuit => "the card must have the same suit",
+            IllegalMove::NotKing => "only a king can go on an empty slot",
+            IllegalMove::NotARun => "the cards below it are not a run",
+            IllegalMove::NoPassesLeft => {
+                "there are no passes through the stock left"
+            }
+            IllegalMove::OffFoundation => {
+                "cards can not be taken off the foundations"
+            }
+            IllegalMove::OnlyRuns => {
+                "only runs from king to ace go to the foundations"
+            }
+            IllegalMove::EmptySlot => {
+                "every slot needs a card before dealing a row"
+            }
+            IllegalMove::TooManyCards => {
+                "too few free cells and empty slots to move that many cards"
+            }
+            IllegalMove::CellTaken => "there is already a card in that cell",
+            IllegalMove::NotThirteen => "the cards must add up to 13",
+            IllegalMove::NotAMove => "that is not a move",
+        };
+
+        write!(f, "{reason}")
+    }
+}
+
+impl std::error::Error for IllegalMove {}
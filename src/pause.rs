@@ -0,0 +1,176 @@
+use std::io::{self, Stdout, Write};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, MouseEvent, MouseEventKind},
+    queue,
+    style::Print,
+};
+
+use crate::{
+    daily::{self, Date},
+    leaderboard,
+    menu::{Input, draw_list, read_input},
+    theme,
+};
+
+// What to do with the game after leaving the pause menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Choice {
+    Resume,
+    Restart,
+    NewDeal,
+    Quit,
+}
+
+// Settings of the game that can be changed from the pause menu
+pub struct Options {
+    pub one_tap: bool,
+    pub show_history: bool,
+    pub show_cards: bool,
+    pub win_chance: bool,
+}
+
+const ENTRIES: [&str; 7] = [
+    "Resume",
+    "Restart deal",
+    "New deal",
+    "Options",
+    "Stats",
+    "Leaderboard",
+    "Quit",
+];
+
+// Shows the pause menu over the board until something is picked, with the
+// arrow keys and enter or with a click. Esc or q goes back to the game. The
+// leaderboard opens on the variant being played.
+pub fn run(out: &mut Stdout, options: &mut Options, variant: &str) -> Choice {
+    let mut selected = 0;
+
+    loop {
+        draw_list(out, "Paused", &ENTRIES, selected, None).unwrap();
+
+        match read_input(&mut selected, ENTRIES.len(), false) {
+            Input::Moved => {}
+            Input::Picked(0) | Input::Back => return Choice::Resume,
+            Input::Picked(1) => return Choice::Restart,
+            Input::Picked(2) => return Choice::NewDeal,
+            Input::Picked(3) => {
+                if run_options(out, options) {
+                    return Choice::Quit;
+                }
+            }
+            Input::Picked(4) => show_stats(out).unwrap(),
+            Input::Picked(5) => show_leaderboard(out, variant).unwrap(),
+            Input::Picked(_) | Input::Quit => return Choice::Quit,
+        }
+    }
+}
+
+// Settings are toggled in place, until going back to the menu. True when
+// quitting the game from there instead.
+fn run_options(out: &mut Stdout, options: &mut Options) -> bool {
+    let mut selected = 0;
+
+    loop {
+        let on_off = |on| if on { "on" } else { "off" };
+        let entries = [
+            format!("One tap        {}", on_off(options.one_tap)),
+            format!("History panel  {}", on_off(options.show_history)),
+            format!("Hidden cards   {}", on_off(options.show_cards)),
+            format!("Win chance     {}", on_off(options.win_chance)),
+            format!("Theme          {}", theme::current_name().name()),
+            String::from("Back"),
+        ];
+        draw_list(out, "Options", &entries, selected, None).unwrap();
+
+        match read_input(&mut selected, entries.len(), false) {
+            Input::Moved => {}
+            Input::Picked(0) => options.one_tap = !options.one_tap,
+            Input::Picked(1) => options.show_history = !options.show_history,
+            Input::Picked(2) => options.show_cards = !options.show_cards,
+            Input::Picked(3) => options.win_chance = !options.win_chance,
+            Input::Picked(4) => {
+                theme::next();
+            }
+            Input::Picked(_) | Input::Back => return false,
+            Input::Quit => return true,
+        }
+    }
+}
+
+// Results of the daily deals, until a key is pressed
+fn show_stats(out: &mut Stdout) -> io::Result<()> {
+    theme::clear(out)?;
+
+    let stats = daily::stats(Date::today());
+    for (row, line) in stats.lines().enumerate() {
+        let line = theme::plain(line);
+        queue!(out, cursor::MoveTo(0, row as u16), Print(line))?;
+    }
+
+    let row = stats.lines().count() as u16 + 1;
+    let help = theme::dim("Press any key to go back");
+    queue!(out, cursor::MoveTo(0, row), Print(help))?;
+    out.flush()?;
+
+    loop {
+        match event::read()? {
+            Event::Key(_)
+            | Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(_),
+                ..
+            }) => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+// Leaderboard of every variant won so far, and the one being played,
+// switched between with the left and right arrow keys until another key
+// is pressed
+fn show_leaderboard(out: &mut Stdout, current: &str) -> io::Result<()> {
+    let mut leaderboard = leaderboard::load();
+    leaderboard.entry(current.to_owned()).or_default();
+
+    let variants: Vec<_> = leaderboard.keys().cloned().collect();
+    let mut shown = variants.iter().position(|v| v == current).unwrap();
+
+    loop {
+        theme::clear(out)?;
+
+        let variant = &variants[shown];
+        let lines = leaderboard::lines(variant, &leaderboard[variant], None);
+        for (row, line) in lines.iter().enumerate() {
+            let line = theme::plain(line);
+            queue!(out, cursor::MoveTo(0, row as u16), Print(line))?;
+        }
+
+        let row = lines.len() as u16 + 1;
+        let help = theme::dim(format!(
+            "Variant {} of {}, left and right to switch, any other key to \
+             go back",
+            shown + 1,
+            variants.len()
+        ));
+        queue!(out, cursor::MoveTo(0, row), Print(help))?;
+        out.flush()?;
+
+        match event::read()? {
+            Event::Key(KeyEvent {
+                code: KeyCode::Left,
+                ..
+            }) => shown = (shown + variants.len() - 1) % variants.len(),
+            Event::Key(KeyEvent {
+                code: KeyCode::Right,
+                ..
+            }) => shown = (shown + 1) % variants.len(),
+            Event::Key(_)
+            | Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(_),
+                ..
+            }) => return Ok(()),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
+
+use solitare::{Rules, SolitareState, rules::Game};
+
+use crate::board;
+
+// Positions to finish, shipped with the game
+const PUZZLES: &str = include_str!("puzzles.txt");
+
+pub struct Puzzle {
+    pub name: &'static str,
+    pub game: Game,
+    layout: String,
+}
+
+impl Puzzle {
+    // Played with the standard rules of its game, whatever the flags, so
+    // it is the same puzzle for everyone
+    pub fn state(&self) -> Result<SolitareState, String> {
+        board::parse(&self.layout, Rules::for_game(self.game))
+    }
+}
+
+// Every puzzle, in the order of the file
+pub fn all() -> Vec<Puzzle> {
+    let mut puzzles: Vec<Puzzle> = Vec::new();
+
+    for line in PUZZLES.lines() {
+        if let Some(name) =
+            line.strip_prefix('[').and_then(|l| l.strip_suffix(']'))
+        {
+            puzzles.push(Puzzle {
+                name,
+                game: Game::Klondike,
+                layout: String::new(),
+            });
+        } else if let Some(puzzle) = puzzles.last_mut() {
+            match line.strip_prefix("Game:") {
+                Some(name) => {
+                    puzzle.game = Game::from_name(name.trim()).unwrap();
+                }
+                None => puzzle.layout += &format!("{line}\n"),
+            }
+        }
+    }
+
+    puzzles
+}
+
+// Fewest moves each puzzle was solved in, by name, kept with the results
+// of the daily deals
+fn path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("solitare").join("puzzles.json"))
+}
+
+pub fn solved() -> BTreeMap<String, usize> {
+    path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+// Marks a puzzle solved, keeping the fewest moves it took
+pub fn record(name: &str, moves: usize) -> io::Result<()> {
+    let Some(path) = path() else {
+        return Ok(());
+    };
+
+    let mut solved = solved();
+    let fewest = solved.entry(name.to_owned()).or_insert(moves);
+    *fewest = moves.min(*fewest);
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(&solved)?)
+}
+
+// Line for the stats, counting only the puzzles still shipped
+pub fn stats() -> String {
+    let solved = solved();
+    let puzzles = all();
+    let n_solved = puzzles
+        .iter()
+        .filter(|puzzle| solved.contains_key(puzzle.name))
+        .count();
+
+    format!("Solved {n_solved} of {} puzzles\n", puzzles.len())
+}
@@ -0,0 +1,387 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use solitare::{
+    Move, Rules, SolitareState,
+    bits::{BitReader, BitWriter},
+    rules::{Game, Scoring},
+};
+
+use crate::{base64, history::History};
+
+// Game read back from a record file, along with why reading stopped early
+// if one of the moves could not be played
+pub struct Record {
+    pub seed: Option<u64>,
+    pub history: History,
+    pub error: Option<String>,
+}
+
+// Seed and starting deal followed by every move made, one per line
+pub fn write(
+    path: &Path,
+    seed: Option<u64>,
+    history: &History,
+) -> io::Result<()> {
+    let mut text = header(seed, &history.initial());
+
+    for i in 0..history.pos() {
+        text += &format!("{}. {}\n", i + 1, history.notation(i));
+    }
+
+    fs::write(path, text)
+}
+
+// Lines before the moves, giving the deal and rules
+pub fn header(seed: Option<u64>, initial: &SolitareState) -> String {
+    let mut text = String::new();
+
+    if let Some(seed) = seed {
+        text += &format!("seed {seed}\n");
+    }
+    text += &format!("deal {}\n", initial.share_code());
+    text += &rules_text(initial.rules());
+
+    text
+}
+
+// Only the rules that differ from the defaults are written
+fn rules_text(rules: Rules) -> String {
+    let mut text = String::new();
+
+    if rules.game != Game::Klondike {
+        text += &format!("game {}\n", rules.game.name());
+    }
+    if rules.suits != 4 {
+        text += &format!("suits {}\n", rules.suits);
+    }
+    if rules.decks != 1 {
+        text += &format!("decks {}\n", rules.decks);
+    }
+    if rules.slots != 7 {
+        text += &format!("slots {}\n", rules.slots);
+    }
+    if rules.draw != 1 {
+        text += &format!("draw {}\n", rules.draw);
+    }
+    if let Some(max_passes) = rules.max_passes {
+        text += &format!("passes {max_passes}\n");
+    }
+    if rules.any_card_on_empty_slot {
+        text += "rule any-on-empty\n";
+    }
+    if !rules.foundation_to_slot {
+        text += "rule no-foundation-to-slot\n";
+    }
+    if rules.thoughtful {
+        text += "rule thoughtful\n";
+    }
+    if rules.run_limit {
+        text += "rule run-limit\n";
+    }
+    match rules.scoring {
+        Scoring::Standard => {}
+        Scoring::Vegas => text += "scoring vegas\n",
+        Scoring::None => text += "scoring none\n",
+    }
+
+    text
+}
+
+fn invalid(line: usize, text: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, format!("line {line}: {text}"))
+}
+
+// Reads a file as written by `write`. The deal takes precedence over the
+// seed so hand edited records can use either. Moves may be numbered or not.
+// The deal is laid out once all the rules are read, wherever they are.
+pub fn read(path: &Path) -> io::Result<Record> {
+    let text = fs::read_to_string(path)?;
+
+    let mut seed = None;
+    let mut deal = None;
+    let mut rules = Rules::default();
+    let mut moves = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        } else if let Some(value) = line.strip_prefix("seed ") {
+            seed =
+                Some(value.trim().parse().map_err(|_| invalid(i + 1, line))?);
+        } else if let Some(value) = line.strip_prefix("game ") {
+            rules.game = Game::from_name(value.trim())
+                .ok_or_else(|| invalid(i + 1, line))?;
+        } else if let Some(value) = line.strip_prefix("suits ") {
+            rules.suits =
+                value.trim().parse().map_err(|_| invalid(i + 1, line))?;
+        } else if let Some(value) = line.strip_prefix("decks ") {
+            rules.decks =
+                value.trim().parse().map_err(|_| invalid(i + 1, line))?;
+        } else if let Some(value) = line.strip_prefix("slots ") {
+            rules.slots =
+                value.trim().parse().map_err(|_| invalid(i + 1, line))?;
+        } else if let Some(value) = line.strip_prefix("draw ") {
+            rules.draw = value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| invalid(i + 1, line))?;
+        } else if let Some(value) = line.strip_prefix("passes ") {
+            rules.max_passes = Some(
+                value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| invalid(i + 1, line))?,
+            );
+        } else if line == "rule any-on-empty" {
+            rules.any_card_on_empty_slot = true;
+        } else if line == "rule no-foundation-to-slot" {
+            rules.foundation_to_slot = false;
+        } else if line == "rule thoughtful" {
+            rules.thoughtful = true;
+        } else if line == "rule run-limit" {
+            rules.run_limit = true;
+        } else if let Some(value) = line.strip_prefix("scoring ") {
+            rules.scoring = match value.trim() {
+                "standard" => Scoring::Standard,
+                "vegas" => Scoring::Vegas,
+                "none" => Scoring::None,
+                _ => return Err(invalid(i + 1, line)),
+            };
+        } else if let Some(code) = line.strip_prefix("deal ") {
+            deal = Some((i + 1, code.trim()));
+        } else {
+            let notation =
+                line.split_once(". ").map_or(line, |(_, notation)| notation);
+
+            let m = Move::from_notation(notation)
+                .ok_or_else(|| invalid(i + 1, line))?;
+
+            moves.push((i + 1, m));
+        }
+    }
+
+    if !rules.is_valid() {
+        return Err(io::Error::new(ErrorKind::InvalidData, "invalid rules"));
+    }
+
+    let initial = match (deal, seed) {
+        (Some((line, code)), _) => SolitareState::from_share_code(code, rules)
+            .ok_or_else(|| invalid(line, &format!("deal {code}")))?,
+        (None, Some(seed)) => SolitareState::new(seed, rules),
+        (None, None) => {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "no seed or deal given",
+            ));
+        }
+    };
+
+    let mut state = initial;
+    let mut history = History::new(initial);
+    let mut error = None;
+
+    for (line, m) in moves {
+        let notation = m.notation(&state);
+
+        if let Err(e) = state.apply_move(m) {
+            error = Some(format!("Line {line}: {notation} is illegal, {e}"));
+            break;
+        }
+
+        history.push(notation, state);
+    }
+
+    Ok(Record {
+        seed,
+        history,
+        error,
+    })
+}
+
+// A whole game as a short string of base64, to share in a chat message
+// rather than as a file. The bits, lowest first:
+//
+//   version      8 bits
+//   rules        6 bytes, as in `Rules::to_bytes`
+//   deal         2 bits, 1 followed by the 64 bits of the seed if it is
+//                set, 0 followed by the cards of the share code in 6 bits
+//                each, or for positions not straight after dealing, 2
+//                followed by the length and bytes of `to_bytes`
+//   moves        their count in 16 bits, then each as its index among the
+//                moves possible at that point, in as few bits as it takes
+const REPLAY_VERSION: u32 = 1;
+
+pub fn to_replay_string(seed: Option<u64>, history: &History) -> String {
+    let initial = history.initial();
+    let mut w = BitWriter::default();
+
+    w.write(REPLAY_VERSION, 8);
+    for byte in initial.rules().to_bytes() {
+        w.write(byte as u32, 8);
+    }
+
+    match (seed, initial.fresh_share_code()) {
+        (Some(seed), _) => {
+            w.write(1, 2);
+            w.write(seed as u32, 32);
+            w.write((seed >> 32) as u32, 32);
+        }
+        (None, Some(code)) => {
+            w.write(0, 2);
+            for c in code.bytes() {
+                w.write(card_index(c).unwrap() as u32, 6);
+            }
+        }
+        (None, None) => {
+            let bytes = initial.to_bytes();
+            w.write(2, 2);
+            w.write(bytes.len() as u32, 8);
+            for byte in bytes {
+                w.write(byte as u32, 8);
+            }
+        }
+    }
+
+    w.write(history.pos() as u32, 16);
+    for i in 0..history.pos() {
+        let (state, next) = (history.state(i), history.state(i + 1));
+        let moves = possible_moves(&state);
+
+        // The move is the one leading to the next position
+        let index = moves
+            .iter()
+            .position(|&m| {
+                let mut after = state;
+                after.apply_move(m).is_ok() && after == next
+            })
+            .unwrap();
+        w.write(index as u32, index_bits(moves.len()));
+    }
+
+    base64::encode(&w.finish())
+}
+
+pub fn from_replay_string(code: &str) -> Result<Record, String> {
+    let bytes = base64::decode(code).ok_or("not base64")?;
+    let mut r = BitReader::new(&bytes);
+    let truncated = || String::from("too short");
+
+    if r.read(8).ok_or_else(truncated)? != REPLAY_VERSION {
+        return Err(String::from("unknown version"));
+    }
+    let rule_bytes = (0..6)
+        .map(|_| r.read(8).map(|byte| byte as u8))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(truncated)?;
+    let rules = Rules::from_bytes(&rule_bytes)?;
+
+    let (seed, initial) = match r.read(2).ok_or_else(truncated)? {
+        1 => {
+            let low = r.read(32).ok_or_else(truncated)? as u64;
+            let seed = low | (r.read(32).ok_or_else(truncated)? as u64) << 32;
+            (Some(seed), SolitareState::new(seed, rules))
+        }
+        0 => {
+            let code = (0..52 * rules.decks as usize)
+                .map(|_| r.read(6).map(card_letter))
+                .collect::<Option<String>>()
+                .ok_or_else(truncated)?;
+            let initial = SolitareState::from_share_code(&code, rules)
+                .ok_or("invalid deal")?;
+            (None, initial)
+        }
+        2 => {
+            let len = r.read(8).ok_or_else(truncated)?;
+            let bytes = (0..len)
+                .map(|_| r.read(8).map(|byte| byte as u8))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(truncated)?;
+            let initial = SolitareState::from_bytes(&bytes)?;
+            if initial.rules() != rules {
+                return Err(String::from("invalid deal"));
+            }
+            (None, initial)
+        }
+        _ => return Err(String::from("invalid deal")),
+    };
+
+    let mut state = initial;
+    let mut history = History::new(initial);
+
+    let n_moves = r.read(16).ok_or_else(truncated)?;
+    for i in 0..n_moves {
+        let moves = possible_moves(&state);
+        let m = r
+            .read(index_bits(moves.len()))
+            .and_then(|index| moves.get(index as usize))
+            .ok_or_else(|| format!("move {} is invalid", i + 1))?;
+
+        let notation = m.notation(&state);
+        state.apply_move(*m).unwrap();
+        history.push(notation, state);
+    }
+
+    Ok(Record {
+        seed,
+        history,
+        error: None,
+    })
+}
+
+// Moves that can be made from a position, in the same order every time.
+// Beyond the legal moves of the engine, cards can go to any empty free cell
+// and pyramid pairs can be picked either way round.
+fn possible_moves(state: &SolitareState) -> Vec<Move> {
+    let mut moves = state.legal_moves();
+
+    let extra: Vec<_> = if state.rules().game == Game::Pyramid {
+        moves
+            .iter()
+            .filter_map(|&m| match m {
+                Move::Remove(a, Some(b)) => Some(Move::Remove(b, Some(a))),
+                _ => None,
+            })
+            .collect()
+    } else {
+        let (n_slots, n_cells) = (state.n_slots() as u8, state.n_cells() as u8);
+        (0..n_slots)
+            .flat_map(|col| (0..n_cells).map(move |c| Move::SlotToCell(col, c)))
+            .filter(|&m| !moves.contains(&m) && state.check_move(m).is_ok())
+            .collect()
+    };
+    moves.extend(extra);
+
+    moves
+}
+
+// Bits for an index among n moves, none when there is only one
+fn index_bits(n: usize) -> u32 {
+    usize::BITS - n.saturating_sub(1).leading_zeros()
+}
+
+// Letters of share codes as card indices and back
+fn card_index(c: u8) -> Option<u8> {
+    match c {
+        b'a'..=b'z' => Some(c - b'a'),
+        b'A'..=b'Z' => Some(c - b'A' + 26),
+        _ => None,
+    }
+}
+
+fn card_letter(index: u32) -> char {
+    let index = index as u8;
+    match index {
+        0..26 => (b'a' + index) as char,
+        _ => (b'A' + index - 26) as char,
+    }
+}
@@ -0,0 +1,812 @@
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crossterm::{
+    Command, cursor,
+    style::{Color, StyledContent, Stylize},
+};
+use once_cell::sync::Lazy;
+
+use solitare::{Card, Location, SolitareState, rules::Game};
+
+use crate::{CLI, CONFIG, config::CardStyle, layout::Layout, theme};
+
+// Face down cards are shown while debugging, until switched off
+static REVEALED: Lazy<AtomicBool> =
+    Lazy::new(|| AtomicBool::new(CLI.play.debug));
+
+// Switches showing face down cards on or off, returning whether they are
+// shown now
+pub fn toggle_revealed() -> bool {
+    !REVEALED.fetch_xor(true, Ordering::Relaxed)
+}
+
+// Whether face down cards and the order of the stock are shown, dimmed, as
+// they are in Thoughtful
+pub fn shows_face_down(state: &SolitareState) -> bool {
+    state.rules().thoughtful || REVEALED.load(Ordering::Relaxed)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Highlight {
+    None,
+    Target(u8),
+    Stock, // Never selected, clicking it draws instead
+    Waste,
+    Slot(u8, u8),
+    FreeCell(u8),
+}
+
+impl Highlight {
+    pub fn location(self) -> Option<Location> {
+        match self {
+            Highlight::None | Highlight::Stock => None,
+            Highlight::Target(i) => Some(Location::Target(i)),
+            Highlight::Waste => Some(Location::Waste),
+            Highlight::Slot(col, row) => Some(Location::Slot(col, row)),
+            Highlight::FreeCell(i) => Some(Location::Cell(i)),
+        }
+    }
+}
+
+impl From<Location> for Highlight {
+    fn from(location: Location) -> Self {
+        match location {
+            Location::Target(i) => Highlight::Target(i),
+            Location::Waste => Highlight::Waste,
+            Location::Slot(col, row) => Highlight::Slot(col, row),
+            Location::Cell(i) => Highlight::FreeCell(i),
+        }
+    }
+}
+
+// Background a card is drawn on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shade {
+    Plain,
+    Selected,
+    Cursor,
+    Hint,
+    Illegal,
+}
+
+impl Shade {
+    fn background(self) -> Color {
+        let colors = theme::current();
+
+        match self {
+            Shade::Plain => colors.card,
+            Shade::Selected => colors.selected,
+            Shade::Cursor => colors.cursor,
+            Shade::Hint => colors.hint,
+            Shade::Illegal => colors.illegal,
+        }
+    }
+
+    // Puts content on the background of the shade. In high contrast mode
+    // shades are not told apart by their color but drawn reversed, and
+    // every card is bold.
+    fn paint<D: Display>(self, content: StyledContent<D>) -> StyledContent<D> {
+        if !CONFIG.high_contrast {
+            return content.on(self.background());
+        }
+
+        let content = content.on(theme::current().card).bold();
+        if self == Shade::Plain {
+            content
+        } else {
+            content.reverse()
+        }
+    }
+}
+
+// Rank of cards drawn in ASCII, a single letter for each
+const ASCII_RANKS: [char; 13] = [
+    'A', '2', '3', '4', '5', '6', '7', '8', '9', 'T', 'J', 'Q', 'K',
+];
+const ASCII_SUITS: [char; 4] = ['S', 'H', 'C', 'D'];
+
+// Columns of a card that are drawn on its background. Cards drawn as text
+// are followed by a gap to tell them apart.
+fn face_width() -> usize {
+    match CONFIG.cards {
+        CardStyle::Glyph => CONFIG.card_width() as usize,
+        _ => CONFIG.card_width() as usize - 1,
+    }
+}
+
+// Card as drawn in the chosen style, padded to the width of a card face
+fn face(card: Card) -> String {
+    let text = match CONFIG.cards {
+        CardStyle::Glyph => card.glyph().to_string(),
+        // Big cards are only drawn on a single line among other text
+        CardStyle::Suits | CardStyle::Big => card.name(),
+        CardStyle::Ascii => format!(
+            "{}{}",
+            ASCII_RANKS[card.rank() as usize - 1],
+            ASCII_SUITS[card.suit() as usize]
+        ),
+    };
+
+    format!("{text:<width$}", width = face_width())
+}
+
+// Spaces after a card face, up to the width of a card
+fn gap() -> &'static str {
+    match CONFIG.cards {
+        CardStyle::Glyph => "",
+        _ => " ",
+    }
+}
+
+// Symbol standing in for a card in the chosen style, like for the back of a
+// card, padded to the width of a card face
+fn symbol(glyph: &str, text: &str) -> String {
+    let symbol = match CONFIG.cards {
+        CardStyle::Glyph => glyph,
+        _ => text,
+    };
+
+    format!("{symbol:<width$}", width = face_width())
+}
+
+pub struct HighlightedCard(pub Card, pub Shade);
+
+impl Display for HighlightedCard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let HighlightedCard(card, shade) = self;
+
+        let theme = theme::current();
+        let colored_card = if card.is_red() {
+            face(*card).with(theme.red)
+        } else {
+            face(*card).with(theme.black)
+        };
+
+        write!(f, "{}", shade.paint(colored_card))?;
+        write!(f, "{}", theme::plain(gap()))
+    }
+}
+
+// Card that would be face down in normal play, shown when playing with
+// every card face up
+pub struct DimmedCard(pub Card);
+
+impl Display for DimmedCard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let theme = theme::current();
+        let dimmed = face(self.0).with(theme.dim).on(theme.card);
+
+        write!(f, "{dimmed}{}", theme::plain(gap()))
+    }
+}
+
+// Back of a face down card, on the background of a shade other than plain
+struct FaceDown(Shade);
+
+impl Display for FaceDown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let theme = theme::current();
+        let back = symbol("🂠", "##").with(theme.back);
+
+        if self.0 != Shade::Plain {
+            write!(f, "{}", self.0.paint(back))?;
+        } else {
+            write!(f, "{}", back.on(theme.table))?;
+        }
+
+        write!(f, "{}", theme::plain(gap()))
+    }
+}
+
+// Face down cards collapsed into a single line, showing how many there are
+// with as much of `▒×4` as fits a card
+struct FaceDownRun(u8);
+
+impl Display for FaceDownRun {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let n = self.0;
+        let texts = match CONFIG.cards {
+            CardStyle::Ascii => {
+                [format!("#x{n}"), format!("x{n}"), n.to_string()]
+            }
+            _ => [format!("▒×{n}"), format!("×{n}"), n.to_string()],
+        };
+        let text = texts
+            .into_iter()
+            .find(|text| text.chars().count() <= face_width())
+            .unwrap_or_else(|| String::from("+"));
+        let text = format!("{text:<width$}", width = face_width());
+
+        let theme = theme::current();
+        write!(f, "{}", text.with(theme.back).on(theme.table))?;
+        write!(f, "{}", theme::plain(gap()))
+    }
+}
+
+// Place without a card, like an empty foundation, shown by a grey symbol.
+// It is only marked when it is part of a hint or under the cursor.
+struct EmptySpot(&'static str, &'static str, Shade); // Glyph, text, shade
+
+impl Display for EmptySpot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let EmptySpot(glyph, text, shade) = *self;
+        let symbol = symbol(glyph, text);
+
+        if shade != Shade::Plain {
+            let dim = theme::current().dim;
+            write!(f, "{}", shade.paint(symbol.with(dim)))?;
+        } else {
+            write!(f, "{}", theme::dim(symbol))?;
+        }
+
+        write!(f, "{}", theme::plain(gap()))
+    }
+}
+
+// Card a foundation takes next, so it can be looked for on the board.
+// None once the foundation is full, and in the games where foundations are
+// not built up card by card.
+fn next_needed(state: &SolitareState, foundation: u8) -> Option<Card> {
+    if matches!(state.rules().game, Game::Spider | Game::Pyramid) {
+        return None;
+    }
+
+    let rank = state.target(foundation).map_or(0, |card| card.rank());
+    let suit = foundation % state.rules().suits;
+
+    (rank < 13).then(|| Card::from_suit_rank(suit, rank + 1))
+}
+
+// Faint card under a foundation, for the one it takes next
+struct NextNeeded(Option<Card>);
+
+impl Display for NextNeeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(card) => write!(f, "{}", theme::dim(face(card)))?,
+            None => write!(f, "{}", theme::plain(" ".repeat(face_width())))?,
+        }
+
+        write!(f, "{}", theme::plain(gap()))
+    }
+}
+
+// Space where there is no card at all
+fn blank() -> impl Display {
+    theme::plain(" ".repeat(CONFIG.card_width() as usize))
+}
+
+// What the box of a big card shows
+#[derive(Debug, Clone, Copy)]
+pub enum BigFace {
+    Up(Card),
+    Dimmed(Card), // Face down in normal play, see `DimmedCard`
+    Down,
+    DownRun(u8), // Face down cards collapsed into one, see `FaceDownRun`
+    Empty(&'static str), // Place without a card, with a symbol in the middle
+}
+
+// Card drawn as a box of `CONFIG.card_height()` lines, with its top left
+// corner at a column and row of the screen
+pub struct BigCard(pub u16, pub u16, pub BigFace, pub Shade);
+
+impl BigCard {
+    // Text of the lines of the box, from the top
+    fn lines(&self) -> [String; 4] {
+        match self.2 {
+            BigFace::Up(card) | BigFace::Dimmed(card) => {
+                let name = card.name();
+                [
+                    format!("┌{name:─<3}┐"),
+                    String::from("│   │"),
+                    String::from("│   │"),
+                    format!("└{name:─>3}┘"),
+                ]
+            }
+            BigFace::Down => {
+                ["┌───┐", "│░░░│", "│░░░│", "└───┘"].map(String::from)
+            }
+            BigFace::DownRun(n) => [
+                format!("┌{:─<3}┐", format!("×{n}")),
+                String::from("│░░░│"),
+                String::from("│░░░│"),
+                String::from("└───┘"),
+            ],
+            BigFace::Empty(symbol) => [
+                String::from("┌───┐"),
+                format!("│ {symbol} │"),
+                String::from("│   │"),
+                String::from("└───┘"),
+            ],
+        }
+    }
+
+    // A line of the box in its colors, where the cursor is
+    fn fmt_line(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        line: String,
+    ) -> std::fmt::Result {
+        let BigCard(_, _, face, shade) = *self;
+
+        let theme = theme::current();
+        let line = match face {
+            BigFace::Up(card) if card.is_red() => line.with(theme.red),
+            BigFace::Up(_) => line.with(theme.black),
+            BigFace::Dimmed(_) | BigFace::Empty(_) => line.with(theme.dim),
+            BigFace::Down | BigFace::DownRun(_) => line.with(theme.back),
+        };
+
+        // Face down cards and empty places are on the table unless they are
+        // shaded
+        match (face, shade) {
+            (BigFace::Dimmed(_), _) => write!(f, "{}", line.on(theme.card)),
+            (
+                BigFace::Down | BigFace::DownRun(_) | BigFace::Empty(_),
+                Shade::Plain,
+            ) => write!(f, "{}", line.on(theme.table)),
+            _ => write!(f, "{}", shade.paint(line)),
+        }
+    }
+}
+
+impl Display for BigCard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let BigCard(col, row, ..) = *self;
+
+        for (i, line) in self.lines().into_iter().enumerate() {
+            cursor::MoveTo(col, row + i as u16).write_ansi(f)?;
+            self.fmt_line(f, line)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Face down cards at the top of a slot that go without a line of their own,
+// as they are drawn on the line of the first one. Only runs of several
+// cards are collapsed, and not when every card is face up.
+fn collapsed(state: &SolitareState, col: usize, compact: bool) -> u8 {
+    let hidden = state.slot_hidden(col);
+
+    if compact
+        && hidden >= 2
+        && !shows_face_down(state)
+        && state.rules().game != Game::Pyramid
+    {
+        hidden - 1
+    } else {
+        0
+    }
+}
+
+// Lines a slot takes up, counting only the first line of the last card
+pub fn slot_lines(state: &SolitareState, col: usize, compact: bool) -> u8 {
+    state.slot_len(col) - collapsed(state, col, compact)
+}
+
+// Lines taken up by the longest slot
+pub fn slots_height(state: &SolitareState, compact: bool) -> u8 {
+    if state.rules().game == Game::Pyramid {
+        return state.max_height();
+    }
+
+    (0..state.n_slots())
+        .map(|col| slot_lines(state, col, compact))
+        .max()
+        .unwrap()
+}
+
+// Line of a slot a card is drawn on, counted from the first
+pub fn slot_line(
+    state: &SolitareState,
+    col: usize,
+    row: u8,
+    compact: bool,
+) -> u8 {
+    match collapsed(state, col, compact) {
+        0 => row,
+        _ if row < state.slot_hidden(col) => 0,
+        n => row - n,
+    }
+}
+
+// Card of a slot drawn on a line, counted from the first. The first line
+// of a collapsed run stands for its first card.
+pub fn slot_row(
+    state: &SolitareState,
+    col: usize,
+    line: u8,
+    compact: bool,
+) -> u8 {
+    match collapsed(state, col, compact) {
+        0 => line,
+        _ if line == 0 => 0,
+        n => line + n,
+    }
+}
+
+// Part of the slots shown on the screen
+#[derive(Debug, Clone, Copy)]
+pub struct View {
+    pub compact: bool,  // Runs of face down cards are collapsed
+    pub scroll: u16,    // Lines of the slots scrolled past
+    pub lines: u16,     // Lines of the slots fitting on the screen
+    pub layout: Layout, // Where the piles of the top row go
+}
+
+impl View {
+    fn shows(self, line: u16) -> bool {
+        (self.scroll..self.scroll + self.lines).contains(&line)
+    }
+}
+
+// Board with the selected cards, the keyboard cursor, and the source and
+// destination of a hint
+pub struct HighlightedSolitareState(
+    pub SolitareState,
+    pub Highlight,
+    pub Highlight,
+    pub [Highlight; 2],
+    pub View,
+);
+
+impl Display for HighlightedSolitareState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = &self.0;
+
+        if CONFIG.cards == CardStyle::Big {
+            return self.fmt_big(f);
+        }
+
+        let layout = self.4.layout;
+
+        cursor::MoveTo(layout.foundations, 0).write_ansi(f)?;
+        for i in 0..state.n_foundations() as u8 {
+            let shade =
+                self.shade(|h| matches!(h, Highlight::Target(t) if t == i));
+
+            // Removed Pyramid cards are not built up, so they are not shown
+            if state.rules().game == Game::Pyramid {
+                write!(f, "{}", blank())?;
+            } else if let Some(card) = state.target(i) {
+                write!(f, "{}", HighlightedCard(card, shade))?;
+            } else {
+                write!(f, "{}", EmptySpot("🂠", "[]", shade))?;
+            }
+        }
+
+        if let Some(separator) = layout.separator {
+            cursor::MoveTo(separator, 0).write_ansi(f)?;
+            write!(f, "{}", theme::plain("┃"))?;
+        }
+        cursor::MoveTo(layout.stock, layout.stock_row).write_ansi(f)?;
+
+        // Free cells take the place of the stock and waste
+        if state.rules().game == Game::FreeCell {
+            for i in 0..state.n_cells() {
+                let shade = self.shade(
+                    |h| matches!(h, Highlight::FreeCell(c) if c as usize == i),
+                );
+
+                if let Some(card) = state.cell(i) {
+                    write!(f, "{}", HighlightedCard(card, shade))?;
+                } else {
+                    write!(f, "{}", EmptySpot("▢", "()", shade))?;
+                }
+            }
+
+            return self.fmt_slots(f);
+        }
+
+        // An empty stock is shown greyed out, clicking it turns the waste,
+        // unless there are no passes left
+        let stock_shade = self.shade(|h| matches!(h, Highlight::Stock));
+
+        if stock_shade != Shade::Plain && state.stock_len() > 0 {
+            write!(f, "{}", FaceDown(stock_shade))?;
+        } else if let Some(card) = state.stock_cards().next()
+            && shows_face_down(state)
+        {
+            write!(f, "{}", DimmedCard(card))?;
+        } else if state.stock_len() > 0 {
+            write!(f, "{}", FaceDown(Shade::Plain))?;
+        } else if state.is_stock_exhausted() {
+            write!(f, "{}", EmptySpot("×", "x", Shade::Plain))?;
+        } else {
+            write!(f, "{}", EmptySpot("🂠", "[]", stock_shade))?;
+        }
+        // Gap before the waste, left out when stacked
+        cursor::MoveTo(layout.waste, layout.stock_row).write_ansi(f)?;
+
+        // When drawing several cards at a time they are fanned out, but
+        // only the top one can be played
+        let n_fanned = state.waste_len().min(state.rules().draw as usize);
+        let fan = state.waste_cards().skip(state.waste_len() - n_fanned);
+
+        for (i, card) in fan.enumerate() {
+            let shade = if i + 1 == n_fanned {
+                self.shade(|h| matches!(h, Highlight::Waste))
+            } else {
+                Shade::Plain
+            };
+            write!(f, "{}", HighlightedCard(card, shade))?;
+        }
+
+        self.fmt_slots(f)
+    }
+}
+
+impl HighlightedSolitareState {
+    // How to draw the card at the highlights matching `at`. Selecting
+    // cards takes precedence over the cursor, and the cursor over the hint.
+    fn shade(&self, at: impl Fn(Highlight) -> bool) -> Shade {
+        if at(self.1) {
+            Shade::Selected
+        } else if at(self.2) {
+            Shade::Cursor
+        } else if self.3.into_iter().any(at) {
+            Shade::Hint
+        } else {
+            Shade::Plain
+        }
+    }
+
+    // Everything below the top row
+    fn fmt_slots(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = &self.0;
+        let layout = self.4.layout;
+
+        // The line between the top row and the slots has the cards the
+        // foundations take next, unless the stock row is there
+        if !layout.stacked() {
+            cursor::MoveTo(layout.foundations, 1).write_ansi(f)?;
+            for i in 0..state.n_foundations() as u8 {
+                write!(f, "{}", NextNeeded(next_needed(state, i)))?;
+            }
+        }
+        cursor::MoveTo(0, layout.slots_row).write_ansi(f)?;
+
+        if state.rules().game == Game::Pyramid {
+            return self.fmt_pyramid(f);
+        }
+
+        let View { compact, .. } = self.4;
+
+        for line in 0..slots_height(state, compact) {
+            if !self.4.shows(line as u16) {
+                continue;
+            }
+
+            for col_ind in 0..state.n_slots() {
+                let row_ind = slot_row(state, col_ind, line, compact);
+
+                // Highlighting a slot card also highlights the ones on it
+                let shade = self.shade(|h| {
+                    matches!(h, Highlight::Slot(c, r) if c as usize == col_ind && row_ind >= r)
+                });
+
+                if row_ind == 0 && state.slot_len(col_ind) == 0 {
+                    write!(f, "{}", EmptySpot(" ", " ", shade))?;
+                } else if row_ind >= state.slot_len(col_ind) {
+                    write!(f, "{}", blank())?;
+                } else if line == 0 && collapsed(state, col_ind, compact) > 0 {
+                    let hidden = state.slot_hidden(col_ind);
+                    write!(f, "{}", FaceDownRun(hidden))?;
+                } else if row_ind < state.slot_hidden(col_ind)
+                    && shows_face_down(state)
+                {
+                    let card = state.slot_card(col_ind, row_ind as usize);
+                    write!(f, "{}", DimmedCard(card))?;
+                } else if row_ind < state.slot_hidden(col_ind) {
+                    write!(f, "{}", FaceDown(Shade::Plain))?;
+                } else {
+                    let card = state.slot_card(col_ind, row_ind as usize);
+                    write!(f, "{}", HighlightedCard(card, shade))?;
+                }
+            }
+            writeln!(f, "\r")?;
+        }
+
+        Ok(())
+    }
+
+    // Each row is shifted half a card to the left of the one below, so
+    // every card overlaps the two it covers. Removed cards leave a gap.
+    fn fmt_pyramid(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = &self.0;
+
+        let rows = state.n_slots();
+
+        for row in 0..rows {
+            if !self.4.shows(row as u16) {
+                continue;
+            }
+
+            let indent = (rows - 1 - row) * CONFIG.card_width() as usize;
+            write!(f, "{}", theme::plain(" ".repeat(indent)))?;
+
+            for i in 0..=row {
+                if let Some(card) = state.pyramid_card(row, i) {
+                    let shade = self.shade(|h| {
+                        matches!(h, Highlight::Slot(r, c) if r as usize == row && c as usize == i)
+                    });
+                    write!(f, "{}", HighlightedCard(card, shade))?;
+                } else {
+                    write!(f, "{}", blank())?;
+                }
+                write!(f, "{}", blank())?;
+            }
+            writeln!(f, "\r")?;
+        }
+
+        Ok(())
+    }
+
+    // Big cards are drawn at their place on the screen, as they take up
+    // several lines. Cards further down a slot are drawn over the ones
+    // above, leaving only their top line showing.
+    fn fmt_big(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = &self.0;
+        let w = CONFIG.card_width();
+        let height = CONFIG.card_height();
+        let game = state.rules().game;
+        let layout = self.4.layout;
+
+        // Removed Pyramid cards are not built up, so they are not shown
+        if game != Game::Pyramid {
+            for i in 0..state.n_foundations() as u8 {
+                let shade =
+                    self.shade(|h| matches!(h, Highlight::Target(t) if t == i));
+                let face = match state.target(i) {
+                    Some(card) => BigFace::Up(card),
+                    None => BigFace::Empty(" "),
+                };
+                let col = layout.foundation(i);
+                write!(f, "{}", BigCard(col, 0, face, shade))?;
+
+                // Below it, the card it takes next, unless the stock is
+                if let Some(card) = next_needed(state, i)
+                    && !layout.stacked()
+                {
+                    cursor::MoveTo(col + 1, height).write_ansi(f)?;
+                    write!(f, "{}", theme::dim(card.name()))?;
+                }
+            }
+        }
+
+        if let Some(separator) = layout.separator {
+            for row in 0..height {
+                cursor::MoveTo(separator, row).write_ansi(f)?;
+                write!(f, "{}", theme::plain("┃"))?;
+            }
+        }
+        let (stock_col, stock_row) = (layout.stock, layout.stock_row);
+
+        if game == Game::FreeCell {
+            for i in 0..state.n_cells() {
+                let shade = self.shade(
+                    |h| matches!(h, Highlight::FreeCell(c) if c as usize == i),
+                );
+                let face = match state.cell(i) {
+                    Some(card) => BigFace::Up(card),
+                    None => BigFace::Empty(" "),
+                };
+                let col = stock_col + i as u16 * w;
+                write!(f, "{}", BigCard(col, stock_row, face, shade))?;
+            }
+        } else {
+            let shade = self.shade(|h| matches!(h, Highlight::Stock));
+            let face = if let Some(card) = state.stock_cards().next()
+                && shows_face_down(state)
+            {
+                BigFace::Dimmed(card)
+            } else if state.stock_len() > 0 {
+                BigFace::Down
+            } else if state.is_stock_exhausted() {
+                BigFace::Empty("×")
+            } else {
+                BigFace::Empty(" ")
+            };
+            write!(f, "{}", BigCard(stock_col, stock_row, face, shade))?;
+
+            let n_fanned = state.waste_len().min(state.rules().draw as usize);
+            let fan = state.waste_cards().skip(state.waste_len() - n_fanned);
+            let waste_col = layout.waste;
+
+            for (i, card) in fan.enumerate() {
+                let shade = if i + 1 == n_fanned {
+                    self.shade(|h| matches!(h, Highlight::Waste))
+                } else {
+                    Shade::Plain
+                };
+                let col = waste_col + i as u16 * w;
+                let card = BigCard(col, stock_row, BigFace::Up(card), shade);
+                write!(f, "{card}")?;
+            }
+        }
+
+        if game == Game::Pyramid {
+            let rows = state.n_slots();
+
+            for row in 0..rows {
+                for i in 0..=row {
+                    let Some(card) = state.pyramid_card(row, i) else {
+                        continue;
+                    };
+                    let shade = self.shade(|h| {
+                        matches!(h, Highlight::Slot(r, c) if r as usize == row && c as usize == i)
+                    });
+                    let col = ((rows - 1 - row + 2 * i) as u16) * w;
+                    let card = BigCard(col, 0, BigFace::Up(card), shade);
+                    self.fmt_big_slot_card(f, card, row as u16)?;
+                }
+            }
+
+            return Ok(());
+        }
+
+        let View { compact, .. } = self.4;
+
+        for col_ind in 0..state.n_slots() {
+            let col = col_ind as u16 * w;
+            let hidden = state.slot_hidden(col_ind);
+            let n_collapsed = collapsed(state, col_ind, compact);
+
+            for line in 0..slot_lines(state, col_ind, compact).max(1) {
+                let row_ind = slot_row(state, col_ind, line, compact);
+
+                // Highlighting a slot card also highlights the ones on it
+                let shade = self.shade(|h| {
+                    matches!(h, Highlight::Slot(c, r) if c as usize == col_ind && row_ind >= r)
+                });
+
+                let face = if state.slot_len(col_ind) == 0 {
+                    BigFace::Empty(" ")
+                } else {
+                    let card = state.slot_card(col_ind, row_ind as usize);
+
+                    if line == 0 && n_collapsed > 0 {
+                        BigFace::DownRun(hidden)
+                    } else if row_ind >= hidden {
+                        BigFace::Up(card)
+                    } else if shows_face_down(state) {
+                        BigFace::Dimmed(card)
+                    } else {
+                        BigFace::Down
+                    }
+                };
+                let card = BigCard(col, 0, face, shade);
+                self.fmt_big_slot_card(f, card, line as u16)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Big card with its top on a line of the slots, leaving out the lines
+    // scrolled out of view
+    fn fmt_big_slot_card(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        card: BigCard,
+        line: u16,
+    ) -> std::fmt::Result {
+        let view = self.4;
+        let slots_row = view.layout.slots_row;
+
+        for (i, text) in card.lines().into_iter().enumerate() {
+            let line = line + i as u16;
+            if view.shows(line) {
+                let row = slots_row + line - view.scroll;
+                cursor::MoveTo(card.0, row).write_ansi(f)?;
+                card.fmt_line(f, text)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+use std::{fmt::Display, io::Write};
+
+use crossterm::{cursor, queue, style::Print};
+
+#[cfg(feature = "ratatui")]
+mod buffered;
+#[cfg(feature = "ratatui")]
+pub use buffered::BufferedScreen;
+
+use solitare::Card;
+
+use crate::{
+    CONFIG,
+    config::CardStyle,
+    render::{BigCard, BigFace, HighlightedCard, Shade},
+    theme,
+};
+
+// Where the game is drawn. Everything is styled text put at a column and
+// row of the screen, and only shown once the frame is presented.
+pub trait Renderer {
+    // Empties the screen, leaving only the table
+    fn clear(&mut self);
+
+    fn draw(&mut self, col: u16, row: u16, content: &dyn Display);
+
+    // Card with its top left corner at a column and row, in the card style
+    // of the config
+    fn draw_card(&mut self, col: u16, row: u16, card: Card, shade: Shade) {
+        if CONFIG.cards == CardStyle::Big {
+            self.draw(col, row, &BigCard(col, row, BigFace::Up(card), shade));
+        } else {
+            self.draw(col, row, &HighlightedCard(card, shade));
+        }
+    }
+
+    fn present(&mut self);
+
+    // The terminal was drawn on some other way, so what is shown can no
+    // longer be known
+    fn invalidate(&mut self) {}
+}
+
+// The terminal, or anything else taking what is written to it, drawn on
+// with crossterm
+impl<W: Write> Renderer for W {
+    fn clear(&mut self) {
+        theme::clear(self).unwrap();
+        queue!(self, cursor::MoveTo(0, 0)).unwrap();
+    }
+
+    fn draw(&mut self, col: u16, row: u16, content: &dyn Display) {
+        queue!(self, cursor::MoveTo(col, row), Print(content)).unwrap();
+    }
+
+    fn present(&mut self) {
+        self.flush().unwrap();
+    }
+}
+
+// Screen kept as lines of plain text, leaving out the colors and styles.
+// Cursor movements in what is drawn, as for big cards, are followed.
+#[derive(Default)]
+pub struct TextScreen {
+    lines: Vec<Vec<char>>,
+    cursor: (usize, usize), // Column and row
+}
+
+impl TextScreen {
+    // Text of the screen, without trailing spaces
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(|line| {
+                let line: String = line.iter().collect();
+                line.trim_end().to_owned() + "\n"
+            })
+            .collect()
+    }
+
+    fn put(&mut self, c: char) {
+        let (col, row) = self.cursor;
+
+        if self.lines.len() <= row {
+            self.lines.resize(row + 1, Vec::new());
+        }
+        let line = &mut self.lines[row];
+        if line.len() <= col {
+            line.resize(col + 1, ' ');
+        }
+
+        line[col] = c;
+        self.cursor.0 += 1;
+    }
+
+    // Writes text at the cursor. Of the escape sequences, only moving the
+    // cursor does anything.
+    fn write(&mut self, text: &str) {
+        parse(text, |token| match token {
+            Token::Char(c) => self.put(c),
+            Token::MoveTo(col, row) => {
+                self.cursor = (col as usize, row as usize)
+            }
+            Token::Style(_) => {}
+            Token::Return => self.cursor.0 = 0,
+            Token::NewLine => self.cursor.1 += 1,
+        });
+    }
+}
+
+// Piece of styled text as written to the terminal
+pub enum Token<'a> {
+    Char(char),
+    MoveTo(u16, u16), // Column and row
+    #[cfg_attr(not(feature = "ratatui"), allow(dead_code))]
+    Style(&'a str), // Parameters of a `ESC [ … m` sequence, like `1;7`
+    Return,
+    NewLine,
+}
+
+// Goes through the characters and escape sequences of text written to the
+// terminal. Sequences other than moving the cursor and styling the text
+// are left out.
+pub fn parse<'a>(text: &'a str, mut f: impl FnMut(Token<'a>)) {
+    let mut chars = text.char_indices();
+
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '\x1b' => {
+                let Some((start, '[')) = chars.next() else {
+                    continue;
+                };
+                // Parameters up to the letter ending the sequence
+                let Some((end, letter)) =
+                    chars.by_ref().find(|(_, c)| c.is_ascii_alphabetic())
+                else {
+                    return;
+                };
+                let params = &text[start + 1..end];
+
+                match letter {
+                    'H' => {
+                        // Row and column counted from 1
+                        let mut numbers = params
+                            .split(';')
+                            .map(|n| n.parse::<u16>().unwrap_or(1).max(1) - 1);
+                        let row = numbers.next().unwrap_or(0);
+                        let col = numbers.next().unwrap_or(0);
+                        f(Token::MoveTo(col, row));
+                    }
+                    'm' => f(Token::Style(params)),
+                    _ => {}
+                }
+            }
+            '\r' => f(Token::Return),
+            '\n' => f(Token::NewLine),
+            c => f(Token::Char(c)),
+        }
+    }
+}
+
+impl Renderer for TextScreen {
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn draw(&mut self, col: u16, row: u16, content: &dyn Display) {
+        self.cursor = (col as usize, row as usize);
+        self.write(&content.to_string());
+    }
+
+    fn present(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use solitare::{Rules, SolitareState, rules::Game};
+
+    use super::*;
+    use crate::{
+        layout::Layout,
+        render::{Highlight, HighlightedSolitareState, View},
+    };
+
+    #[test]
+    fn text_is_drawn_over_what_was_there() {
+        let mut screen = TextScreen::default();
+        screen.draw(2, 1, &"\x1b[1mbold\x1b[0m");
+        screen.draw(4, 1, &"ld");
+        screen.draw(0, 0, &"a\r\nb");
+
+        assert_eq!(screen.text(), "a\nb bold\n");
+    }
+
+    // Tests are run with big cards, drawn line by line with the cursor
+    #[test]
+    fn deal_is_drawn_in_big_cards() {
+        let state = SolitareState::new(4, Rules::for_game(Game::Klondike));
+        let board = HighlightedSolitareState(
+            state,
+            Highlight::None,
+            Highlight::None,
+            [Highlight::None; 2],
+            View {
+                compact: false,
+                scroll: 0,
+                lines: u16::MAX,
+                layout: Layout::new(&state),
+            },
+        );
+
+        let mut screen = TextScreen::default();
+        screen.draw(0, 0, &board);
+
+        let expected = "\
+┌───┐ ┌───┐ ┌───┐ ┌───┐  ┃ ┌───┐
+│   │ │   │ │   │ │   │  ┃ │░░░│
+│   │ │   │ │   │ │   │  ┃ │░░░│
+└───┘ └───┘ └───┘ └───┘  ┃ └───┘
+ A♠    A♥    A♣    A♦
+┌3♣─┐ ┌───┐ ┌───┐ ┌───┐ ┌───┐ ┌───┐ ┌───┐
+│   │ ┌5♠─┐ ┌───┐ ┌───┐ ┌───┐ ┌───┐ ┌───┐
+│   │ │   │ ┌8♦─┐ ┌───┐ ┌───┐ ┌───┐ ┌───┐
+└─3♣┘ │   │ │   │ ┌7♦─┐ ┌───┐ ┌───┐ ┌───┐
+      └─5♠┘ │   │ │   │ ┌3♦─┐ ┌───┐ ┌───┐
+            └─8♦┘ │   │ │   │ ┌9♥─┐ ┌───┐
+                  └─7♦┘ │   │ │   │ ┌Q♦─┐
+                        └─3♦┘ │   │ │   │
+                              └─9♥┘ │   │
+                                    └─Q♦┘
+";
+        assert_eq!(screen.text(), expected);
+    }
+}
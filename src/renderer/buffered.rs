@@ -0,0 +1,139 @@
+use std::{
+    fmt::Display,
+    io::{Stdout, stdout},
+};
+
+use crossterm::style::SetBackgroundColor;
+use ratatui::{
+    Terminal,
+    backend::CrosstermBackend,
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+};
+
+use super::{Renderer, Token, parse};
+use crate::theme;
+
+// Frames drawn with ratatui. Ratatui keeps the frame last shown and only
+// writes the cells that changed, so redrawing the whole board after every
+// click does not make the screen flicker as clearing the terminal does.
+pub struct BufferedScreen {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    frame: Buffer, // Shown at the next present
+    cursor: (u16, u16),
+    style: Style,
+}
+
+impl BufferedScreen {
+    pub fn new() -> Self {
+        let terminal = Terminal::new(CrosstermBackend::new(stdout())).unwrap();
+        let size = terminal.size().unwrap();
+
+        Self {
+            terminal,
+            frame: Buffer::empty(Rect::new(0, 0, size.width, size.height)),
+            cursor: (0, 0),
+            style: Style::default(),
+        }
+    }
+
+    fn put(&mut self, c: char) {
+        if let Some(cell) = self.frame.cell_mut(self.cursor) {
+            cell.set_char(c).set_style(self.style);
+        }
+        self.cursor.0 = self.cursor.0.saturating_add(1);
+    }
+
+    // Follows the parameters of a `ESC [ … m` sequence, as written by
+    // crossterm for the colors and attributes of the themes
+    fn set_style(&mut self, params: &str) {
+        let mut numbers =
+            params.split(';').map(|n| n.parse::<u8>().unwrap_or(0));
+
+        while let Some(n) = numbers.next() {
+            let style = self.style;
+            self.style = match n {
+                0 => Style::reset(),
+                1 => style.add_modifier(Modifier::BOLD),
+                2 => style.add_modifier(Modifier::DIM),
+                7 => style.add_modifier(Modifier::REVERSED),
+                22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+                27 => style.remove_modifier(Modifier::REVERSED),
+                30..=37 => style.fg(Color::Indexed(n - 30)),
+                38 => style.fg(color(&mut numbers)),
+                39 => style.fg(Color::Reset),
+                40..=47 => style.bg(Color::Indexed(n - 40)),
+                48 => style.bg(color(&mut numbers)),
+                49 => style.bg(Color::Reset),
+                90..=97 => style.fg(Color::Indexed(n - 90 + 8)),
+                100..=107 => style.bg(Color::Indexed(n - 100 + 8)),
+                _ => style,
+            };
+        }
+    }
+}
+
+// Color after a 38 or 48, either `5;n` from the 256 colors or `2;r;g;b`
+fn color(numbers: &mut impl Iterator<Item = u8>) -> Color {
+    match numbers.next() {
+        Some(5) => Color::Indexed(numbers.next().unwrap_or(0)),
+        Some(2) => {
+            let mut next = || numbers.next().unwrap_or(0);
+            Color::Rgb(next(), next(), next())
+        }
+        _ => Color::Reset,
+    }
+}
+
+impl Renderer for BufferedScreen {
+    fn clear(&mut self) {
+        let size = self.terminal.size().unwrap();
+        self.frame = Buffer::empty(Rect::new(0, 0, size.width, size.height));
+
+        // The whole frame on the color of the table
+        self.style = Style::reset();
+        let table = SetBackgroundColor(theme::current().table).to_string();
+        parse(&table, |token| {
+            if let Token::Style(params) = token {
+                self.set_style(params);
+            }
+        });
+        self.frame.set_style(self.frame.area, self.style);
+        self.cursor = (0, 0);
+    }
+
+    fn draw(&mut self, col: u16, row: u16, content: &dyn Display) {
+        self.cursor = (col, row);
+
+        parse(&content.to_string(), |token| match token {
+            Token::Char(c) => self.put(c),
+            Token::MoveTo(col, row) => self.cursor = (col, row),
+            Token::Style(params) => self.set_style(params),
+            Token::Return => self.cursor.0 = 0,
+            Token::NewLine => self.cursor.1 = self.cursor.1.saturating_add(1),
+        });
+    }
+
+    fn present(&mut self) {
+        // Cut to the size of the terminal, in case it changed since the
+        // frame was cleared
+        self.terminal
+            .draw(|frame| {
+                let buf = frame.buffer_mut();
+                for at in buf.area.intersection(self.frame.area).positions() {
+                    buf[at] = self.frame[at].clone();
+                }
+            })
+            .unwrap();
+    }
+
+    // Clears the terminal and forgets the frame last shown. Resizing does
+    // so without asking the terminal where its cursor is, as clear would.
+    fn invalidate(&mut self) {
+        let size = self.terminal.size().unwrap();
+        self.terminal
+            .resize(Rect::new(0, 0, size.width, size.height))
+            .unwrap();
+    }
+}
@@ -0,0 +1,239 @@
+use crate::{
+    card::Card,
+    solitare_state::{MAX_CELLS, MAX_DECKS, MAX_SLOTS},
+};
+
+// Which solitaire game is played. The games share the cards, slots and
+// foundations, but deal and build differently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Game {
+    #[default]
+    Klondike,
+    // Rows are dealt from the stock onto every slot, and complete runs of
+    // a suit from king to ace go to the foundations by themselves
+    Spider,
+    // Every card is dealt face up, with free cells instead of a stock
+    FreeCell,
+    // The slots are the rows of a pyramid, cleared by removing pairs of
+    // uncovered cards adding up to 13
+    Pyramid,
+    // Two decks dealt face up, built down by suit one card at a time
+    FortyThieves,
+}
+
+impl Game {
+    pub const ALL: [Game; 5] = [
+        Game::Klondike,
+        Game::Spider,
+        Game::FreeCell,
+        Game::Pyramid,
+        Game::FortyThieves,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Game::Klondike => "klondike",
+            Game::Spider => "spider",
+            Game::FreeCell => "freecell",
+            Game::Pyramid => "pyramid",
+            Game::FortyThieves => "fortythieves",
+        }
+    }
+
+    // Whether cards in the slots are built down in suit rather than in
+    // alternating colors
+    pub fn builds_in_suit(self) -> bool {
+        matches!(self, Game::Spider | Game::FortyThieves)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|game| game.name() == name)
+    }
+
+    // Name as shown to the player
+    pub fn title(self) -> &'static str {
+        match self {
+            Game::Klondike => "Klondike",
+            Game::Spider => "Spider",
+            Game::FreeCell => "FreeCell",
+            Game::Pyramid => "Pyramid",
+            Game::FortyThieves => "Forty Thieves",
+        }
+    }
+
+    // Rules of the game in a few words
+    pub fn summary(self) -> &'static str {
+        match self {
+            Game::Klondike => {
+                "Build down in alternating colors, drawing from the stock"
+            }
+            Game::Spider => "Two decks, build runs of a suit from king to ace",
+            Game::FreeCell => "Every card face up, with four free cells",
+            Game::Pyramid => "Remove pairs of uncovered cards adding up to 13",
+            Game::FortyThieves => {
+                "Two decks face up, build down by suit one card at a time"
+            }
+        }
+    }
+}
+
+// How a game is scored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Scoring {
+    #[default]
+    Standard, // Points for moves, as in the classic Windows version
+    Vegas, // Money per foundation card, kept across deals by the frontend
+    None,
+}
+
+// Rules of the game that can be changed from standard Klondike
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Rules {
+    pub draw: u8,                     // Cards turned over from the stock
+    pub max_passes: Option<u8>,       // Passes through the stock, if limited
+    pub any_card_on_empty_slot: bool, // Not only kings
+    pub foundation_to_slot: bool,     // Cards can be taken off foundations
+    pub thoughtful: bool,             // Every card is dealt face up
+    pub scoring: Scoring,
+    pub decks: u8, // Full decks shuffled together
+    pub slots: u8, // Working slots the cards are dealt to
+    pub game: Game,
+    // Suits in play, 1, 2 or 4. With fewer suits there are more copies of
+    // each card, so the number of cards stays the same.
+    pub suits: u8,
+    pub cells: u8, // Free cells holding a single card each
+    // Runs are only moved as far as the free cells and empty slots allow,
+    // as in FreeCell where it is always the rule
+    pub run_limit: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            draw: 1,
+            max_passes: None,
+            any_card_on_empty_slot: false,
+            foundation_to_slot: true,
+            thoughtful: false,
+            scoring: Scoring::Standard,
+            decks: 1,
+            slots: 7,
+            game: Game::Klondike,
+            suits: 4,
+            cells: 0,
+            run_limit: false,
+        }
+    }
+}
+
+impl Rules {
+    // Standard rules of a game, with all four suits in Spider
+    pub fn for_game(game: Game) -> Self {
+        match game {
+            Game::Klondike => Self::default(),
+            Game::Spider => Self::spider(4),
+            Game::FreeCell => Self::freecell(),
+            Game::Pyramid => Self::pyramid(),
+            Game::FortyThieves => Self::forty_thieves(),
+        }
+    }
+
+    // Spider with two decks dealt to ten slots
+    pub fn spider(suits: u8) -> Self {
+        Self {
+            foundation_to_slot: false,
+            decks: 2,
+            slots: 10,
+            game: Game::Spider,
+            suits,
+            ..Self::default()
+        }
+    }
+
+    // FreeCell with eight slots and four free cells
+    pub fn freecell() -> Self {
+        Self {
+            any_card_on_empty_slot: true,
+            foundation_to_slot: false,
+            scoring: Scoring::None,
+            slots: 8,
+            game: Game::FreeCell,
+            cells: 4,
+            ..Self::default()
+        }
+    }
+
+    // Pyramid with seven rows and three passes through the stock
+    pub fn pyramid() -> Self {
+        Self {
+            max_passes: Some(3),
+            foundation_to_slot: false,
+            game: Game::Pyramid,
+            ..Self::default()
+        }
+    }
+
+    // Forty Thieves with ten slots and a single pass through the stock
+    pub fn forty_thieves() -> Self {
+        Self {
+            max_passes: Some(1),
+            any_card_on_empty_slot: true,
+            foundation_to_slot: false,
+            decks: 2,
+            slots: 10,
+            game: Game::FortyThieves,
+            ..Self::default()
+        }
+    }
+
+    // Whether a game can be dealt and played by these rules. There must be
+    // enough cards to fill the slots when dealing.
+    pub fn is_valid(&self) -> bool {
+        let (decks, slots) = (self.decks as usize, self.slots as usize);
+
+        self.draw > 0
+            && self.max_passes != Some(0)
+            && (1..=MAX_DECKS).contains(&decks)
+            && (1..=MAX_SLOTS).contains(&slots)
+            && matches!(self.suits, 1 | 2 | 4)
+            && self.cells as usize <= MAX_CELLS
+            && self.n_dealt() <= 52 * decks
+    }
+
+    // Number of cards dealt to the slots, the rest go to the stock
+    pub fn n_dealt(&self) -> usize {
+        let slots = self.slots as usize;
+
+        match self.game {
+            Game::Klondike | Game::Pyramid => slots * (slots + 1) / 2,
+            // Five rows, then one more card for the first four slots
+            Game::Spider => 5 * slots + 4,
+            Game::FreeCell => 52 * self.decks as usize,
+            Game::FortyThieves => 4 * slots,
+        }
+    }
+
+    // Number of copies of a card in the game
+    pub fn copies(&self, card: Card) -> u8 {
+        if card.suit() < self.suits {
+            self.decks * 4 / self.suits
+        } else {
+            0
+        }
+    }
+}
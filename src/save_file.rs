@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+use solitare::SolitareState;
+
+use crate::history::History;
+
+// Everything needed to resume a game, including the moves that can be
+// undone and redone along the current line of the history
+#[derive(Serialize, Deserialize)]
+pub struct SaveFile {
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub vegas: Option<i32>, // Vegas balance from earlier deals
+    #[serde(default)]
+    pub seconds: u64, // Time played
+    initial: SolitareState,
+    moves: Vec<SavedMove>,
+    pos: usize, // Number of moves applied
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedMove {
+    notation: String,
+    state: SolitareState,
+}
+
+impl SaveFile {
+    pub fn new(
+        seed: Option<u64>,
+        vegas: Option<i32>,
+        seconds: u64,
+        history: &History,
+    ) -> Self {
+        Self {
+            seed,
+            vegas,
+            seconds,
+            initial: history.initial(),
+            moves: (0..history.len())
+                .map(|i| SavedMove {
+                    notation: history.notation(i).to_owned(),
+                    state: history.state(i + 1),
+                })
+                .collect(),
+            pos: history.pos(),
+        }
+    }
+
+    pub fn history(self) -> History {
+        let mut history = History::new(self.initial);
+
+        for SavedMove { notation, state } in self.moves {
+            history.push(notation, state);
+        }
+
+        history.jump(self.pos);
+
+        history
+    }
+}
@@ -0,0 +1,46 @@
+// Standard scoring, as in the classic Windows versions
+
+use crate::moves::Move;
+
+pub const WASTE_TO_SLOT: i32 = 5;
+pub const TO_FOUNDATION: i32 = 10;
+pub const FLIP: i32 = 5; // Turning over a hidden slot card
+pub const FOUNDATION_TO_SLOT: i32 = -15;
+// Turning the waste back over. Drawing three at a time is penalized less,
+// as more passes are needed to see every card.
+pub const RECYCLE_DRAW_ONE: i32 = -100;
+pub const RECYCLE_DRAW_THREE: i32 = -20;
+// Points lost for every ten seconds of play
+pub const TIME_PENALTY: i32 = 2;
+
+// Spider starts with some points, loses one for every move and gains more
+// for every complete run. Time is not counted.
+pub const SPIDER_START: u16 = 500;
+pub const SPIDER_MOVE: i32 = -1;
+pub const SPIDER_RUN: i32 = 100;
+
+// Pyramid gains points for every card removed. Time is not counted.
+pub const PYRAMID_CARD: i32 = 5;
+
+// Points for a Klondike move, not counting the card it may turn over
+pub fn move_points(m: Move, draw: u8, recycles: bool) -> i32 {
+    match m {
+        Move::Draw if recycles && draw == 1 => RECYCLE_DRAW_ONE,
+        Move::Draw if recycles => RECYCLE_DRAW_THREE,
+        Move::Draw | Move::SlotToSlot(..) => 0,
+        Move::WasteToSlot(_) => WASTE_TO_SLOT,
+        Move::WasteToFoundation
+        | Move::SlotToFoundation(_)
+        | Move::CellToFoundation(_) => TO_FOUNDATION,
+        Move::FoundationToSlot(..) => FOUNDATION_TO_SLOT,
+        Move::SlotToCell(..) | Move::CellToSlot(..) | Move::Remove(..) => 0,
+    }
+}
+
+// Score after subtracting the penalty for the time played. The score never
+// goes below zero.
+pub fn with_time_penalty(score: u16, seconds: u64) -> u16 {
+    let penalty = TIME_PENALTY as u64 * (seconds / 10);
+
+    (score as u64).saturating_sub(penalty) as u16
+}
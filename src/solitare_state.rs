@@ -1,277 +1,1501 @@
-use std::{env, fmt::Display};
+use std::collections::HashSet;
 
-use crossterm::style::Stylize;
-use once_cell::sync::Lazy;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 
-static TWICE_WIDTH: Lazy<bool> = Lazy::new(|| {
-    env::args().any(|x| matches!(x.as_str(), "-tw" | "--twice-width"))
-});
+use crate::{
+    card::Card,
+    moves::{IllegalMove, Move},
+    rules::{Game, Rules, Scoring},
+    scoring,
+};
 
-pub struct Card(pub u8);
+// Largest layout there is room for. The number of decks and slots in play
+// is given by the rules.
+pub const MAX_DECKS: usize = 2;
+pub const MAX_SLOTS: usize = 10;
+pub const MAX_FOUNDATIONS: usize = 4 * MAX_DECKS;
+pub const MAX_CELLS: usize = 4;
+// In Spider, rows dealt on top of a slot can be built on again, so a slot
+// has no smaller bound than the number of cards
+pub const MAX_HEIGHT: usize = 52 * MAX_DECKS;
+// Cards left for the stock after dealing the slots
+pub const MAX_DECK_SIZE: usize = 52 * MAX_DECKS;
 
-impl Card {
-    fn from_index(i: usize) -> Self {
-        let rank = (i % 13 + 1) as u8;
-        let suit = (i / 13) as u8;
+mod encoding;
+#[cfg(feature = "serde")]
+mod save;
+mod zobrist;
 
-        Self::from_suit_rank(suit, rank)
+// Unused slot entries are kept at zero, so equal boards compare equal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "save::StateRepr", try_from = "save::StateRepr")
+)]
+pub struct SolitareState {
+    // Waste bottom first, then stock in drawing order
+    deck: [u8; MAX_DECK_SIZE],
+    deck_len: u8,
+    waste_len: u8, // Number of cards at the start of deck in the waste
+    passes: u8,    // Passes started so far
+    score: u16,    // Standard score, not counting time penalties
+    rules: Rules,
+    // Number of "solved" cards on each foundation. Foundation i holds the
+    // suit i % 4, so with two decks there are two of each.
+    targets: [u8; MAX_FOUNDATIONS],
+    slots: [[u8; MAX_HEIGHT]; MAX_SLOTS], // Working slots
+    slots_lens: [u8; MAX_SLOTS],
+    slots_hidden: [u8; MAX_SLOTS],
+    cells: [u8; MAX_CELLS], // Free cells, 0 when empty
+    // Zobrist keys of the cards in each slot and in the stock and waste,
+    // kept up to date by the moves
+    slots_keys: [u64; MAX_SLOTS],
+    deck_key: u64,
+}
+
+// A place on the board a card can be moved from or to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Location {
+    Target(u8),   // Foundation by index
+    Waste,        // Top card of the waste
+    Slot(u8, u8), // Column and row
+    Cell(u8),     // Free cell
+}
+
+pub fn shuffle(data: &mut [u8], rng: &mut impl Rng) {
+    for i in 0..data.len() {
+        // Sampling u32 rather than usize gives the same deals on all
+        // platforms
+        let j = rng.random_range(i as u32..data.len() as u32) as usize;
+
+        data.swap(i, j);
     }
+}
+
+// Deck of the deal numbered by a seed, shuffled by ChaCha20 so every
+// platform and version deals the same cards
+pub fn shuffled_deck(seed: u64, rules: Rules) -> Vec<u8> {
+    shuffled_deck_from(&mut ChaCha20Rng::seed_from_u64(seed), rules)
+}
 
-    fn from_suit_rank(suit: u8, rank: u8) -> Self {
-        assert!(suit < 4 && rank <= 13);
+// Decks are added one after the other before shuffling, so a single deck
+// gives the same deals as it always has. With fewer suits, the suits in
+// play are repeated in place of the others.
+pub fn shuffled_deck_from(rng: &mut impl Rng, rules: Rules) -> Vec<u8> {
+    let mut deck: Vec<u8> = (0..52 * rules.decks as usize)
+        .map(|i| {
+            let suit = (i / 13) as u8 % rules.suits;
+            Card::from_suit_rank(suit, (i % 13) as u8 + 1).0
+        })
+        .collect();
 
-        Self((suit << 4) | rank)
+    shuffle(&mut deck, rng);
+
+    deck
+}
+
+// Largest deal number of the Microsoft FreeCell shuffle
+pub const MAX_MS_DEAL: u32 = 0x7fff_ffff;
+
+// Deck of FreeCell deal `number` as numbered by Microsoft FreeCell and
+// FreeCell Pro, from their linear congruential generator, in the order it
+// is dealt. Published lists of which deals can be won use these numbers.
+pub fn ms_deck(number: u32) -> Vec<u8> {
+    assert!((1..=MAX_MS_DEAL).contains(&number));
+
+    // Cards are numbered with the rank first, then the suit in the order
+    // ♣ ♦ ♥ ♠
+    const SUITS: [u8; 4] = [2, 3, 1, 0];
+    let card = |i: u32| {
+        Card::from_suit_rank(SUITS[i as usize % 4], (i / 4) as u8 + 1).0
+    };
+
+    let mut state = number;
+    let mut next = || {
+        state = state.wrapping_mul(214013).wrapping_add(2531011) & 0x7fff_ffff;
+        state >> 16
+    };
+
+    let mut cards: Vec<u32> = (0..52).rev().collect();
+    for i in 0..52 {
+        let j = 51 - next() as usize % (52 - i);
+        cards.swap(i, j);
     }
 
-    pub fn to_ind(&self) -> usize {
-        (self.suit() * 13 + self.rank() - 1) as usize
+    cards.into_iter().map(card).collect()
+}
+
+impl SolitareState {
+    // The same seed and rules always give the same deal
+    pub fn new(seed: u64, rules: Rules) -> Self {
+        Self::new_from(&mut ChaCha20Rng::seed_from_u64(seed), rules)
     }
 
-    fn rank(&self) -> u8 {
-        self.0 & 0b0000_1111
+    // Deal shuffled by a generator, the same one again for the same
+    // generator in the same state
+    pub fn new_from(rng: &mut impl Rng, rules: Rules) -> Self {
+        Self::deal(&shuffled_deck_from(rng, rules), rules)
     }
 
-    fn suit(&self) -> u8 {
-        self.0 >> 4
+    // Column and row of every card dealt to the slots, in dealing order
+    fn deal_order(rules: Rules) -> Vec<(usize, usize)> {
+        let n_slots = rules.slots as usize;
+
+        match rules.game {
+            // Each row starts one slot further right, so slot i gets i + 1
+            Game::Klondike => (0..n_slots)
+                .flat_map(|i| (i..n_slots).map(move |j| (j, i)))
+                .collect(),
+            // Pyramid rows from the top, each one card wider
+            Game::Pyramid => (0..n_slots)
+                .flat_map(|row| (0..=row).map(move |i| (row, i)))
+                .collect(),
+            // Whole rows, left to right
+            Game::Spider | Game::FreeCell | Game::FortyThieves => (0..rules
+                .n_dealt())
+                .map(|k| (k % n_slots, k / n_slots))
+                .collect(),
+        }
     }
 
-    fn is_red(&self) -> bool {
-        (self.0 >> 4) & 1 == 1
+    // Deals the first cards to the slots and the rest to the stock, so the
+    // last card is drawn last
+    pub fn deal(deck: &[u8], rules: Rules) -> Self {
+        assert!(rules.is_valid() && deck.len() == 52 * rules.decks as usize);
+
+        let mut state = Self {
+            deck: [0; MAX_DECK_SIZE],
+            deck_len: 0,
+            waste_len: 0,
+            passes: 1,
+            score: 0,
+            rules,
+            targets: [0; MAX_FOUNDATIONS],
+            slots: [[0; MAX_HEIGHT]; MAX_SLOTS],
+            slots_lens: [0; MAX_SLOTS],
+            slots_hidden: [0; MAX_SLOTS],
+            cells: [0; MAX_CELLS],
+            slots_keys: [0; MAX_SLOTS],
+            deck_key: 0,
+        };
+        let face_up = matches!(
+            rules.game,
+            Game::FreeCell | Game::Pyramid | Game::FortyThieves
+        );
+
+        // Dealing to slots, only the top card of each is face up, unless
+        // they all are:
+        for ((col, row), &card) in Self::deal_order(rules).into_iter().zip(deck)
+        {
+            state.slots[col][row] = card;
+            state.slots_lens[col] = row as u8 + 1;
+            state.slots_hidden[col] = if face_up { 0 } else { row as u8 };
+        }
+
+        let rest = &deck[rules.n_dealt()..];
+        state.deck[..rest.len()].copy_from_slice(rest);
+        state.deck_len = rest.len() as u8;
+
+        if rules.game == Game::Spider && rules.scoring == Scoring::Standard {
+            state.score = scoring::SPIDER_START;
+        }
+
+        state.rehash();
+
+        state
     }
 
-    fn render(
-        &self,
-        f: &mut std::fmt::Formatter<'_>,
-        highlight: bool,
-    ) -> std::fmt::Result {
-        let rank = self.rank();
-        let rank_offset = if let 1..=11 = rank { rank } else { rank + 1 };
+    // The cards the player can not see, face down in the slots and in the
+    // stock, dealt again at random among the places they take up. Gives
+    // one of the positions the player might be in.
+    pub fn shuffle_hidden(&mut self, rng: &mut impl Rng) {
+        let mut hidden: Vec<u8> = self.hidden_cards().map(|c| c.0).collect();
+        shuffle(&mut hidden, rng);
 
-        let suit = self.suit();
-        let suit_offset = [0, 1, 3, 2][suit as usize] << 4;
+        let mut cards = hidden.into_iter();
+        for col in 0..self.n_slots() {
+            for card in &mut self.slots[col][..self.slots_hidden[col] as usize]
+            {
+                *card = cards.next().unwrap();
+            }
+        }
+        for card in
+            &mut self.deck[self.waste_len as usize..self.deck_len as usize]
+        {
+            *card = cards.next().unwrap();
+        }
 
-        let card_char =
-            char::from_u32('🂠' as u32 + suit_offset + rank_offset as u32)
-                .unwrap();
+        self.rehash();
+    }
 
-        let colored_card = if self.is_red() {
-            card_char.red()
-        } else {
-            card_char.black()
-        };
+    // Letter for each card in dealing order, a-z for ♠ and ♥, A-Z for ♣ and
+    // ♦. First the slot cards, then the stock. Only meaningful for a
+    // position straight after dealing.
+    pub fn share_code(&self) -> String {
+        let mut code = String::new();
 
-        let (highlighted_card, pad) = if highlight {
-            (colored_card.on_dark_green(), " ".on_dark_green())
-        } else {
-            (colored_card.on_white(), " ".on_white())
-        };
+        let cards = Self::deal_order(self.rules)
+            .into_iter()
+            .map(|(col, row)| self.slots[col][row])
+            .chain(self.deck[..self.deck_len()].iter().copied());
 
-        if *TWICE_WIDTH {
-            write!(f, "{}{}", highlighted_card, pad)?;
-        } else {
-            write!(f, "{}", highlighted_card)?;
+        for card in cards {
+            let ind = Card(card).to_ind() as u8;
+            code.push(match ind {
+                0..26 => (b'a' + ind) as char,
+                _ => (b'A' + ind - 26) as char,
+            });
         }
 
-        Ok(())
+        code
     }
 
-    fn highlight(self, highlight: bool) -> HighlightedCard {
-        HighlightedCard(self, highlight)
+    // Share code of a position straight after dealing, None for positions
+    // the code would not give back, like boards read from a file
+    pub fn fresh_share_code(&self) -> Option<String> {
+        let dealt = Self::deal_order(self.rules)
+            .into_iter()
+            .all(|(col, row)| self.slots[col][row] != 0)
+            && !self.deck[..self.deck_len()].contains(&0);
+        if !dealt {
+            return None;
+        }
+
+        let code = self.share_code();
+        (Self::from_share_code(&code, self.rules) == Some(*self))
+            .then_some(code)
     }
-}
 
-impl Display for Card {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.render(f, false)
+    // Deal for a share code, laid out by the given rules. Every card must
+    // be in the code once for each copy of it in the game.
+    pub fn from_share_code(code: &str, rules: Rules) -> Option<Self> {
+        let mut deck = Vec::new();
+        let mut used = [0; 52];
+
+        if !rules.is_valid() {
+            return None;
+        }
+
+        for c in code.chars() {
+            let ind = match c {
+                'a'..='z' => c as u8 - b'a',
+                'A'..='Z' => c as u8 - b'A' + 26,
+                _ => return None,
+            } as usize;
+
+            let card = Card::from_index(ind);
+
+            if used[ind] == rules.copies(card) {
+                return None;
+            }
+
+            used[ind] += 1;
+            deck.push(card.0);
+        }
+
+        if deck.len() != 52 * rules.decks as usize {
+            return None;
+        }
+
+        Some(Self::deal(&deck, rules))
     }
-}
 
-pub struct HighlightedCard(Card, bool);
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
 
-impl Display for HighlightedCard {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.0.render(f, self.1)
+    // Number of the current pass through the stock, starting at 1
+    pub fn pass(&self) -> u8 {
+        self.passes
     }
-}
 
-const N: usize = 7;
-const MAX_HEIGHT: usize = N - 1 + 13;
+    // Whether the stock is empty and can not be turned over again
+    pub fn is_stock_exhausted(&self) -> bool {
+        self.stock_len() == 0
+            && self.rules.max_passes.is_some_and(|max| self.passes >= max)
+    }
 
-#[derive(Debug, Clone, Copy)]
-pub struct SolitareState {
-    deck: u64,        // 1 bit per card, suits ordered: ♠, ♥, ♣, ♦
-    targets: [u8; 4], // Number of "solved" cards for each suit
-    slots: [[u8; MAX_HEIGHT]; N], // Working slots
-    slots_lens: [u8; N], // Combo: 4 low bits: len, 4 high bits: n hidden
-}
+    // Standard score for the moves made so far
+    pub fn score(&self) -> u16 {
+        self.score
+    }
 
-pub fn shuffle(data: &mut [u8]) {
-    for i in 0..data.len() {
-        let j = rand::random_range(i..data.len());
+    pub fn n_slots(&self) -> usize {
+        self.rules.slots as usize
+    }
 
-        data.swap(i, j);
+    pub fn n_foundations(&self) -> usize {
+        4 * self.rules.decks as usize
     }
-}
 
-pub fn shuffled_deck() -> [u8; 52] {
-    let mut deck = [0; 52];
+    pub fn n_cells(&self) -> usize {
+        self.rules.cells as usize
+    }
 
-    for (i, x) in deck.iter_mut().enumerate() {
-        *x = Card::from_index(i).0;
+    // Card in a free cell, if any
+    pub fn cell(&self, i: usize) -> Option<Card> {
+        self.cells.get(i).filter(|&&c| c != 0).map(|&c| Card(c))
     }
 
-    shuffle(&mut deck);
+    // Top card of a foundation, if any
+    pub fn target(&self, foundation: u8) -> Option<Card> {
+        match self.targets.get(foundation as usize) {
+            None | Some(0) => None,
+            Some(&rank) => {
+                Some(Card::from_suit_rank(foundation % self.rules.suits, rank))
+            }
+        }
+    }
 
-    deck
-}
+    // Number of cards in the stock and waste together
+    pub fn deck_len(&self) -> usize {
+        self.deck_len as usize
+    }
 
-#[derive(Debug, Clone, Copy)]
-pub enum Highlight {
-    Target(u8),
-    Deck(u8),
-    Slot(u8, u8),
-}
+    pub fn stock_len(&self) -> usize {
+        (self.deck_len - self.waste_len) as usize
+    }
 
-impl SolitareState {
-    pub fn new() -> Self {
-        let mut state = Self {
-            deck: 0,
-            targets: [0; 4],
-            slots: [[0; MAX_HEIGHT]; N],
-            slots_lens: [0; N],
+    pub fn waste_len(&self) -> usize {
+        self.waste_len as usize
+    }
+
+    // Stock cards, the next one to be drawn first
+    pub fn stock_cards(&self) -> impl Iterator<Item = Card> {
+        self.deck[self.waste_len()..self.deck_len()]
+            .iter()
+            .map(|&c| Card(c))
+    }
+
+    // Waste cards, the top one last
+    pub fn waste_cards(&self) -> impl Iterator<Item = Card> {
+        self.deck[..self.waste_len()].iter().map(|&c| Card(c))
+    }
+
+    pub fn waste_top(&self) -> Option<Card> {
+        self.waste_cards().last()
+    }
+
+    // Cards the player can not see: face down in the slots, then the stock
+    pub fn hidden_cards(&self) -> impl Iterator<Item = Card> {
+        (0..self.n_slots())
+            .flat_map(move |col| {
+                (0..self.slot_hidden(col) as usize)
+                    .map(move |row| self.slot_card(col, row))
+            })
+            .chain(self.stock_cards())
+    }
+
+    pub fn slot_len(&self, col: usize) -> u8 {
+        self.slots_lens[col]
+    }
+
+    pub fn slot_hidden(&self, col: usize) -> u8 {
+        self.slots_hidden[col]
+    }
+
+    pub fn slot_card(&self, col: usize, row: usize) -> Card {
+        Card(self.slots[col][row])
+    }
+
+    pub fn max_height(&self) -> u8 {
+        (0..self.n_slots())
+            .map(|col| self.slot_len(col))
+            .max()
+            .unwrap()
+    }
+
+    // Card of a pyramid row, unless it has been removed
+    pub fn pyramid_card(&self, row: usize, i: usize) -> Option<Card> {
+        let card = *self.slots.get(row)?.get(i)?;
+        (i <= row && card != 0).then_some(Card(card))
+    }
+
+    // Whether neither of the two cards below a pyramid card is left
+    pub fn is_uncovered(&self, row: usize, i: usize) -> bool {
+        row + 1 >= self.n_slots()
+            || self.slots[row + 1][i] == 0 && self.slots[row + 1][i + 1] == 0
+    }
+
+    pub fn is_won(&self) -> bool {
+        if self.rules.game == Game::Pyramid {
+            return self.slots.iter().flatten().all(|&c| c == 0);
+        }
+
+        self.targets[..self.n_foundations()]
+            .iter()
+            .all(|&t| t == 13)
+    }
+
+    // Checks that the board is one the rules can lead to: every card of the
+    // game is on it exactly once, unused entries are zero, hidden cards are
+    // covered and, in Klondike, the face up cards of a slot are built on
+    // each other. Tells what is wrong otherwise.
+    pub fn check_invariants(&self) -> Result<(), String> {
+        let rules = &self.rules;
+        let (deck_len, waste_len) = (self.deck_len(), self.waste_len());
+        let mut seen = [0u8; 52];
+        let mut count = |card: u8, place: &str| {
+            if card == 0 || Card(card).rank() > 13 || Card(card).suit() >= 4 {
+                return Err(format!("invalid card {card} in {place}"));
+            }
+            seen[Card(card).to_ind()] += 1;
+            Ok(())
         };
 
-        let deck = shuffled_deck();
-        let mut cur_card = 0;
+        if waste_len > deck_len {
+            return Err(format!("{waste_len} cards in a waste of {deck_len}"));
+        }
+        for &card in &self.deck[..deck_len] {
+            count(card, "the stock")?;
+        }
+        if self.deck[deck_len..].iter().any(|&c| c != 0) {
+            return Err(String::from("cards after the end of the stock"));
+        }
 
-        // Dealing to slots:
-        for i in 0..N {
-            for j in i..N {
-                state.slots[j][i] = deck[cur_card];
-                cur_card += 1;
+        for (i, &n) in self.targets.iter().enumerate() {
+            if i >= self.n_foundations() && n != 0 {
+                return Err(format!("cards on unused foundation {}", i + 1));
+            }
+            if n > 13 {
+                return Err(format!("{n} cards on foundation {}", i + 1));
+            }
+            if rules.game == Game::Pyramid {
+                continue;
+            }
+            for rank in 1..=n {
+                let card = Card::from_suit_rank(i as u8 % rules.suits, rank);
+                count(card.0, "the foundations")?;
             }
+        }
 
-            state.slots_lens[i] = ((i << 4) as u8) | ((i + 1) as u8);
+        for (i, &card) in self.cells.iter().enumerate() {
+            if i >= self.n_cells() && card != 0 {
+                return Err(format!("card in unused free cell {}", i + 1));
+            }
+            if card != 0 {
+                count(card, "the free cells")?;
+            }
         }
 
-        // Counting which are left for remaining deck
-        for &card in deck.iter().skip(cur_card) {
-            state.deck |= 1 << Card(card).to_ind();
+        for col in 0..MAX_SLOTS {
+            let (len, hidden) = (self.slots_lens[col], self.slots_hidden[col]);
+            let slot = &self.slots[col];
+            let name = format!("slot {}", col + 1);
+
+            if col >= self.n_slots() && len != 0 {
+                return Err(format!("cards in unused {name}"));
+            }
+            if hidden > 0 && hidden >= len {
+                return Err(format!(
+                    "{hidden} hidden cards in {name} of {len}"
+                ));
+            }
+            if slot[len as usize..].iter().any(|&c| c != 0) {
+                return Err(format!("cards after the end of {name}"));
+            }
+
+            for &card in &slot[..len as usize] {
+                // Cards removed from a pyramid leave a hole
+                if rules.game == Game::Pyramid && card == 0 {
+                    continue;
+                }
+                count(card, &name)?;
+            }
+
+            // Only the top card is turned up, so the cards on it were all
+            // built on each other
+            if rules.game == Game::Klondike && !rules.thoughtful {
+                let built = slot[hidden as usize..len as usize].windows(2).all(
+                    |pair| {
+                        let (lower, upper) = (Card(pair[0]), Card(pair[1]));
+                        upper.rank() + 1 == lower.rank()
+                            && upper.is_red() != lower.is_red()
+                    },
+                );
+                if !built {
+                    return Err(format!("face up cards of {name} not built"));
+                }
+            }
         }
 
-        state
+        let complete = if rules.game == Game::Pyramid {
+            (0..4).all(|suit| {
+                let left: u8 = seen[suit * 13..(suit + 1) * 13].iter().sum();
+                left + self.targets[suit] == 13
+            })
+        } else {
+            seen.iter()
+                .enumerate()
+                .all(|(i, &n)| n == rules.copies(Card::from_index(i)))
+        };
+        if !complete {
+            return Err(String::from("cards missing or there twice"));
+        }
+
+        self.check_keys()
     }
 
-    fn render(
-        &self,
-        f: &mut std::fmt::Formatter<'_>,
-        highlight: Option<Highlight>,
-    ) -> std::fmt::Result {
-        let hl_ind = if let Some(Highlight::Target(i)) = highlight {
-            i as usize
+    // Plain text version of the board, with hidden cards as `##`
+    pub fn plain_text(&self) -> String {
+        let mut text = String::new();
+
+        for foundation in 0..self.n_foundations() as u8 {
+            match self.target(foundation) {
+                Some(card) => text += &format!("{:>3}", card.name()),
+                None => text += " --",
+            }
+        }
+
+        text += " |";
+
+        if self.rules.game == Game::FreeCell {
+            for i in 0..self.n_cells() {
+                match self.cell(i) {
+                    Some(card) => text += &format!("{:>3}", card.name()),
+                    None => text += " --",
+                }
+            }
         } else {
-            4 // Out of bounds, will never hit
-        };
+            text += if self.stock_len() > 0 { " ##" } else { " --" };
+        }
+
+        if let Some(card) = self.waste_top() {
+            text += &format!("{:>4}", card.name());
+        }
+
+        text += "\n\n";
 
-        for suit in 0..4 {
-            if self.targets[suit] == 0 {
-                write!(f, "{}", "🂠".dark_grey())?;
-                if *TWICE_WIDTH {
-                    write!(f, " ")?;
+        if self.rules.game == Game::Pyramid {
+            // Each row is shifted by half a card, removed cards are blank
+            for row in 0..self.n_slots() {
+                let mut line = "  ".repeat(self.n_slots() - 1 - row);
+
+                for i in 0..=row {
+                    match self.pyramid_card(row, i) {
+                        Some(card) => line += &format!("{:>4}", card.name()),
+                        None => line += "    ",
+                    }
                 }
-            } else {
-                write!(
-                    f,
-                    "{}",
-                    Card::from_suit_rank(suit as u8, self.targets[suit])
-                        .highlight(suit == hl_ind),
-                )?;
+
+                text += line.trim_end();
+                text += "\n";
             }
+
+            return text;
         }
 
-        write!(f, " ┃ ")?;
+        for row_ind in 0..self.max_height() {
+            let mut line = String::new();
 
-        let mut remaining_deck = self.deck;
-        let mut i: usize = 0;
+            for col_ind in 0..self.n_slots() {
+                if row_ind >= self.slot_len(col_ind) {
+                    line += "   ";
+                } else if row_ind < self.slot_hidden(col_ind) {
+                    line += " ##";
+                } else {
+                    let card = self.slot_card(col_ind, row_ind as usize);
+                    line += &format!("{:>3}", card.name());
+                }
+            }
 
-        let hl_ind = if let Some(Highlight::Deck(i)) = highlight {
-            i as u32
-        } else {
-            52 // Will never hit
+            text += line.trim_end();
+            text += "\n";
+        }
+
+        text
+    }
+
+    // [src, dst]
+    pub fn is_location_valid(&self, location: Location) -> [bool; 2] {
+        if self.rules.game == Game::Pyramid {
+            // Any uncovered card can be picked, and paired with another
+            let valid = match location {
+                Location::Waste => self.waste_len > 0,
+                Location::Slot(row, i) => {
+                    let (row, i) = (row as usize, i as usize);
+                    self.pyramid_card(row, i).is_some()
+                        && self.is_uncovered(row, i)
+                }
+                _ => false,
+            };
+
+            return [valid; 2];
+        }
+
+        match location {
+            Location::Target(i) => {
+                if (i as usize) < self.n_foundations() {
+                    [self.targets[i as usize] > 0, true]
+                } else {
+                    [false; 2]
+                }
+            }
+            Location::Waste => [self.waste_len > 0, false],
+            Location::Cell(i) => [
+                self.cell(i as usize).is_some(),
+                (i as usize) < self.n_cells(),
+            ],
+            Location::Slot(col, row) => {
+                if (col as usize) < self.n_slots() {
+                    let n_cards = self.slot_len(col as usize);
+                    let n_hidden = self.slot_hidden(col as usize);
+
+                    [(n_hidden..n_cards).contains(&row), true]
+                } else {
+                    [false; 2]
+                }
+            }
+        }
+    }
+
+    // Card at a location that is valid as a source
+    pub fn card_at(&self, location: Location) -> Card {
+        match location {
+            Location::Target(i) => self.target(i).unwrap(),
+            Location::Waste => self.waste_top().unwrap(),
+            Location::Cell(i) => self.cell(i as usize).unwrap(),
+            Location::Slot(col, row) => {
+                self.slot_card(col as usize, row as usize)
+            }
+        }
+    }
+
+    // Removes the top n cards of a slot, flipping the next one if needed
+    fn pop_slot(&mut self, col: usize, n: u8) {
+        let n_cards = self.slots_lens[col] - n;
+        let n_hidden = &mut self.slots_hidden[col];
+        let popped =
+            &mut self.slots[col][n_cards as usize..(n_cards + n) as usize];
+
+        for (i, card) in popped.iter().enumerate() {
+            self.slots_keys[col] ^=
+                zobrist::slot_key(n_cards as usize + i, *card);
+        }
+        popped.fill(0);
+
+        if *n_hidden > 0 && *n_hidden == n_cards {
+            *n_hidden -= 1;
+        }
+
+        self.slots_lens[col] = n_cards;
+    }
+
+    // Removes the top card of the waste
+    fn pop_waste(&mut self) {
+        let (waste_len, deck_len) = (self.waste_len(), self.deck_len());
+
+        self.deck_key ^= self.deck_keys(waste_len - 1..waste_len);
+        self.deck.copy_within(waste_len..deck_len, waste_len - 1);
+        self.deck[deck_len - 1] = 0;
+
+        self.waste_len -= 1;
+        self.deck_len -= 1;
+    }
+
+    // The move that picks up the card(s) at `from` and drops them on `to`
+    pub fn move_between(
+        &self,
+        from: Location,
+        to: Location,
+    ) -> Result<Move, IllegalMove> {
+        if !self.is_location_valid(from)[0] {
+            return Err(IllegalMove::NoCard);
+        }
+
+        // Picking the same card twice removes it on its own
+        if self.rules.game == Game::Pyramid {
+            let other = (to != from).then_some(to);
+            return Ok(Move::Remove(from, other));
+        }
+
+        match (from, to) {
+            (Location::Waste, Location::Slot(col, _)) => {
+                Ok(Move::WasteToSlot(col))
+            }
+            (Location::Waste, Location::Target(_)) => {
+                Ok(Move::WasteToFoundation)
+            }
+            (Location::Slot(from_col, row), Location::Slot(col, _)) => {
+                Ok(Move::SlotToSlot(from_col, row, col))
+            }
+            (Location::Slot(col, row), Location::Target(_)) => {
+                if row + 1 == self.slot_len(col as usize) {
+                    Ok(Move::SlotToFoundation(col))
+                } else {
+                    Err(IllegalMove::NotTopCard)
+                }
+            }
+            (Location::Target(i), Location::Slot(col, _)) => {
+                Ok(Move::FoundationToSlot(i, col))
+            }
+            (Location::Slot(col, row), Location::Cell(cell)) => {
+                if row + 1 == self.slot_len(col as usize) {
+                    Ok(Move::SlotToCell(col, cell))
+                } else {
+                    Err(IllegalMove::NotTopCard)
+                }
+            }
+            (Location::Cell(cell), Location::Slot(col, _)) => {
+                Ok(Move::CellToSlot(cell, col))
+            }
+            (Location::Cell(cell), Location::Target(_)) => {
+                Ok(Move::CellToFoundation(cell))
+            }
+            _ => Err(IllegalMove::NotAMove),
+        }
+    }
+
+    // Where a move picks up its cards and where it drops them, the other
+    // way around from `move_between`. Drawing has neither, and a lone
+    // Pyramid king has nowhere to go.
+    pub fn move_locations(&self, m: Move) -> [Option<Location>; 2] {
+        let top = |col: u8| {
+            let len = self.slot_len(col as usize);
+            Location::Slot(col, len.saturating_sub(1))
+        };
+        let foundation = |card: Option<Card>| {
+            let i = self.foundation_for(card?).ok()?;
+            Some(Location::Target(i as u8))
+        };
+        let top_card = |col: u8| {
+            let len = self.slot_len(col as usize) as usize;
+            Some(self.slot_card(col as usize, len.checked_sub(1)?))
         };
 
-        for j in 0..self.deck.count_ones() {
-            let skip = remaining_deck.trailing_zeros() + 1;
+        match m {
+            Move::Draw => [None, None],
+            Move::WasteToSlot(col) => [Some(Location::Waste), Some(top(col))],
+            Move::WasteToFoundation => {
+                [Some(Location::Waste), foundation(self.waste_top())]
+            }
+            Move::SlotToSlot(from_col, row, col) => {
+                [Some(Location::Slot(from_col, row)), Some(top(col))]
+            }
+            Move::SlotToFoundation(col) => {
+                [Some(top(col)), foundation(top_card(col))]
+            }
+            Move::FoundationToSlot(i, col) => {
+                [Some(Location::Target(i)), Some(top(col))]
+            }
+            Move::SlotToCell(col, cell) => {
+                [Some(top(col)), Some(Location::Cell(cell))]
+            }
+            Move::CellToSlot(cell, col) => {
+                [Some(Location::Cell(cell)), Some(top(col))]
+            }
+            Move::CellToFoundation(cell) => [
+                Some(Location::Cell(cell)),
+                foundation(self.cell(cell as usize)),
+            ],
+            Move::Remove(a, b) => [Some(a), b],
+        }
+    }
 
-            i += skip as usize;
-            remaining_deck >>= skip;
+    // Checks that the cards from a row down to the top of a slot form a
+    // descending run, so they can be moved together. The colors alternate
+    // in Klondike, in Spider and Forty Thieves the run must be all one suit.
+    fn check_run(&self, col: usize, row: usize) -> Result<(), IllegalMove> {
+        let slot_len = self.slot_len(col) as usize;
 
-            write!(f, "{}", Card::from_index(i - 1).highlight(j == hl_ind))?;
+        let is_run = (row + 1..slot_len).all(|i| {
+            let (upper, lower) =
+                (self.slot_card(col, i - 1), self.slot_card(col, i));
+
+            lower.rank() + 1 == upper.rank()
+                && if self.rules.game.builds_in_suit() {
+                    lower.suit() == upper.suit()
+                } else {
+                    lower.is_red() != upper.is_red()
+                }
+        });
+
+        if is_run {
+            Ok(())
+        } else {
+            Err(IllegalMove::NotARun)
         }
+    }
 
-        writeln!(f, "\n\r")?;
+    // Checks that a card can be put on top of a slot
+    fn check_slot_fits(
+        &self,
+        card: Card,
+        col: usize,
+    ) -> Result<(), IllegalMove> {
+        let slot_len = self.slot_len(col);
+        let spider = self.rules.game == Game::Spider;
 
-        let max_height =
-            self.slots_lens.iter().map(|l| l & 0x0f).max().unwrap();
+        if slot_len == 0 {
+            return if card.rank() == 13
+                || self.rules.any_card_on_empty_slot
+                || spider
+            {
+                Ok(())
+            } else {
+                Err(IllegalMove::NotKing)
+            };
+        }
 
-        let (hl_col, hl_row) = if let Some(Highlight::Slot(i, j)) = highlight {
-            (i as usize, j)
+        let target_card = self.slot_card(col, slot_len as usize - 1);
+
+        if card.rank() + 1 != target_card.rank() {
+            Err(IllegalMove::NotOneLower)
+        } else if self.rules.game == Game::FortyThieves
+            && card.suit() != target_card.suit()
+        {
+            Err(IllegalMove::OtherSuit)
+        } else if card.is_red() == target_card.is_red()
+            && !self.rules.game.builds_in_suit()
+        {
+            Err(IllegalMove::SameColor)
         } else {
-            (N + 1, max_height + 1) // Too high, will never hit
+            Ok(())
+        }
+    }
+
+    // Most cards that can be moved to a slot at once, when runs are moved
+    // one card at a time through the free cells and empty slots as in
+    // FreeCell. Each empty slot other than the destination doubles the
+    // number. None when any run can be moved.
+    pub fn max_run(&self, col: usize) -> Option<usize> {
+        if self.rules.game != Game::FreeCell && !self.rules.run_limit {
+            return None;
+        }
+
+        let free_cells =
+            self.n_cells() - self.cells.iter().filter(|&&c| c != 0).count();
+        let empty_slots = (0..self.n_slots())
+            .filter(|&i| i != col && self.slot_len(i) == 0)
+            .count();
+
+        Some((free_cells + 1) << empty_slots)
+    }
+
+    fn check_supermove(&self, n: usize, col: usize) -> Result<(), IllegalMove> {
+        match self.max_run(col) {
+            Some(max) if n > max => Err(IllegalMove::TooManyCards),
+            _ => Ok(()),
+        }
+    }
+
+    fn cell_card(&self, cell: u8) -> Result<Card, IllegalMove> {
+        self.check_cell_index(cell)?;
+        self.cell(cell as usize).ok_or(IllegalMove::NoCard)
+    }
+
+    fn check_cell_index(&self, cell: u8) -> Result<(), IllegalMove> {
+        if (cell as usize) < self.n_cells() {
+            Ok(())
+        } else {
+            Err(IllegalMove::NotAMove)
+        }
+    }
+
+    // The first foundation of the card's suit it can be put on
+    fn foundation_for(&self, card: Card) -> Result<usize, IllegalMove> {
+        if self.rules.game == Game::Spider {
+            return Err(IllegalMove::OnlyRuns);
+        }
+
+        (card.suit() as usize..self.n_foundations())
+            .step_by(self.rules.suits as usize)
+            .find(|&i| card.rank() == self.targets[i] + 1)
+            .ok_or(IllegalMove::NotNextRank)
+    }
+
+    // Deals a card from the stock onto every slot, for Spider
+    fn deal_row(&mut self) {
+        let n = self.n_slots().min(self.stock_len());
+
+        for col in 0..n {
+            self.push_slot(col, Card(self.deck[col]));
+        }
+
+        let deck_len = self.deck_len();
+        self.deck_key ^= self.deck_keys(0..n);
+        self.deck.copy_within(n..deck_len, 0);
+        self.deck[deck_len - n..deck_len].fill(0);
+        self.deck_len -= n as u8;
+    }
+
+    // Moves a complete run from king to ace on top of a slot to the first
+    // empty foundation of its suit, for Spider. Returns whether there was
+    // one.
+    fn remove_complete_run(&mut self, col: usize) -> bool {
+        let Some(row) = (self.slot_len(col) as usize).checked_sub(13) else {
+            return false;
         };
 
-        for row_ind in 0..max_height {
-            for col_ind in 0..N {
-                let col_len = self.slots_lens[col_ind] & 0x0f;
-                let n_hidden = self.slots_lens[col_ind] >> 4;
-                if row_ind >= col_len {
-                    write!(f, " ")?;
-                    if *TWICE_WIDTH {
-                        write!(f, " ")?;
-                    }
-                } else if row_ind < n_hidden {
-                    write!(f, "{}", "🂠".blue())?;
-                    if *TWICE_WIDTH {
-                        write!(f, " ")?;
+        let king = self.slot_card(col, row);
+
+        if row < self.slot_hidden(col) as usize
+            || king.rank() != 13
+            || self.check_run(col, row).is_err()
+        {
+            return false;
+        }
+
+        let suits = self.rules.suits as usize;
+        let Some(foundation) = (king.suit() as usize..self.n_foundations())
+            .step_by(suits)
+            .find(|&i| self.targets[i] == 0)
+        else {
+            return false;
+        };
+
+        self.targets[foundation] = 13;
+        self.pop_slot(col, 13);
+
+        true
+    }
+
+    fn push_slot(&mut self, col: usize, card: Card) {
+        let slot_len = self.slot_len(col);
+
+        self.slots[col][slot_len as usize] = card.0;
+        self.slots_lens[col] = slot_len + 1;
+        self.slots_keys[col] ^= zobrist::slot_key(slot_len as usize, card.0);
+    }
+
+    // Checks whether a move is legal without performing it
+    pub fn check_move(&self, m: Move) -> Result<(), IllegalMove> {
+        // Pyramid has no moves of its own other than drawing
+        let pyramid = self.rules.game == Game::Pyramid;
+        if pyramid != matches!(m, Move::Remove(..)) && m != Move::Draw {
+            return Err(IllegalMove::NotAMove);
+        }
+
+        match m {
+            Move::Draw => {
+                let spider = self.rules.game == Game::Spider;
+
+                if self.deck_len == 0 {
+                    Err(IllegalMove::NoCard)
+                } else if spider
+                    && (0..self.n_slots()).any(|col| self.slot_len(col) == 0)
+                {
+                    Err(IllegalMove::EmptySlot)
+                } else if self.is_stock_exhausted() {
+                    Err(IllegalMove::NoPassesLeft)
+                } else {
+                    Ok(())
+                }
+            }
+            Move::WasteToSlot(col) => {
+                self.check_slot_index(col)?;
+                self.check_slot_fits(self.waste_card()?, col as usize)
+            }
+            Move::WasteToFoundation => {
+                self.foundation_for(self.waste_card()?).map(|_| ())
+            }
+            Move::SlotToSlot(from_col, row, col) => {
+                self.check_slot_index(col)?;
+
+                if !self.is_location_valid(Location::Slot(from_col, row))[0] {
+                    return Err(IllegalMove::NoCard);
+                }
+
+                if from_col == col {
+                    return Err(IllegalMove::SameSlot);
+                }
+
+                self.check_run(from_col as usize, row as usize)?;
+
+                // Forty Thieves cards are moved one at a time
+                let n_moved = self.slot_len(from_col as usize) - row;
+                if self.rules.game == Game::FortyThieves && n_moved > 1 {
+                    return Err(IllegalMove::NotTopCard);
+                }
+                self.check_supermove(n_moved as usize, col as usize)?;
+
+                let card = self.slot_card(from_col as usize, row as usize);
+
+                self.check_slot_fits(card, col as usize)
+            }
+            Move::SlotToFoundation(col) => {
+                self.check_slot_index(col)?;
+
+                let slot_len = self.slot_len(col as usize);
+
+                if slot_len == 0 {
+                    return Err(IllegalMove::NoCard);
+                }
+
+                let card = self.slot_card(col as usize, slot_len as usize - 1);
+
+                self.foundation_for(card).map(|_| ())
+            }
+            Move::FoundationToSlot(i, col) => {
+                self.check_slot_index(col)?;
+
+                let card = self.target(i).ok_or(IllegalMove::NoCard)?;
+
+                if !self.rules.foundation_to_slot {
+                    return Err(IllegalMove::OffFoundation);
+                }
+
+                self.check_slot_fits(card, col as usize)
+            }
+            Move::SlotToCell(col, cell) => {
+                self.check_slot_index(col)?;
+                self.check_cell_index(cell)?;
+
+                if self.slot_len(col as usize) == 0 {
+                    Err(IllegalMove::NoCard)
+                } else if self.cell(cell as usize).is_some() {
+                    Err(IllegalMove::CellTaken)
+                } else {
+                    Ok(())
+                }
+            }
+            Move::CellToSlot(cell, col) => {
+                self.check_slot_index(col)?;
+                self.check_slot_fits(self.cell_card(cell)?, col as usize)
+            }
+            Move::CellToFoundation(cell) => {
+                self.foundation_for(self.cell_card(cell)?).map(|_| ())
+            }
+            Move::Remove(a, b) => {
+                let rank = |location| {
+                    if self.is_location_valid(location)[0] {
+                        Ok(self.card_at(location).rank())
+                    } else {
+                        Err(IllegalMove::NoCard)
                     }
+                };
+
+                let total = match b {
+                    Some(b) if b == a => return Err(IllegalMove::NotAMove),
+                    Some(b) => rank(a)? + rank(b)?,
+                    None => rank(a)?,
+                };
+
+                if total == 13 {
+                    Ok(())
                 } else {
-                    write!(
-                        f,
-                        "{}",
-                        Card(self.slots[col_ind][row_ind as usize])
-                            .highlight(col_ind == hl_col && row_ind >= hl_row)
-                    )?;
+                    Err(IllegalMove::NotThirteen)
                 }
             }
-            writeln!(f, "\r")?;
+        }
+    }
+
+    // Performs a move if it is legal, otherwise leaves the state untouched
+    pub fn apply_move(&mut self, m: Move) -> Result<(), IllegalMove> {
+        self.check_move(m)?;
+
+        let recycles = self.stock_len() == 0;
+        let n_hidden = self.n_hidden();
+        let n_solved = self.n_solved();
+
+        match m {
+            Move::Draw if self.rules.game == Game::Spider => self.deal_row(),
+            Move::Draw => {
+                let waste_len = self.waste_len();
+                let draw = self.rules.draw.min(self.stock_len() as u8);
+                // Cards going from the stock to the waste or back
+                let turned = match draw {
+                    0 => 0..waste_len,
+                    _ => waste_len..waste_len + draw as usize,
+                };
+                self.deck_key ^= self.deck_keys(turned.clone());
+
+                // Turns the waste back over once the stock runs out
+                if draw > 0 {
+                    self.waste_len += draw;
+                } else {
+                    self.waste_len = 0;
+                    self.passes += 1;
+                }
+
+                self.deck_key ^= self.deck_keys(turned);
+            }
+            Move::WasteToSlot(col) => {
+                let card = self.waste_card()?;
+
+                self.push_slot(col as usize, card);
+                self.pop_waste();
+            }
+            Move::WasteToFoundation => {
+                let card = self.waste_card()?;
+
+                self.targets[self.foundation_for(card)?] += 1;
+                self.pop_waste();
+            }
+            Move::SlotToSlot(from_col, row, col) => {
+                let (from_col, col) = (from_col as usize, col as usize);
+                let n_moved = self.slot_len(from_col) - row;
+
+                for i in 0..n_moved as usize {
+                    let card = self.slot_card(from_col, row as usize + i);
+                    self.push_slot(col, card);
+                }
+
+                self.pop_slot(from_col, n_moved);
+            }
+            Move::SlotToFoundation(col) => {
+                let col = col as usize;
+                let card = self.slot_card(col, self.slot_len(col) as usize - 1);
+
+                self.targets[self.foundation_for(card)?] += 1;
+                self.pop_slot(col, 1);
+            }
+            Move::FoundationToSlot(i, col) => {
+                let card = self.target(i).ok_or(IllegalMove::NoCard)?;
+
+                self.targets[i as usize] -= 1;
+                self.push_slot(col as usize, card);
+            }
+            Move::SlotToCell(col, cell) => {
+                let col = col as usize;
+                let card = self.slot_card(col, self.slot_len(col) as usize - 1);
+
+                self.cells[cell as usize] = card.0;
+                self.pop_slot(col, 1);
+            }
+            Move::CellToSlot(cell, col) => {
+                let card = self.cell_card(cell)?;
+
+                self.cells[cell as usize] = 0;
+                self.push_slot(col as usize, card);
+            }
+            Move::CellToFoundation(cell) => {
+                let card = self.cell_card(cell)?;
+
+                self.targets[self.foundation_for(card)?] += 1;
+                self.cells[cell as usize] = 0;
+            }
+            // The foundations count the removed cards of each suit
+            Move::Remove(a, b) => {
+                for location in [Some(a), b].into_iter().flatten() {
+                    let card = self.card_at(location);
+                    self.targets[card.suit() as usize] += 1;
+
+                    match location {
+                        Location::Slot(row, i) => {
+                            let (row, i) = (row as usize, i as usize);
+                            self.slots_keys[row] ^=
+                                zobrist::slot_key(i, self.slots[row][i]);
+                            self.slots[row][i] = 0;
+                        }
+                        _ => self.pop_waste(),
+                    }
+                }
+            }
+        }
+
+        if self.rules.game == Game::Spider {
+            for col in 0..self.n_slots() {
+                self.remove_complete_run(col);
+            }
+        }
+
+        if self.rules.scoring == Scoring::Standard {
+            let points = match self.rules.game {
+                Game::Klondike | Game::FreeCell | Game::FortyThieves => {
+                    let flips = (n_hidden - self.n_hidden()) as i32;
+                    scoring::move_points(m, self.rules.draw, recycles)
+                        + flips * scoring::FLIP
+                }
+                Game::Spider => {
+                    let runs = ((self.n_solved() - n_solved) / 13) as i32;
+                    scoring::SPIDER_MOVE + runs * scoring::SPIDER_RUN
+                }
+                Game::Pyramid => {
+                    let removed = (self.n_solved() - n_solved) as i32;
+                    removed * scoring::PYRAMID_CARD
+                }
+            };
+            self.score = self.score.saturating_add_signed(points as i16);
+        }
+
+        #[cfg(debug_assertions)]
+        if let Err(e) = self.check_invariants() {
+            panic!("{m:?} left the board broken: {e}");
         }
 
         Ok(())
     }
 
-    // pub fn try_move()
+    // Whether a card can go to its foundation without any risk of needing
+    // it in the slots later. That is the case when every card one rank
+    // lower that could be put on it, of the other color or of the same suit
+    // depending on the game, is already on the foundations.
+    pub fn is_safe_to_foundation(&self, card: Card) -> bool {
+        let (first, step) = if self.rules.game.builds_in_suit() {
+            (card.suit() as usize, self.rules.suits as usize)
+        } else {
+            (!card.is_red() as usize, 2)
+        };
+        let mut builds_on = (first..self.n_foundations())
+            .step_by(step)
+            .map(|i| self.targets[i]);
 
-    pub fn highlight(self, highlight: Highlight) -> HighlightedSolitareState {
-        HighlightedSolitareState(self, highlight)
+        card.rank() <= 2 || builds_on.all(|t| t + 1 >= card.rank())
     }
-}
 
-impl Display for SolitareState {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.render(f, None)
+    // The waste, free cell and slot top cards, with the moves that would
+    // put them on their foundations
+    fn foundation_candidates(&self) -> impl Iterator<Item = (Move, Card)> {
+        let waste =
+            self.waste_top().map(|card| (Move::WasteToFoundation, card));
+
+        let cells = (0..self.n_cells()).filter_map(|i| {
+            Some((Move::CellToFoundation(i as u8), self.cell(i)?))
+        });
+
+        let slots = (0..self.n_slots()).filter_map(|col| {
+            let len = self.slot_len(col) as usize;
+            let card = self.slot_card(col, len.checked_sub(1)?);
+            Some((Move::SlotToFoundation(col as u8), card))
+        });
+
+        waste.into_iter().chain(cells).chain(slots)
     }
-}
 
-impl Default for SolitareState {
-    fn default() -> Self {
-        Self::new()
+    // A legal move putting a card on its foundation that is safe to make,
+    // if there is one
+    pub fn safe_foundation_move(&self) -> Option<Move> {
+        self.foundation_candidates()
+            .find(|&(m, card)| {
+                self.is_safe_to_foundation(card) && self.check_move(m).is_ok()
+            })
+            .map(|(m, _)| m)
+    }
+
+    // Whether the game can be finished by only putting cards on the
+    // foundations: every slot card is face up and every deck card can be
+    // reached by drawing one at a time, as often as needed. Spider can not
+    // be finished this way, as the cards only go to the foundations in runs.
+    pub fn can_auto_complete(&self) -> bool {
+        let deck_reachable = self.deck_len == 0
+            || self.rules.draw == 1 && self.rules.max_passes.is_none();
+
+        self.rules.game == Game::Klondike
+            && !self.is_won()
+            && self.n_hidden() == 0
+            && deck_reachable
+    }
+
+    // Next move when finishing a game that can be auto-completed. Puts the
+    // lowest card it can on a foundation, or draws if there is none.
+    pub fn auto_complete_move(&self) -> Option<Move> {
+        self.foundation_candidates()
+            .filter(|&(m, _)| self.check_move(m).is_ok())
+            .min_by_key(|(_, card)| card.rank())
+            .map(|(m, _)| m)
+            .or_else(|| {
+                self.check_move(Move::Draw).is_ok().then_some(Move::Draw)
+            })
+    }
+
+    // Every legal move from the current position
+    pub fn legal_moves(&self) -> Vec<Move> {
+        if self.rules.game == Game::Pyramid {
+            return self.legal_pyramid_moves();
+        }
+
+        let mut moves = vec![Move::Draw, Move::WasteToFoundation];
+        let n_slots = self.n_slots() as u8;
+
+        moves.extend((0..n_slots).map(Move::WasteToSlot));
+
+        for from_col in 0..n_slots {
+            moves.push(Move::SlotToFoundation(from_col));
+
+            let n_hidden = self.slot_hidden(from_col as usize);
+            let n_cards = self.slot_len(from_col as usize);
+
+            for row in n_hidden..n_cards {
+                moves.extend(
+                    (0..n_slots)
+                        .map(|col| Move::SlotToSlot(from_col, row, col)),
+                );
+            }
+        }
+
+        for i in 0..self.n_foundations() as u8 {
+            moves
+                .extend((0..n_slots).map(|col| Move::FoundationToSlot(i, col)));
+        }
+
+        for cell in 0..self.n_cells() as u8 {
+            moves.push(Move::CellToFoundation(cell));
+            moves.extend((0..n_slots).map(|col| Move::CellToSlot(cell, col)));
+
+            // Empty free cells are all alike, so only the first one is used
+            if self.cell(cell as usize).is_none()
+                && (0..cell).all(|i| self.cell(i as usize).is_some())
+            {
+                moves.extend(
+                    (0..n_slots).map(|col| Move::SlotToCell(col, cell)),
+                );
+            }
+        }
+
+        moves.retain(|&m| self.check_move(m).is_ok());
+
+        moves
     }
-}
 
-pub struct HighlightedSolitareState(SolitareState, Highlight);
+    fn legal_pyramid_moves(&self) -> Vec<Move> {
+        let n_slots = self.n_slots() as u8;
+        let rows = (0..n_slots)
+            .flat_map(|row| (0..=row).map(move |i| Location::Slot(row, i)));
 
-impl Display for HighlightedSolitareState {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.0.render(f, Some(self.1))
+        let cards: Vec<_> = [Location::Waste]
+            .into_iter()
+            .chain(rows)
+            .filter(|&l| self.is_location_valid(l)[0])
+            .collect();
+
+        let mut moves = vec![Move::Draw];
+
+        for (k, &a) in cards.iter().enumerate() {
+            moves.push(Move::Remove(a, None));
+            moves.extend(
+                cards[k + 1..].iter().map(|&b| Move::Remove(a, Some(b))),
+            );
+        }
+
+        moves.retain(|&m| self.check_move(m).is_ok());
+
+        moves
+    }
+
+    // Number of cards on the foundations
+    pub fn n_solved(&self) -> usize {
+        self.targets.iter().map(|&t| t as usize).sum()
+    }
+
+    // Number of face-down cards in the slots
+    pub fn n_hidden(&self) -> usize {
+        (0..self.n_slots())
+            .map(|col| self.slot_hidden(col) as usize)
+            .sum()
+    }
+
+    // Whether this position has made progress compared to an earlier one
+    // that can not be undone: more cards on the foundations, fewer cards in
+    // the deck or fewer hidden cards.
+    pub fn has_progressed_from(&self, earlier: &Self) -> bool {
+        self.n_solved() > earlier.n_solved()
+            || self.deck_len() < earlier.deck_len()
+            || self.n_hidden() < earlier.n_hidden()
+    }
+
+    // Whether the game is lost, meaning no sequence of moves leads to any
+    // progress. Positions needing too many rearranging moves before making
+    // progress are assumed not to be dead ends.
+    pub fn is_dead_end(&self) -> bool {
+        const SEARCH_LIMIT: usize = 10_000;
+
+        if self.is_won() {
+            return false;
+        }
+
+        let start = self.position();
+
+        let mut visited = HashSet::from([start]);
+        let mut queue = vec![start];
+
+        while let Some(state) = queue.pop() {
+            for m in state.legal_moves() {
+                let mut next = state;
+                next.apply_move(m).unwrap();
+                let next = next.position();
+
+                if next.has_progressed_from(self) {
+                    return false;
+                }
+
+                if visited.insert(next) {
+                    if visited.len() > SEARCH_LIMIT {
+                        return false;
+                    }
+                    queue.push(next);
+                }
+            }
+        }
+
+        true
+    }
+
+    // The position without the score, and without the pass count when the
+    // passes are not limited. Neither changes which moves can be made, so
+    // searches compare positions this way, otherwise moving a card back and
+    // forth would never end.
+    pub(crate) fn position(&self) -> Self {
+        let passes = match self.rules.max_passes {
+            Some(_) => self.passes,
+            None => 1,
+        };
+
+        Self {
+            score: 0,
+            passes,
+            ..*self
+        }
+    }
+
+    // The position with the slots and free cells sorted, the same for
+    // every order of them. Slots can be told apart in Pyramid, where they
+    // are rows, and in Spider while rows are still dealt from the stock,
+    // one card to each slot in turn.
+    pub(crate) fn canonical_position(&self) -> Self {
+        let mut position = self.position();
+
+        let cells = &mut position.cells[..self.n_cells()];
+        cells.sort_unstable();
+
+        if self.slots_interchangeable() {
+            let n_slots = self.n_slots();
+            let mut order: Vec<usize> = (0..n_slots).collect();
+            order.sort_unstable_by_key(|&col| {
+                (
+                    &self.slots[col][..self.slots_lens[col] as usize],
+                    self.slots_hidden[col],
+                )
+            });
+
+            for (i, col) in order.into_iter().enumerate() {
+                position.slots[i] = self.slots[col];
+                position.slots_lens[i] = self.slots_lens[col];
+                position.slots_hidden[i] = self.slots_hidden[col];
+                position.slots_keys[i] = self.slots_keys[col];
+            }
+        }
+
+        position
+    }
+
+    // Whether the slots can be told apart only by their cards, so their
+    // order makes no difference to the moves there are
+    fn slots_interchangeable(&self) -> bool {
+        match self.rules.game {
+            Game::Pyramid => false,
+            Game::Spider => self.stock_len() == 0,
+            _ => true,
+        }
+    }
+
+    fn waste_card(&self) -> Result<Card, IllegalMove> {
+        self.waste_top().ok_or(IllegalMove::NoCard)
+    }
+
+    fn check_slot_index(&self, col: u8) -> Result<(), IllegalMove> {
+        if (col as usize) < self.n_slots() {
+            Ok(())
+        } else {
+            Err(IllegalMove::NotAMove)
+        }
+    }
+}
+
+impl Default for SolitareState {
+    fn default() -> Self {
+        Self::new(rand::random(), Rules::default())
     }
 }
@@ -0,0 +1,219 @@
+use super::{
+    MAX_CELLS, MAX_DECK_SIZE, MAX_FOUNDATIONS, MAX_HEIGHT, MAX_SLOTS,
+    SolitareState,
+};
+use crate::{
+    bits::{BitReader, BitWriter},
+    rules::{Game, Rules, Scoring},
+};
+
+// Compact binary form of the state, packed bit by bit with the lowest bits
+// first. A Klondike position takes some 64 bytes rather than the more than
+// a kilobyte of SolitareState:
+//
+//   rules                     6 bytes, one field after the other
+//   passes, score             8 and 16 bits
+//   stock and waste length    7 bits each, then a card for each
+//   foundations               4 bits each, the cards on them
+//   free cells                a card each, 0 when empty
+//   slots                     7 bits each of length and face-down cards,
+//                             then the cards from the bottom
+//
+// Cards take 6 bits, as the suit and rank of `Card` fit in them. Only the
+// foundations, free cells and slots in play are written, so the length
+// follows from the rules.
+const LEN_BITS: u32 = 7;
+const CARD_BITS: u32 = 6;
+const TARGET_BITS: u32 = 4;
+
+// Reading past the end of the bytes given
+fn too_few() -> String {
+    String::from("too few bytes")
+}
+
+const SCORINGS: [Scoring; 3] =
+    [Scoring::Standard, Scoring::Vegas, Scoring::None];
+
+impl SolitareState {
+    // The position in the compact form above, exactly as it is, as quick
+    // to write and read back as a save
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = BitWriter::with_capacity(128);
+
+        write_rules(&mut w, &self.rules);
+        w.write(self.passes as u32, 8);
+        w.write(self.score as u32, 16);
+
+        w.write(self.deck_len as u32, LEN_BITS);
+        w.write(self.waste_len as u32, LEN_BITS);
+        for &card in &self.deck[..self.deck_len()] {
+            w.write(card as u32, CARD_BITS);
+        }
+
+        for &n in &self.targets[..self.n_foundations()] {
+            w.write(n as u32, TARGET_BITS);
+        }
+        for &card in &self.cells[..self.n_cells()] {
+            w.write(card as u32, CARD_BITS);
+        }
+
+        for col in 0..self.n_slots() {
+            let len = self.slots_lens[col];
+            w.write(len as u32, LEN_BITS);
+            w.write(self.slots_hidden[col] as u32, LEN_BITS);
+            for &card in &self.slots[col][..len as usize] {
+                w.write(card as u32, CARD_BITS);
+            }
+        }
+
+        w.finish()
+    }
+
+    // The same for every position differing only in the order of the slots
+    // or free cells, as in `canonical_position`
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        self.canonical_position().to_bytes()
+    }
+
+    // Position from `to_bytes`, checked to be one the rules can lead to
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut r = BitReader::new(bytes);
+        let mut state = Self {
+            deck: [0; MAX_DECK_SIZE],
+            deck_len: 0,
+            waste_len: 0,
+            passes: 1,
+            score: 0,
+            rules: read_rules(&mut r)?,
+            targets: [0; MAX_FOUNDATIONS],
+            slots: [[0; MAX_HEIGHT]; MAX_SLOTS],
+            slots_lens: [0; MAX_SLOTS],
+            slots_hidden: [0; MAX_SLOTS],
+            cells: [0; MAX_CELLS],
+            slots_keys: [0; MAX_SLOTS],
+            deck_key: 0,
+        };
+
+        state.passes = r.read_u8(8).ok_or_else(too_few)?;
+        state.score = r.read(16).ok_or_else(too_few)? as u16;
+
+        state.deck_len = r.read_u8(LEN_BITS).ok_or_else(too_few)?;
+        state.waste_len = r.read_u8(LEN_BITS).ok_or_else(too_few)?;
+        if state.deck_len() > MAX_DECK_SIZE {
+            return Err(String::from("too many cards in the stock and waste"));
+        }
+        for i in 0..state.deck_len() {
+            state.deck[i] = r.read_u8(CARD_BITS).ok_or_else(too_few)?;
+        }
+
+        for i in 0..state.n_foundations() {
+            state.targets[i] = r.read_u8(TARGET_BITS).ok_or_else(too_few)?;
+        }
+        for i in 0..state.n_cells() {
+            state.cells[i] = r.read_u8(CARD_BITS).ok_or_else(too_few)?;
+        }
+
+        for col in 0..state.n_slots() {
+            let len = r.read_u8(LEN_BITS).ok_or_else(too_few)?;
+            if len as usize > MAX_HEIGHT {
+                return Err(format!("too many cards in slot {}", col + 1));
+            }
+            state.slots_lens[col] = len;
+            state.slots_hidden[col] =
+                r.read_u8(LEN_BITS).ok_or_else(too_few)?;
+            for row in 0..len as usize {
+                state.slots[col][row] =
+                    r.read_u8(CARD_BITS).ok_or_else(too_few)?;
+            }
+        }
+
+        if r.has_bytes_left() || r.has_bits_left() {
+            return Err(String::from("too many bytes"));
+        }
+
+        state.rehash();
+        state.check_invariants()?;
+
+        Ok(state)
+    }
+}
+
+impl Rules {
+    // The rules alone, in the 6 bytes they take at the start of
+    // `SolitareState::to_bytes`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = BitWriter::with_capacity(6);
+        write_rules(&mut w, self);
+        w.finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut r = BitReader::new(bytes);
+        let rules = read_rules(&mut r)?;
+
+        if r.has_bytes_left() {
+            return Err(String::from("too many bytes"));
+        }
+
+        Ok(rules)
+    }
+}
+
+fn write_rules(w: &mut BitWriter, rules: &Rules) {
+    let index = |found: Option<usize>| found.unwrap() as u32;
+
+    w.write(rules.draw as u32, 8);
+    w.write(rules.max_passes.unwrap_or(0) as u32, 8);
+    w.write(rules.any_card_on_empty_slot as u32, 1);
+    w.write(rules.foundation_to_slot as u32, 1);
+    w.write(rules.thoughtful as u32, 1);
+    w.write(index(SCORINGS.iter().position(|&s| s == rules.scoring)), 2);
+    w.write(rules.decks as u32, 3);
+    w.write(rules.slots as u32, 5);
+    w.write(index(Game::ALL.iter().position(|&g| g == rules.game)), 3);
+    w.write(rules.suits as u32, 3);
+    w.write(rules.cells as u32, 3);
+    w.write(rules.run_limit as u32, 1);
+    // Rounded up to whole bytes, leaving room for more rules
+    w.write(0, 9);
+}
+
+fn read_rules(r: &mut BitReader) -> Result<Rules, String> {
+    let draw = r.read_u8(8).ok_or_else(too_few)?;
+    let max_passes = r.read_u8(8).ok_or_else(too_few)?;
+    let any_card_on_empty_slot = r.read(1).ok_or_else(too_few)? == 1;
+    let foundation_to_slot = r.read(1).ok_or_else(too_few)? == 1;
+    let thoughtful = r.read(1).ok_or_else(too_few)? == 1;
+    let scoring = SCORINGS.get(r.read(2).ok_or_else(too_few)? as usize);
+    let decks = r.read_u8(3).ok_or_else(too_few)?;
+    let slots = r.read_u8(5).ok_or_else(too_few)?;
+    let game = Game::ALL.get(r.read(3).ok_or_else(too_few)? as usize);
+    let suits = r.read_u8(3).ok_or_else(too_few)?;
+    let cells = r.read_u8(3).ok_or_else(too_few)?;
+    let run_limit = r.read(1).ok_or_else(too_few)? == 1;
+    r.read(9).ok_or_else(too_few)?;
+
+    let (Some(&scoring), Some(&game)) = (scoring, game) else {
+        return Err(String::from("invalid rules"));
+    };
+    let rules = Rules {
+        draw,
+        max_passes: (max_passes > 0).then_some(max_passes),
+        any_card_on_empty_slot,
+        foundation_to_slot,
+        thoughtful,
+        scoring,
+        decks,
+        slots,
+        game,
+        suits,
+        cells,
+        run_limit,
+    };
+
+    if !rules.is_valid() {
+        return Err(String::from("invalid rules"));
+    }
+
+    Ok(rules)
+}
@@ -0,0 +1,263 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    MAX_CELLS, MAX_DECK_SIZE, MAX_FOUNDATIONS, MAX_HEIGHT, MAX_SLOTS,
+    SolitareState,
+};
+use crate::{
+    card::Card,
+    rules::{Game, Rules},
+};
+
+// Serialized form of the state. Cards are stored by name, like `10♥`, so
+// saves do not depend on the bit-packed layout of SolitareState. This is
+// also the JSON exchanged with other tools, so fields are only ever added,
+// with defaults for positions written before them:
+//
+//   {
+//     "foundations": [3, 0, 1, 0],
+//     "stock": ["K♠", "4♦", ...],
+//     "waste": ["2♣", "9♥"],
+//     "passes": 1,
+//     "score": 15,
+//     "rules": { "game": "klondike", "draw": 1, ... },
+//     "slots": [{ "hidden": ["7♣"], "visible": ["Q♥", "J♠"] }, ...],
+//     "cells": [null, "5♦", null, null]
+//   }
+//
+// Suits can also be given by their letters, like `10H`. Foundation i holds
+// suit i % 4 in the order ♠ ♥ ♣ ♦, counting cards from the ace. Pyramid
+// rows are slots without hidden cards, with `-` for removed cards.
+#[derive(Serialize, Deserialize)]
+pub struct StateRepr {
+    foundations: Vec<u8>, // Number of cards on each foundation, ♠ ♥ ♣ ♦ …
+    stock: Vec<String>,   // Next card to be drawn first
+    waste: Vec<String>,   // Top card last
+    #[serde(default = "default_passes")]
+    passes: u8,
+    #[serde(default)]
+    score: u16,
+    #[serde(default)]
+    rules: Rules,
+    slots: Vec<SlotRepr>,
+    #[serde(default)]
+    cells: Vec<Option<String>>, // Free cells, null when empty
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SlotRepr {
+    hidden: Vec<String>,  // Face-down cards, bottom first
+    visible: Vec<String>, // Face-up cards, bottom first
+}
+
+impl From<SolitareState> for StateRepr {
+    fn from(state: SolitareState) -> Self {
+        let slots = (0..state.n_slots())
+            .map(|col| {
+                let (hidden, visible) = state.slots[col]
+                    [..state.slot_len(col) as usize]
+                    .split_at(state.slot_hidden(col) as usize);
+
+                SlotRepr {
+                    hidden: hidden.iter().map(|&c| card_name(c)).collect(),
+                    visible: visible.iter().map(|&c| card_name(c)).collect(),
+                }
+            })
+            .collect();
+
+        Self {
+            foundations: state.targets[..state.n_foundations()].to_vec(),
+            stock: state.stock_cards().map(|c| c.name()).collect(),
+            waste: state.waste_cards().map(|c| c.name()).collect(),
+            passes: state.passes,
+            score: state.score,
+            rules: state.rules,
+            slots,
+            cells: (0..state.n_cells())
+                .map(|i| state.cell(i).map(|c| c.name()))
+                .collect(),
+        }
+    }
+}
+
+// Cards removed from a pyramid leave a hole, written as `-`
+fn card_name(card: u8) -> String {
+    match card {
+        0 => String::from("-"),
+        _ => Card(card).name(),
+    }
+}
+
+fn default_passes() -> u8 {
+    1
+}
+
+// Counts the card as seen, which it may be once for every copy in the game
+fn parse_card(
+    name: &str,
+    seen: &mut [u8; 52],
+    rules: &Rules,
+) -> Result<Card, String> {
+    let card =
+        Card::from_name(name).ok_or_else(|| format!("invalid card: {name}"))?;
+
+    if seen[card.to_ind()] == rules.copies(card) {
+        return Err(format!("duplicate card: {name}"));
+    }
+    seen[card.to_ind()] += 1;
+
+    Ok(card)
+}
+
+#[cfg(feature = "json")]
+impl SolitareState {
+    // Position as JSON in the form of `StateRepr`
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    // Checks that the position is complete, with every card of the game
+    // exactly once, and that it is one the rules can lead to
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| e.to_string())
+    }
+}
+
+impl TryFrom<StateRepr> for SolitareState {
+    type Error = String;
+
+    fn try_from(repr: StateRepr) -> Result<Self, Self::Error> {
+        let mut state = Self {
+            deck: [0; MAX_DECK_SIZE],
+            deck_len: 0,
+            waste_len: 0,
+            passes: 1,
+            score: 0,
+            rules: Rules::default(),
+            targets: [0; MAX_FOUNDATIONS],
+            slots: [[0; MAX_HEIGHT]; MAX_SLOTS],
+            slots_lens: [0; MAX_SLOTS],
+            slots_hidden: [0; MAX_SLOTS],
+            cells: [0; MAX_CELLS],
+            slots_keys: [0; MAX_SLOTS],
+            deck_key: 0,
+        };
+        let mut seen = [0; 52];
+
+        if !repr.rules.is_valid() {
+            return Err(String::from("invalid rules"));
+        }
+        let (rules, decks) = (repr.rules, repr.rules.decks);
+
+        if repr.foundations.len() != 4 * decks as usize {
+            return Err(format!("expected {} foundations", 4 * decks));
+        }
+
+        for (i, &n) in repr.foundations.iter().enumerate() {
+            if n > 13 {
+                return Err(format!("too many cards on foundation {i}"));
+            }
+
+            state.targets[i] = n;
+
+            // Pyramid foundations only count the removed cards of each suit
+            if rules.game == Game::Pyramid {
+                continue;
+            }
+
+            for rank in 1..=n {
+                let card = Card::from_suit_rank(i as u8 % rules.suits, rank);
+                seen[card.to_ind()] += 1;
+            }
+        }
+
+        if repr.waste.len() + repr.stock.len() > MAX_DECK_SIZE {
+            return Err(String::from("too many cards in the stock and waste"));
+        }
+
+        for (i, name) in repr.waste.iter().chain(&repr.stock).enumerate() {
+            state.deck[i] = parse_card(name, &mut seen, &rules)?.0;
+        }
+
+        state.deck_len = (repr.waste.len() + repr.stock.len()) as u8;
+        state.waste_len = repr.waste.len() as u8;
+
+        if repr.passes == 0
+            || repr.rules.max_passes.is_some_and(|max| repr.passes > max)
+        {
+            return Err(format!("invalid pass number {}", repr.passes));
+        }
+        state.passes = repr.passes;
+        state.score = repr.score;
+        state.rules = repr.rules;
+
+        if repr.slots.len() != state.n_slots() {
+            return Err(format!("expected {} slots", state.n_slots()));
+        }
+
+        for (col, slot) in repr.slots.iter().enumerate() {
+            let n_hidden = slot.hidden.len();
+            let n_cards = n_hidden + slot.visible.len();
+
+            if n_cards > MAX_HEIGHT {
+                return Err(format!("too many cards in slot {}", col + 1));
+            }
+            // Face-down cards are covered by at least one face-up card
+            if n_hidden > 0 && slot.visible.is_empty() {
+                return Err(format!("no face-up card in slot {}", col + 1));
+            }
+
+            for (row, name) in
+                slot.hidden.iter().chain(&slot.visible).enumerate()
+            {
+                if rules.game == Game::Pyramid && name == "-" {
+                    continue;
+                }
+
+                state.slots[col][row] = parse_card(name, &mut seen, &rules)?.0;
+            }
+
+            if rules.game == Game::Pyramid
+                && (n_cards != col + 1 || n_hidden > 0)
+            {
+                return Err(format!("pyramid row {} is not a row", col + 1));
+            }
+
+            state.slots_lens[col] = n_cards as u8;
+            state.slots_hidden[col] = n_hidden as u8;
+        }
+
+        if repr.cells.len() != state.n_cells() {
+            return Err(format!("expected {} free cells", state.n_cells()));
+        }
+
+        for (i, name) in repr.cells.iter().enumerate() {
+            if let Some(name) = name {
+                state.cells[i] = parse_card(name, &mut seen, &rules)?.0;
+            }
+        }
+
+        let all_seen = if rules.game == Game::Pyramid {
+            (0..4).all(|suit| {
+                let left: u8 = seen[suit * 13..(suit + 1) * 13].iter().sum();
+                left + state.targets[suit] == 13
+            })
+        } else {
+            seen.iter()
+                .enumerate()
+                .all(|(i, &n)| n == rules.copies(Card::from_index(i)))
+        };
+
+        if !all_seen {
+            return Err(format!("not all {} cards are present", 52 * decks));
+        }
+
+        state.rehash();
+        // Positions the moves could not lead to, like a Klondike slot with
+        // face-up cards not built on each other, are turned away here
+        // rather than going wrong in play
+        state.check_invariants()?;
+
+        Ok(state)
+    }
+}
@@ -0,0 +1,127 @@
+use std::ops::Range;
+
+use super::SolitareState;
+
+// Zobrist hashing: every card in every place has a key of random-looking
+// bits, and a position hashes to the keys of its cards combined. The keys
+// of the slots and of the stock and waste, which hold most of the cards,
+// are kept up to date by the moves, so only the few foundations and free
+// cells are gone through when hashing.
+//
+// Keys are XORed together within a slot, so moving a card in or out takes
+// a single XOR. The slots are then mixed and added up rather than XORed,
+// so cards can not cancel out between slots of the same contents, and the
+// order of the slots does not matter unless they can be told apart. Waste
+// cards are counted from the bottom and stock cards from the last one to
+// be drawn, so neither change when the top card of the waste is taken.
+
+const SLOT: u64 = 1;
+const WASTE: u64 = 2;
+const STOCK: u64 = 3;
+const HIDDEN: u64 = 4;
+const COLUMN: u64 = 5;
+const TARGET: u64 = 6;
+const CELL: u64 = 7;
+const PASSES: u64 = 8;
+
+// The splitmix64 finalizer, spreading every bit of x over the result
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+// Key of a card, or a count, in a kind of place at an index
+fn key(kind: u64, index: usize, card: u8) -> u64 {
+    mix(kind << 48 | (index as u64) << 8 | card as u64)
+}
+
+// Key of a card in a slot, 0 for the holes left in a pyramid
+pub(super) fn slot_key(row: usize, card: u8) -> u64 {
+    match card {
+        0 => 0,
+        _ => key(SLOT, row, card),
+    }
+}
+
+impl SolitareState {
+    // Hash of the position, in the sense of `canonical_position`: the
+    // score and the order of interchangeable slots and of the free cells
+    // make no difference. Neither do the rules, so only positions of the
+    // same game should be compared.
+    pub fn zobrist(&self) -> u64 {
+        let interchangeable = self.slots_interchangeable();
+
+        let slots = (0..self.n_slots()).fold(0u64, |hash, col| {
+            let column = if interchangeable {
+                0
+            } else {
+                key(COLUMN, col, 0)
+            };
+            let hidden = key(HIDDEN, self.slots_hidden[col] as usize, 0);
+
+            hash.wrapping_add(mix(self.slots_keys[col] ^ hidden ^ column))
+        });
+
+        let cells = self.cells[..self.n_cells()]
+            .iter()
+            .filter(|&&card| card != 0)
+            .fold(0u64, |hash, &card| hash.wrapping_add(key(CELL, 0, card)));
+
+        let targets = self.targets[..self.n_foundations()]
+            .iter()
+            .enumerate()
+            .fold(0, |hash, (i, &n)| hash ^ key(TARGET, i, n));
+
+        let passes = match self.rules.max_passes {
+            Some(_) => key(PASSES, self.passes as usize, 0),
+            None => 0,
+        };
+
+        slots ^ cells ^ targets ^ passes ^ self.deck_key
+    }
+
+    // Keys of the stock and waste cards in a range of the deck, to take
+    // them out of `deck_key` before they move and put them back after
+    pub(super) fn deck_keys(&self, range: Range<usize>) -> u64 {
+        range.fold(0, |hash, i| {
+            let card = self.deck[i];
+            hash ^ if i < self.waste_len() {
+                key(WASTE, i, card)
+            } else {
+                key(STOCK, self.deck_len() - 1 - i, card)
+            }
+        })
+    }
+
+    // Key of the cards in a slot, from scratch
+    fn slot_keys(&self, col: usize) -> u64 {
+        self.slots[col][..self.slots_lens[col] as usize]
+            .iter()
+            .enumerate()
+            .fold(0, |hash, (row, &card)| hash ^ slot_key(row, card))
+    }
+
+    // Sets the keys kept up to date by the moves, for a position put
+    // together some other way
+    pub(super) fn rehash(&mut self) {
+        for col in 0..self.slots_keys.len() {
+            self.slots_keys[col] = self.slot_keys(col);
+        }
+        self.deck_key = self.deck_keys(0..self.deck_len());
+    }
+
+    // Whether the keys kept up to date by the moves are what they should be
+    pub(super) fn check_keys(&self) -> Result<(), String> {
+        let mut fresh = *self;
+        fresh.rehash();
+
+        if (fresh.slots_keys, fresh.deck_key)
+            != (self.slots_keys, self.deck_key)
+        {
+            return Err(String::from("hash keys out of date"));
+        }
+
+        Ok(())
+    }
+}
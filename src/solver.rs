@@ -0,0 +1,362 @@
+// Depth-first search for a way to win a deal. Every position is only
+// searched once, and moves that can not help are left out. Positions
+// differing only in the order of the slots or free cells count as the same.
+
+use std::collections::{HashSet, VecDeque};
+
+use rand::Rng;
+
+use crate::{
+    card::Card,
+    moves::Move,
+    rules::{Game, Rules},
+    solitare_state::{Location, SolitareState},
+};
+
+// Positions searched before giving up, which keeps the time used to some
+// seconds and the memory to some tens of megabytes
+pub const MAX_POSITIONS: usize = 1_000_000;
+
+// Positions searched for each deal when looking for one that can be won.
+// Deals that take longer to decide are passed over rather than waited for.
+pub const WINNABLE_POSITIONS: usize = 100_000;
+
+// Positions looked at for a hint, few enough to answer right away
+pub const HINT_POSITIONS: usize = 10_000;
+
+// Ways the hidden cards could lie tried for `win_chance`, and positions
+// searched for each, few enough for a guess in the time of a move
+pub const CHANCE_SAMPLES: usize = 20;
+pub const CHANCE_POSITIONS: usize = 2_000;
+
+// Memory taken by each position searched, for its hash and the room the
+// table of them keeps to grow into
+pub const POSITION_BYTES: usize = 24;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Solution {
+    Won(Vec<Move>), // Moves winning the game, in order
+    Lost,           // No sequence of moves wins
+    GaveUp,         // Too many positions to search them all
+}
+
+struct Frame {
+    state: SolitareState,
+    moves: Vec<Move>,
+    next: usize, // Index of the next move to try
+}
+
+impl Frame {
+    fn new(state: SolitareState) -> Self {
+        Self {
+            state,
+            moves: ordered_moves(&state),
+            next: 0,
+        }
+    }
+}
+
+// Searches every position reachable from a state, up to a number of them,
+// for one where the game is won
+pub fn solve(state: &SolitareState, max_positions: usize) -> Solution {
+    // Only hashes are kept, as whole positions take up a lot of memory
+    let mut visited = HashSet::from([position_hash(state)]);
+    let mut stack = vec![Frame::new(*state)];
+    let mut path = Vec::new();
+
+    while let Some(frame) = stack.last_mut() {
+        if frame.state.is_won() {
+            return Solution::Won(path);
+        }
+
+        let Some(&m) = frame.moves.get(frame.next) else {
+            stack.pop();
+            path.pop();
+            continue;
+        };
+        frame.next += 1;
+
+        let mut next = frame.state;
+        next.apply_move(m).unwrap();
+
+        if !visited.insert(position_hash(&next)) {
+            continue;
+        }
+        if visited.len() > max_positions {
+            return Solution::GaveUp;
+        }
+
+        path.push(m);
+        stack.push(Frame::new(next));
+    }
+
+    Solution::Lost
+}
+
+// How hard a deal is, by how many positions the solver searches to win it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,   // Won within HINT_POSITIONS
+    Medium, // Within WINNABLE_POSITIONS
+    Hard,   // Within the most positions allowed
+    Unwinnable,
+    Unknown, // Too many positions to tell
+}
+
+impl Difficulty {
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+            Difficulty::Unwinnable => "not winnable",
+            Difficulty::Unknown => "too hard to tell",
+        }
+    }
+}
+
+// Difficulty of a position, along with the winning moves found. The solver
+// is run with ten times as many positions each time, so easy deals are told
+// apart quickly.
+pub fn difficulty(
+    state: &SolitareState,
+    max_positions: usize,
+) -> (Difficulty, Option<Vec<Move>>) {
+    let tiers = [
+        (HINT_POSITIONS, Difficulty::Easy),
+        (WINNABLE_POSITIONS, Difficulty::Medium),
+        (max_positions, Difficulty::Hard),
+    ];
+
+    for (positions, difficulty) in tiers {
+        match solve(state, positions.min(max_positions)) {
+            Solution::Won(moves) => return (difficulty, Some(moves)),
+            Solution::Lost => return (Difficulty::Unwinnable, None),
+            Solution::GaveUp => {}
+        }
+    }
+
+    (Difficulty::Unknown, None)
+}
+
+// Rough chance of winning from a position, as far as the player can see
+// it: the share of a number of random ways the face down cards and the
+// stock could lie that a small search wins. Games it does not get to the
+// end of count as lost, so it is on the low side.
+pub fn win_chance(state: &SolitareState, rng: &mut impl Rng) -> f64 {
+    let hidden = state.hidden_cards().count();
+    // Every sample would be the same
+    let samples = if hidden == 0 { 1 } else { CHANCE_SAMPLES };
+
+    let won = (0..samples)
+        .filter(|_| {
+            let mut sample = *state;
+            sample.shuffle_hidden(rng);
+            matches!(solve(&sample, CHANCE_POSITIONS), Solution::Won(_))
+        })
+        .count();
+
+    won as f64 / samples as f64
+}
+
+// Recommended move without solving the whole game: the first move of the
+// shortest sequence making progress, as in `has_progressed_from`, trying
+// the moves in the same order as when solving. None if there is no such
+// sequence within a limited number of positions.
+pub fn hint(state: &SolitareState) -> Option<Move> {
+    let mut visited = HashSet::from([position_hash(state)]);
+    // Positions with the first move made to reach them
+    let mut queue = VecDeque::from([(*state, None)]);
+
+    while let Some((position, first)) = queue.pop_front() {
+        for m in ordered_moves(&position) {
+            let first = first.unwrap_or(m);
+
+            let mut next = position;
+            next.apply_move(m).unwrap();
+
+            if next.has_progressed_from(state) {
+                return Some(first);
+            }
+            if visited.insert(position_hash(&next)) {
+                if visited.len() > HINT_POSITIONS {
+                    return None;
+                }
+                queue.push_back((next, Some(first)));
+            }
+        }
+    }
+
+    None
+}
+
+// Why a move helps, to follow its notation, like "this uncovers a face down
+// card", with cards named by `name`. Only what the move does by itself is
+// told, not what it leads to.
+pub fn explain(
+    state: &SolitareState,
+    m: Move,
+    name: impl Fn(Card) -> String,
+) -> String {
+    let mut after = *state;
+    if after.apply_move(m).is_err() {
+        return String::from("this is not allowed");
+    }
+
+    let game = state.rules().game;
+    let [from, _] = state.move_locations(m);
+    let moved = from.map(|from| name(state.card_at(from)));
+    let empty_slots = |state: &SolitareState| {
+        (0..state.n_slots())
+            .filter(|&col| state.slot_len(col) == 0)
+            .count()
+    };
+    // Card left on top of a slot after taking cards from it
+    let under = match from {
+        Some(Location::Slot(col, row)) if row > 0 && game != Game::Pyramid => {
+            Some(name(state.slot_card(col as usize, row as usize - 1)))
+        }
+        _ => None,
+    };
+
+    if after.is_won() {
+        return String::from("this wins the game");
+    }
+
+    if after.n_solved() > state.n_solved() {
+        return match (game, m, moved) {
+            (Game::Spider, ..) => String::from("this completes a run"),
+            (Game::Pyramid, Move::Remove(_, Some(_)), _) => {
+                String::from("this removes a pair adding up to 13")
+            }
+            (Game::Pyramid, ..) => String::from("this removes a king"),
+            (_, _, Some(card)) => {
+                format!("this puts the {card} on its foundation")
+            }
+            _ => String::from("this builds up a foundation"),
+        };
+    }
+
+    if after.n_hidden() < state.n_hidden() {
+        return String::from("this uncovers a face down card");
+    }
+
+    if game != Game::Pyramid && empty_slots(&after) > empty_slots(state) {
+        // Kings covering face down cards are the ones waiting for a slot
+        let king_in_slot = (0..after.n_slots()).find(|&col| {
+            let hidden = after.slot_hidden(col);
+            hidden > 0 && after.slot_card(col, hidden as usize).rank() == 13
+        });
+
+        return match after.waste_top() {
+            Some(king)
+                if king.rank() == 13
+                    && !after.rules().any_card_on_empty_slot =>
+            {
+                format!("this frees a slot for the {} in the waste", name(king))
+            }
+            _ => match king_in_slot {
+                Some(col) => {
+                    let king =
+                        after.slot_card(col, after.slot_hidden(col) as usize);
+                    format!(
+                        "this frees a slot for the {} in slot {}",
+                        name(king),
+                        col + 1
+                    )
+                }
+                None => String::from("this frees a slot"),
+            },
+        };
+    }
+
+    match (m, moved, under) {
+        (Move::Draw, ..) if game == Game::Spider => {
+            String::from("this deals a new row")
+        }
+        (Move::Draw, ..) if state.stock_len() > 0 => {
+            String::from("this turns over new cards from the stock")
+        }
+        (Move::Draw, ..) => {
+            String::from("this turns the waste over for another pass")
+        }
+        (Move::WasteToSlot(_), Some(card), _) => {
+            format!("this plays the {card} so the card under it can be used")
+        }
+        (Move::SlotToCell(..), Some(card), Some(under)) => {
+            format!("this parks the {card} to get at the {under}")
+        }
+        (Move::SlotToSlot(..), _, Some(under)) => {
+            format!("this gets at the {under} under the moved cards")
+        }
+        (Move::CellToSlot(..), ..) => String::from("this frees a cell"),
+        (Move::FoundationToSlot(..), Some(card), _) => {
+            format!("this takes the {card} back to build on it")
+        }
+        _ => String::from("this keeps the game going"),
+    }
+}
+
+// The first seed from `seed` on, counting up, dealing a game the solver
+// wins. None if none of the seeds tried does.
+pub fn winnable_seed(seed: u64, rules: Rules, tries: u64) -> Option<u64> {
+    (0..tries).map(|i| seed.wrapping_add(i)).find(|&seed| {
+        let state = SolitareState::new(seed, rules);
+        matches!(solve(&state, WINNABLE_POSITIONS), Solution::Won(_))
+    })
+}
+
+fn position_hash(state: &SolitareState) -> u64 {
+    state.zobrist()
+}
+
+// Legal moves in the order they are tried. A card that is safe to put on
+// its foundation always is, as nothing can be lost by it.
+fn ordered_moves(state: &SolitareState) -> Vec<Move> {
+    if let Some(m) = state.safe_foundation_move() {
+        return vec![m];
+    }
+
+    let mut moves: Vec<_> = state
+        .legal_moves()
+        .into_iter()
+        .filter(|&m| !is_pointless(state, m))
+        .collect();
+
+    moves.sort_by_key(|&m| priority(state, m));
+
+    moves
+}
+
+// Moving a whole slot to an empty one gives the same position again
+fn is_pointless(state: &SolitareState, m: Move) -> bool {
+    match m {
+        Move::SlotToSlot(from_col, 0, col) => {
+            state.slot_hidden(from_col as usize) == 0
+                && state.slot_len(col as usize) == 0
+        }
+        _ => false,
+    }
+}
+
+// Lower first. Moves making progress come before ones only rearranging
+// the cards, and taking cards off the foundations comes last.
+fn priority(state: &SolitareState, m: Move) -> u8 {
+    match m {
+        Move::WasteToFoundation
+        | Move::SlotToFoundation(_)
+        | Move::CellToFoundation(_)
+        | Move::Remove(..) => 0,
+        // Turns over the card below
+        Move::SlotToSlot(from_col, row, _)
+            if row > 0 && row == state.slot_hidden(from_col as usize) =>
+        {
+            1
+        }
+        Move::WasteToSlot(_) | Move::CellToSlot(..) => 2,
+        Move::SlotToSlot(..) => 3,
+        Move::SlotToCell(..) => 4,
+        Move::Draw => 5,
+        Move::FoundationToSlot(..) => 6,
+    }
+}
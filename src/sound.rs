@@ -0,0 +1,48 @@
+use std::io::{self, Write};
+
+#[cfg(feature = "sound")]
+use crate::CONFIG;
+
+// Things happening in the game that are heard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    Deal,
+    Flip, // A face down card is turned up
+    Foundation,
+    Win,
+}
+
+impl Effect {
+    // Notes from 1 for C5 up to 25 for C7, with their lengths in 1/32 s
+    #[cfg(feature = "sound")]
+    fn notes(self) -> &'static [(u8, u8)] {
+        match self {
+            Effect::Deal => &[(1, 2), (5, 2), (8, 2)],
+            Effect::Flip => &[(13, 1)],
+            Effect::Foundation => &[(17, 2), (20, 2)],
+            Effect::Win => &[(13, 4), (17, 4), (20, 4), (25, 8)],
+        }
+    }
+}
+
+// Plays the notes of an effect with the sound sequences of the terminal
+// (DECPS), which xterm and a few others play and the rest leave out. They
+// are played after each other while the game goes on.
+#[cfg(feature = "sound")]
+pub fn play(out: &mut impl Write, effect: Effect) -> io::Result<()> {
+    if !CONFIG.sound || CONFIG.volume == 0 {
+        return Ok(());
+    }
+
+    for (note, length) in effect.notes() {
+        write!(out, "\x1b[{};{length};{note},~", CONFIG.volume)?;
+    }
+
+    Ok(())
+}
+
+// Built without sound, nothing is played
+#[cfg(not(feature = "sound"))]
+pub fn play(_: &mut impl Write, _: Effect) -> io::Result<()> {
+    Ok(())
+}
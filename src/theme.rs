@@ -0,0 +1,234 @@
+use std::{
+    fmt::Display,
+    io::{self, Write},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crossterm::{
+    queue,
+    style::{Color, SetBackgroundColor, StyledContent, Stylize, style},
+    terminal,
+};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+use crate::CONFIG;
+
+// Colors of everything drawn, switched between while playing
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub table: Color, // Behind the cards and the text
+    pub text: Color,
+    pub dim: Color, // Empty places, cards shown face up in Thoughtful
+    pub card: Color, // Background of face up cards
+    pub back: Color, // Face down cards
+    pub red: Color,
+    pub black: Color,
+    pub selected: Color,
+    pub cursor: Color,
+    pub hint: Color,
+    pub illegal: Color, // Flashed where a move is not allowed
+    pub accent: Color,  // Scrub bar of the history
+    pub won: Color,     // Days of the calendar the daily deal was won
+    pub tried: Color,   // And the ones it was only tried
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeName {
+    Dark,    // On the background of the terminal
+    Classic, // Green felt
+    Light,
+}
+
+impl ThemeName {
+    pub const ALL: [ThemeName; 3] =
+        [ThemeName::Dark, ThemeName::Classic, ThemeName::Light];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ThemeName::Dark => "dark",
+            ThemeName::Classic => "classic",
+            ThemeName::Light => "light",
+        }
+    }
+
+    fn theme(self) -> Theme {
+        match self {
+            ThemeName::Dark => Theme {
+                table: Color::Reset,
+                text: Color::Reset,
+                dim: Color::DarkGrey,
+                card: Color::White,
+                back: Color::Blue,
+                red: Color::Red,
+                black: Color::Black,
+                selected: Color::DarkGreen,
+                cursor: Color::DarkCyan,
+                hint: Color::DarkYellow,
+                illegal: Color::DarkRed,
+                accent: Color::Yellow,
+                won: Color::Green,
+                tried: Color::Yellow,
+            },
+            ThemeName::Classic => Theme {
+                table: Color::DarkGreen,
+                text: Color::White,
+                dim: Color::Grey,
+                card: Color::White,
+                back: Color::DarkBlue,
+                red: Color::DarkRed,
+                black: Color::Black,
+                selected: Color::Green,
+                cursor: Color::Cyan,
+                hint: Color::Yellow,
+                illegal: Color::Red,
+                accent: Color::Yellow,
+                won: Color::Green,
+                tried: Color::Yellow,
+            },
+            ThemeName::Light => Theme {
+                table: Color::White,
+                text: Color::Black,
+                dim: Color::DarkGrey,
+                card: Color::Grey,
+                back: Color::DarkBlue,
+                red: Color::DarkRed,
+                black: Color::Black,
+                selected: Color::Green,
+                cursor: Color::Cyan,
+                hint: Color::Yellow,
+                illegal: Color::Red,
+                accent: Color::DarkMagenta,
+                won: Color::DarkGreen,
+                tried: Color::DarkYellow,
+            },
+        }
+    }
+}
+
+// Colors set in the config, used instead of the ones of every theme
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+    pub table: Option<Color>,
+    pub text: Option<Color>,
+    pub dim: Option<Color>,
+    pub card: Option<Color>,
+    pub back: Option<Color>,
+    pub red: Option<Color>,
+    pub black: Option<Color>,
+    pub selected: Option<Color>,
+    pub cursor: Option<Color>,
+    pub hint: Option<Color>,
+    pub illegal: Option<Color>,
+    pub accent: Option<Color>,
+    pub won: Option<Color>,
+    pub tried: Option<Color>,
+}
+
+impl Colors {
+    fn apply(&self, theme: Theme) -> Theme {
+        Theme {
+            table: self.table.unwrap_or(theme.table),
+            text: self.text.unwrap_or(theme.text),
+            dim: self.dim.unwrap_or(theme.dim),
+            card: self.card.unwrap_or(theme.card),
+            back: self.back.unwrap_or(theme.back),
+            red: self.red.unwrap_or(theme.red),
+            black: self.black.unwrap_or(theme.black),
+            selected: self.selected.unwrap_or(theme.selected),
+            cursor: self.cursor.unwrap_or(theme.cursor),
+            hint: self.hint.unwrap_or(theme.hint),
+            illegal: self.illegal.unwrap_or(theme.illegal),
+            accent: self.accent.unwrap_or(theme.accent),
+            won: self.won.unwrap_or(theme.won),
+            tried: self.tried.unwrap_or(theme.tried),
+        }
+    }
+}
+
+// The built in themes in the order of `ThemeName::ALL`, with the colors of
+// the config
+static THEMES: Lazy<Vec<Theme>> = Lazy::new(|| {
+    ThemeName::ALL
+        .into_iter()
+        .map(|name| CONFIG.colors.apply(name.theme()))
+        .collect()
+});
+
+// Used instead of the themes in high contrast mode, with only the colors
+// standing out the most
+static HIGH_CONTRAST: Theme = Theme {
+    table: Color::Black,
+    text: Color::White,
+    dim: Color::White,
+    card: Color::White,
+    back: Color::White,
+    red: Color::Red,
+    black: Color::Black,
+    selected: Color::Black,
+    cursor: Color::Black,
+    hint: Color::Black,
+    illegal: Color::Black,
+    accent: Color::White,
+    won: Color::Green,
+    tried: Color::Yellow,
+};
+
+// Index of the theme in use, starting with the one in the config
+static CURRENT: Lazy<AtomicUsize> = Lazy::new(|| {
+    let i = ThemeName::ALL.iter().position(|&name| name == CONFIG.theme);
+    AtomicUsize::new(i.unwrap())
+});
+
+pub fn current() -> &'static Theme {
+    if CONFIG.high_contrast {
+        return &HIGH_CONTRAST;
+    }
+
+    &THEMES[CURRENT.load(Ordering::Relaxed)]
+}
+
+pub fn current_name() -> ThemeName {
+    ThemeName::ALL[CURRENT.load(Ordering::Relaxed)]
+}
+
+// Switches to the next built in theme, returning it
+pub fn next() -> ThemeName {
+    let i = (CURRENT.load(Ordering::Relaxed) + 1) % ThemeName::ALL.len();
+    CURRENT.store(i, Ordering::Relaxed);
+
+    ThemeName::ALL[i]
+}
+
+// Text on the table. Everything drawn has the color of the table behind it,
+// as styled text leaves the background of the terminal after it.
+pub fn plain<D: Display>(content: D) -> StyledContent<D> {
+    let theme = current();
+    style(content).with(theme.text).on(theme.table)
+}
+
+pub fn dim<D: Display>(content: D) -> StyledContent<D> {
+    let theme = current();
+    style(content).with(theme.dim).on(theme.table)
+}
+
+pub fn won<D: Display>(content: D) -> StyledContent<D> {
+    let theme = current();
+    style(content).with(theme.won).on(theme.table)
+}
+
+pub fn tried<D: Display>(content: D) -> StyledContent<D> {
+    let theme = current();
+    style(content).with(theme.tried).on(theme.table)
+}
+
+// Blanks the whole screen to the color of the table
+pub fn clear(out: &mut impl Write) -> io::Result<()> {
+    queue!(
+        out,
+        SetBackgroundColor(current().table),
+        terminal::Clear(terminal::ClearType::All)
+    )
+}
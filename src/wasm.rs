@@ -0,0 +1,80 @@
+// Bindings for running the engine in a browser, built with
+//
+//   cargo build --lib --target wasm32-unknown-unknown --no-default-features \
+//       --features wasm
+//
+// and wrapped for JavaScript by wasm-bindgen. Moves are passed in the
+// notation of records, like `W>S5`, and the board comes back as plain text
+// or, with the `json` feature, as JSON.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Move, Rules, SolitareState, rules::Game, solver};
+
+#[wasm_bindgen]
+pub struct Solitaire {
+    state: SolitareState,
+}
+
+#[wasm_bindgen]
+impl Solitaire {
+    // Deals a game by its name, like "klondike", with its standard rules.
+    // The same seed always gives the same deal.
+    #[wasm_bindgen(constructor)]
+    pub fn new(game: &str, seed: u64) -> Result<Solitaire, String> {
+        let game = Game::from_name(game)
+            .ok_or_else(|| format!("unknown game: {game}"))?;
+
+        Ok(Self {
+            state: SolitareState::new(seed, Rules::for_game(game)),
+        })
+    }
+
+    // Makes a move given in notation, leaving the board untouched if it is
+    // not allowed
+    #[wasm_bindgen(js_name = applyMove)]
+    pub fn apply_move(&mut self, notation: &str) -> Result<(), String> {
+        let m = Move::from_notation(notation)
+            .ok_or_else(|| format!("invalid move: {notation}"))?;
+
+        self.state
+            .apply_move(m)
+            .map_err(|e| format!("{notation}: {e}"))
+    }
+
+    // Notation of every legal move
+    #[wasm_bindgen(js_name = legalMoves)]
+    pub fn legal_moves(&self) -> Vec<String> {
+        self.state
+            .legal_moves()
+            .into_iter()
+            .map(|m| m.notation(&self.state))
+            .collect()
+    }
+
+    // Notation of a move making progress, if one is found quickly
+    pub fn hint(&self) -> Option<String> {
+        solver::hint(&self.state).map(|m| m.notation(&self.state))
+    }
+
+    #[wasm_bindgen(js_name = isWon)]
+    pub fn is_won(&self) -> bool {
+        self.state.is_won()
+    }
+
+    pub fn score(&self) -> u16 {
+        self.state.score()
+    }
+
+    // The board as plain text, with hidden cards as `##`
+    pub fn render(&self) -> String {
+        self.state.plain_text()
+    }
+
+    // The board in the JSON form of `SolitareState::to_json`
+    #[cfg(feature = "json")]
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> String {
+        self.state.to_json()
+    }
+}
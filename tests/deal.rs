@@ -0,0 +1,26 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use solitare::{Card, Rules, SolitareState, solitare_state};
+
+// Deals are shared and replayed by their seed, so a seed must keep giving
+// the same cards
+#[test]
+fn seed_gives_fixed_deck() {
+    let deck = solitare_state::shuffled_deck(1, Rules::default());
+    let names: Vec<String> =
+        deck[..8].iter().map(|&card| Card(card).name()).collect();
+
+    assert_eq!(names, ["4♥", "7♣", "8♣", "J♠", "6♠", "5♣", "2♣", "A♣"]);
+}
+
+#[test]
+fn seed_deals_like_its_generator() {
+    let rules = Rules::default();
+    let mut rng = ChaCha20Rng::seed_from_u64(7);
+
+    assert_eq!(
+        SolitareState::new(7, rules),
+        SolitareState::new_from(&mut rng, rules)
+    );
+}
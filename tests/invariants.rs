@@ -0,0 +1,52 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+use solitare::{Rules, SolitareState, rules::Game};
+
+// Random games of every kind, checking the board after each move
+#[test]
+fn moves_keep_invariants() {
+    for game in Game::ALL {
+        for seed in 0..5 {
+            let mut state = SolitareState::new(seed, Rules::for_game(game));
+            let mut rng = ChaCha20Rng::seed_from_u64(seed);
+            assert_eq!(state.check_invariants(), Ok(()), "{game:?} deal");
+
+            for _ in 0..200 {
+                let moves = state.legal_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                let m = moves[rng.random_range(0..moves.len())];
+                state.apply_move(m).unwrap();
+
+                assert_eq!(state.check_invariants(), Ok(()), "{game:?} {m:?}");
+            }
+        }
+    }
+}
+
+// A Klondike slot with face-up cards not built on each other, which no
+// game leads to, is turned away when loaded
+#[cfg(feature = "json")]
+#[test]
+fn broken_board_is_rejected() {
+    let state = SolitareState::new(1, Rules::default());
+    let mut json: serde_json::Value =
+        serde_json::from_str(&state.to_json()).unwrap();
+
+    // The top cards of every slot, piled up on the first, which has no
+    // face-down cards
+    let slots = json["slots"].as_array_mut().unwrap();
+    let mut tops = Vec::new();
+    for slot in slots.iter_mut() {
+        tops.append(slot["visible"].as_array_mut().unwrap());
+        if let Some(card) = slot["hidden"].as_array_mut().unwrap().pop() {
+            slot["visible"] = serde_json::json!([card]);
+        }
+    }
+    slots[0]["visible"] = tops.into();
+
+    let loaded = SolitareState::from_json(&json.to_string());
+    assert!(loaded.is_err_and(|e| e.contains("not built")));
+}